@@ -0,0 +1,88 @@
+// align = 16
+struct CameraUniform {
+    druvis_world_space_camera_position: vec4<f32>,
+    druvis_view_matrix: mat4x4<f32>,
+    druvis_projection_matrix: mat4x4<f32>,
+    druvis_projection_params: vec4<f32>,
+};
+
+// align = 16
+struct LightUniform {
+    druvis_light_type: u32,
+    druvis_light_intensity: f32,
+    druvis_light_color: vec4<f32>,
+    druvis_light_position: vec4<f32>,
+    druvis_light_direction: vec4<f32>,
+};
+
+struct PerFrameUniform {
+    camera_uniform: CameraUniform,
+    light_uniform: LightUniform,
+}
+
+@group(0) @binding(0)
+var<uniform> per_frame_uniform: PerFrameUniform;
+
+struct PerObjectUniform {
+    druvis_matrix_m: mat4x4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> per_object_uniform: PerObjectUniform;
+
+// the edge values of the MMD material
+struct ShaderProperties {
+    edge_color: vec4<f32>,
+    // x is the material edge size
+    edge_size: vec4<f32>,
+};
+@group(2) @binding(0)
+var<uniform> shader_properties: ShaderProperties;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// edge width in normalized device units of the screen height at edge size 1
+const EDGE_WIDTH: f32 = 0.003;
+
+@vertex
+fn vs_main(
+    vertex: SkinnedVertexInput,
+    // per vertex edge scale, multiplied with the material edge size
+    @location(12) edge_scale: f32,
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    let model = apply_morphs(vertex, in_vertex_index);
+
+    let projection_matrix: mat4x4<f32> = per_frame_uniform.camera_uniform.druvis_projection_matrix;
+    let view_matrix: mat4x4<f32> = per_frame_uniform.camera_uniform.druvis_view_matrix;
+    let model_matrix: mat4x4<f32> = per_object_uniform.druvis_matrix_m;
+
+    let skinned = skin(model);
+
+    let view_projection = projection_matrix * view_matrix * model_matrix;
+    var clip_position = view_projection * vec4<f32>(skinned.position, 1.0);
+    let clip_normal = (view_projection * vec4<f32>(skinned.normal, 0.0)).xy;
+
+    // extrude in screen space, so the outline keeps its width at any distance
+    let aspect = projection_matrix[1][1] / projection_matrix[0][0];
+    let screen_normal = clip_normal * vec2<f32>(aspect, 1.0);
+    let width = EDGE_WIDTH * shader_properties.edge_size.x * edge_scale;
+    if dot(screen_normal, screen_normal) > 0.0 {
+        let offset = normalize(screen_normal) * vec2<f32>(1.0 / aspect, 1.0) * width;
+        clip_position = vec4<f32>(clip_position.xy + offset * clip_position.w, clip_position.zw);
+    }
+
+    var out: VertexOutput;
+    out.clip_position = clip_position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if shader_properties.edge_color.a <= 0.0 {
+        discard;
+    }
+    return shader_properties.edge_color;
+}
@@ -0,0 +1,158 @@
+// align = 16
+struct CameraUniform {
+    druvis_world_space_camera_position: vec4<f32>,
+    druvis_view_matrix: mat4x4<f32>,
+    druvis_projection_matrix: mat4x4<f32>,
+    druvis_projection_params: vec4<f32>,
+};
+
+// align = 16
+struct LightUniform {
+    druvis_light_type: u32,
+    druvis_light_intensity: f32,
+    druvis_light_color: vec4<f32>,
+    druvis_light_position: vec4<f32>,
+    druvis_light_direction: vec4<f32>,
+};
+
+struct PerFrameUniform {
+    camera_uniform: CameraUniform,
+    light_uniform: LightUniform,
+}
+
+@group(0) @binding(0)
+var<uniform> per_frame_uniform: PerFrameUniform;
+
+struct PerObjectUniform {
+    druvis_matrix_m: mat4x4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> per_object_uniform: PerObjectUniform;
+
+// MMD material values, material morphs write these every frame
+struct ShaderProperties {
+    diffuse: vec4<f32>,
+    // rgb and specular power
+    specular: vec4<f32>,
+    ambient: vec4<f32>,
+    edge_color: vec4<f32>,
+    edge_size: vec4<f32>,
+    texture_mul: vec4<f32>,
+    texture_add: vec4<f32>,
+    sphere_mul: vec4<f32>,
+    sphere_add: vec4<f32>,
+    toon_mul: vec4<f32>,
+    toon_add: vec4<f32>,
+    // x is the sphere mode, 0 none, 1 multiply, 2 add, 3 multiply with the additional uv
+    sphere_mode: vec4<f32>,
+};
+@group(2) @binding(0)
+var<uniform> shader_properties: ShaderProperties;
+
+// albedo texture
+@group(2) @binding(1)
+var albedo_texture: texture_2d<f32>;
+@group(2) @binding(2)
+var albedo_texture_sampler: sampler;
+
+// sphere map, sampled with the view space normal
+@group(2) @binding(3)
+var sphere_texture: texture_2d<f32>;
+@group(2) @binding(4)
+var sphere_texture_sampler: sampler;
+
+// toon ramp, lit at the top and shadowed at the bottom
+@group(2) @binding(5)
+var toon_texture: texture_2d<f32>;
+@group(2) @binding(6)
+var toon_texture_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) world_pos: vec3<f32>,
+    @location(3) view_normal: vec3<f32>,
+    @location(4) additional_uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    vertex: SkinnedVertexInput,
+    @location(11) additional_uv: vec4<f32>,
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    let model = apply_morphs(vertex, in_vertex_index);
+
+    let projection_matrix: mat4x4<f32> = per_frame_uniform.camera_uniform.druvis_projection_matrix;
+    let view_matrix: mat4x4<f32> = per_frame_uniform.camera_uniform.druvis_view_matrix;
+    let model_matrix: mat4x4<f32> = per_object_uniform.druvis_matrix_m;
+
+    let skinned = skin(model);
+
+    let world_pos = model_matrix * vec4<f32>(skinned.position, 1.0);
+    let world_normal = model_matrix * vec4<f32>(skinned.normal, 0.0);
+
+    var out: VertexOutput;
+    out.clip_position = projection_matrix * view_matrix * world_pos;
+    out.tex_coords = model.tex_coords;
+    out.normal = world_normal.xyz;
+    out.world_pos = world_pos.xyz;
+    out.view_normal = (view_matrix * world_normal).xyz;
+    out.additional_uv = additional_uv.xy;
+    return out;
+}
+
+// Fragment shader
+
+const SPHERE_MODE_MULTIPLY: u32 = 1u;
+const SPHERE_MODE_ADD: u32 = 2u;
+const SPHERE_MODE_SUB_TEXTURE: u32 = 3u;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let light = per_frame_uniform.light_uniform;
+    let light_dir = normalize(-light.druvis_light_direction.xyz);
+    let light_color = light.druvis_light_color.rgb * light.druvis_light_intensity;
+    let normal = normalize(in.normal);
+    let props = shader_properties;
+
+    // sample everything before the discard, which makes the control flow non uniform
+    let texture_color = textureSample(albedo_texture, albedo_texture_sampler, in.tex_coords) * props.texture_mul + props.texture_add;
+
+    let view_normal = normalize(in.view_normal);
+    var sphere_uv = vec2<f32>(view_normal.x * 0.5 + 0.5, 0.5 - view_normal.y * 0.5);
+    let sphere_mode = u32(props.sphere_mode.x);
+    if sphere_mode == SPHERE_MODE_SUB_TEXTURE {
+        sphere_uv = in.additional_uv;
+    }
+    let sphere_color = textureSample(sphere_texture, sphere_texture_sampler, sphere_uv).rgb * props.sphere_mul.rgb + props.sphere_add.rgb;
+
+    let toon_v = clamp(0.5 - dot(normal, light_dir) * 0.5, 0.0, 1.0);
+    let toon_color = textureSample(toon_texture, toon_texture_sampler, vec2<f32>(0.0, toon_v)).rgb * props.toon_mul.rgb + props.toon_add.rgb;
+
+    let alpha = props.diffuse.a * texture_color.a;
+    // hidden by a material morph, keep it out of the depth buffer too
+    if alpha <= 0.0 {
+        discard;
+    }
+
+    var color = clamp(props.diffuse.rgb * light_color + props.ambient.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    color *= texture_color.rgb;
+    if sphere_mode == SPHERE_MODE_MULTIPLY || sphere_mode == SPHERE_MODE_SUB_TEXTURE {
+        color *= sphere_color;
+    } else if sphere_mode == SPHERE_MODE_ADD {
+        color += sphere_color;
+    }
+    color *= toon_color;
+
+    // specular power in w, 0 turns it off
+    if props.specular.w > 0.0 {
+        let eye_dir = normalize(per_frame_uniform.camera_uniform.druvis_world_space_camera_position.xyz - in.world_pos);
+        let half_dir = normalize(eye_dir + light_dir);
+        color += pow(max(dot(normal, half_dir), 0.0), props.specular.w) * props.specular.rgb * light_color;
+    }
+
+    return vec4<f32>(color, alpha);
+}
@@ -0,0 +1,156 @@
+// Included by the skinned shaders, declares the skinning bindings at group 3 and the morph and skinning functions.
+
+// skinning matrices, bone world matrix * inverse bind matrix
+@group(3) @binding(0)
+var<storage, read> bone_matrices: array<mat4x4<f32>>;
+
+struct DualQuaternion {
+    real: vec4<f32>,
+    dual: vec4<f32>,
+};
+
+// the same transforms as unit dual quaternions
+@group(3) @binding(1)
+var<storage, read> bone_dual_quaternions: array<DualQuaternion>;
+
+struct MorphOffset {
+    position: vec4<f32>,
+    uv: vec4<f32>,
+    morph_index: u32,
+};
+
+// morph offsets of vertex v are morph_offsets[start..start + count] with (start, count) = morph_ranges[v]
+@group(3) @binding(2)
+var<storage, read> morph_ranges: array<vec2<u32>>;
+@group(3) @binding(3)
+var<storage, read> morph_offsets: array<MorphOffset>;
+@group(3) @binding(4)
+var<storage, read> morph_weights: array<f32>;
+
+const DEFORM_TYPE_SDEF: u32 = 1u;
+const DEFORM_TYPE_QDEF: u32 = 2u;
+
+fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+fn quat_slerp(a: vec4<f32>, b: vec4<f32>, t: f32) -> vec4<f32> {
+    var c = b;
+    var d = dot(a, b);
+    if d < 0.0 {
+        c = -b;
+        d = -d;
+    }
+    if d > 0.9995 {
+        return normalize(mix(a, c, t));
+    }
+    let theta = acos(d);
+    let s = sin(theta);
+    return a * (sin((1.0 - t) * theta) / s) + c * (sin(t * theta) / s);
+}
+
+struct SkinnedVertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+};
+
+// the attributes of `SkinnedModelVertex` every skinned shader reads,
+// shaders take the others as separate vertex inputs
+struct SkinnedVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    // @location(3) tangent: vec3<f32>,
+    // @location(4) bitangent: vec3<f32>,
+    @location(5) bone_indices: vec4<u32>,
+    @location(6) bone_weights: vec4<f32>,
+    @location(7) deform_type: u32,
+    @location(8) sdef_c: vec3<f32>,
+    @location(9) sdef_r0: vec3<f32>,
+    @location(10) sdef_r1: vec3<f32>,
+};
+
+fn apply_morphs(model: SkinnedVertexInput, vertex_index: u32) -> SkinnedVertexInput {
+    var out = model;
+    if vertex_index >= arrayLength(&morph_ranges) {
+        return out;
+    }
+
+    let range = morph_ranges[vertex_index];
+    for (var i = range.x; i < range.x + range.y; i++) {
+        let offset = morph_offsets[i];
+        let weight = morph_weights[offset.morph_index];
+        out.position += offset.position.xyz * weight;
+        out.tex_coords += offset.uv.xy * weight;
+    }
+    return out;
+}
+
+fn skin_linear(model: SkinnedVertexInput) -> SkinnedVertex {
+    let skin_matrix = bone_matrices[model.bone_indices.x] * model.bone_weights.x
+        + bone_matrices[model.bone_indices.y] * model.bone_weights.y
+        + bone_matrices[model.bone_indices.z] * model.bone_weights.z
+        + bone_matrices[model.bone_indices.w] * model.bone_weights.w;
+
+    var out: SkinnedVertex;
+    out.position = (skin_matrix * vec4<f32>(model.position, 1.0)).xyz;
+    out.normal = (skin_matrix * vec4<f32>(model.normal, 0.0)).xyz;
+    return out;
+}
+
+// spherical deformation, rotates around C with the slerped bone rotation
+fn skin_sdef(model: SkinnedVertexInput) -> SkinnedVertex {
+    let w0 = model.bone_weights.x;
+    let w1 = model.bone_weights.y;
+    let q0 = bone_dual_quaternions[model.bone_indices.x].real;
+    let q1 = bone_dual_quaternions[model.bone_indices.y].real;
+    let q = quat_slerp(q0, q1, w1);
+
+    let m0 = bone_matrices[model.bone_indices.x];
+    let m1 = bone_matrices[model.bone_indices.y];
+    let center = (m0 * vec4<f32>(model.sdef_r0, 1.0)).xyz * w0
+        + (m1 * vec4<f32>(model.sdef_r1, 1.0)).xyz * w1;
+
+    var out: SkinnedVertex;
+    out.position = quat_rotate(q, model.position - model.sdef_c) + center;
+    out.normal = quat_rotate(q, model.normal);
+    return out;
+}
+
+// dual quaternion skinning
+fn skin_qdef(model: SkinnedVertexInput) -> SkinnedVertex {
+    let dq0 = bone_dual_quaternions[model.bone_indices.x];
+    let dq1 = bone_dual_quaternions[model.bone_indices.y];
+    let dq2 = bone_dual_quaternions[model.bone_indices.z];
+    let dq3 = bone_dual_quaternions[model.bone_indices.w];
+
+    // keep every rotation in the hemisphere of the first one
+    let w = model.bone_weights * vec4<f32>(
+        1.0,
+        sign(dot(dq0.real, dq1.real) + 0.000001),
+        sign(dot(dq0.real, dq2.real) + 0.000001),
+        sign(dot(dq0.real, dq3.real) + 0.000001),
+    );
+    var real = dq0.real * w.x + dq1.real * w.y + dq2.real * w.z + dq3.real * w.w;
+    var dual = dq0.dual * w.x + dq1.dual * w.y + dq2.dual * w.z + dq3.dual * w.w;
+    let len = length(real);
+    real = real / len;
+    dual = dual / len;
+
+    let translation = 2.0 * (real.w * dual.xyz - dual.w * real.xyz + cross(real.xyz, dual.xyz));
+
+    var out: SkinnedVertex;
+    out.position = quat_rotate(real, model.position) + translation;
+    out.normal = quat_rotate(real, model.normal);
+    return out;
+}
+
+// skins with the deformation the vertex was exported with
+fn skin(model: SkinnedVertexInput) -> SkinnedVertex {
+    if model.deform_type == DEFORM_TYPE_SDEF {
+        return skin_sdef(model);
+    } else if model.deform_type == DEFORM_TYPE_QDEF {
+        return skin_qdef(model);
+    }
+    return skin_linear(model);
+}
@@ -7,3 +7,5 @@ pub const BIND_GROUP_SHADER_PROPERTIES: u32 = 2;
 
 pub const BIND_GROUP_INDEX_PER_FRAME: u32 = 0;
 pub const BIND_GROUP_INDEX_PER_OBJECT: u32 = 1;
+
+pub const BIND_GROUP_INDEX_SKINNING: u32 = 3;
@@ -17,6 +17,8 @@ pub struct PerspectiveCamera {
     pub position: Point3<f32>,
     pub yaw: Rad<f32>,
     pub pitch: Rad<f32>,
+    // rotation around the view direction, positive tilts the up vector to the right
+    pub roll: Rad<f32>,
     pub aspect: f32,
     pub fovy: Rad<f32>,
     pub znear: f32,
@@ -29,6 +31,7 @@ impl Default for PerspectiveCamera {
             position: Point3 { x: 0.0, y: 0.0, z: 0.0 },
             yaw: Rad(0.0),
             pitch: Rad(0.0),
+            roll: Rad(0.0),
             aspect: 16.0 / 9.0,
             fovy: Deg(60.0).into(),
             znear: 0.1,
@@ -56,6 +59,7 @@ impl PerspectiveCamera {
             position: position.into(),
             yaw: yaw.into(),
             pitch: pitch.into(),
+            roll: Rad(0.0),
             aspect,
             fovy: fovy.into(),
             znear,
@@ -80,15 +84,19 @@ impl GetViewProjectionMatrix for PerspectiveCamera {
         let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
 
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(
-                cos_pitch * cos_yaw,
-                sin_pitch,
-                cos_pitch * sin_yaw
-            ).normalize(),
-            Vector3::unit_y(),
-        )
+        let forward = Vector3::new(
+            cos_pitch * cos_yaw,
+            sin_pitch,
+            cos_pitch * sin_yaw
+        ).normalize();
+        if self.roll.0 == 0.0 {
+            return Matrix4::look_to_rh(self.position, forward, Vector3::unit_y());
+        }
+
+        let right = forward.cross(Vector3::unit_y()).normalize();
+        let (sin_roll, cos_roll) = self.roll.0.sin_cos();
+        let up = right.cross(forward) * cos_roll + right * sin_roll;
+        Matrix4::look_to_rh(self.position, forward, up)
     }
 
     fn projection_matrix(&self) -> Matrix4<f32> {
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use super::{SkeletonData, MorphControllerData};
+
+/// Poses a skeleton every frame, e.g. a VMD motion
+pub trait Animation {
+    /// Moves playback on by `dt`, returns true when it jumped instead of playing on,
+    /// after a seek or when a looping motion wraps around
+    fn advance(&mut self, dt: Duration) -> bool;
+
+    /// Writes the local pose and morph weights of the current time, world matrices
+    /// are left to the caller so physics can run between the two deform passes
+    fn pose(&self, skeleton: &mut SkeletonData, morphs: Option<&mut MorphControllerData>);
+}
+
+/// Animation of the `SkeletonData` component of the same game object, played by
+/// `DruvisComponent<SkeletonData>::update` before physics
+#[derive(Default)]
+pub struct AnimatorData {
+    pub animation: Option<Box<dyn Animation>>,
+}
+
+impl AnimatorData {
+    pub fn new(animation: Box<dyn Animation>) -> Self {
+        Self {
+            animation: Some(animation),
+        }
+    }
+}
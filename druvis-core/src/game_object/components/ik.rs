@@ -0,0 +1,160 @@
+use cgmath::{Vector3, Quaternion, Matrix4, InnerSpace, SquareMatrix, Rotation, Rotation3, Rad, Euler, One};
+
+use super::SkeletonData;
+
+pub struct IkLink {
+    pub bone: usize,
+    // euler angle limits in radians as (min, max)
+    pub angle_limit: Option<(Vector3<f32>, Vector3<f32>)>,
+    // accumulated angle of links limited to a single axis
+    pub plane_angle: f32,
+}
+
+impl IkLink {
+    pub fn new(bone: usize, angle_limit: Option<(Vector3<f32>, Vector3<f32>)>) -> Self {
+        Self {
+            bone,
+            angle_limit,
+            plane_angle: 0.0,
+        }
+    }
+
+    // the only axis with a non zero range and its (min, max), e.g. x for knees
+    fn single_axis(&self) -> Option<(usize, f32, f32)> {
+        let (min, max) = self.angle_limit?;
+        let mut axes = (0..3).filter(|&i| min[i] != 0.0 || max[i] != 0.0);
+        let axis = axes.next()?;
+        if axes.next().is_some() {
+            return None;
+        }
+        Some((axis, min[axis], max[axis]))
+    }
+}
+
+/// CCD chain of an IK bone, moves `target` onto the IK bone by rotating `links`
+pub struct IkChain {
+    pub target: usize,
+    pub loop_count: u32,
+    // max rotation of a link in one iteration, in radians
+    pub limit_angle: f32,
+    // ordered from the target towards the root
+    pub links: Vec<IkLink>,
+}
+
+fn world_position(matrix: &Matrix4<f32>) -> Vector3<f32> {
+    matrix.w.truncate()
+}
+
+fn clamp_euler(rotation: Quaternion<f32>, min: Vector3<f32>, max: Vector3<f32>) -> Quaternion<f32> {
+    let euler = Euler::from(rotation);
+    Quaternion::from(Euler::new(
+        Rad(euler.x.0.clamp(min.x, max.x)),
+        Rad(euler.y.0.clamp(min.y, max.y)),
+        Rad(euler.z.0.clamp(min.z, max.z)),
+    ))
+}
+
+impl SkeletonData {
+    // effector and destination as unit directions in the space of `bone`
+    fn local_directions(&self, bone: usize, effector: Vector3<f32>, destination: Vector3<f32>) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let inverse = self.bones[bone].world_matrix.invert()?;
+        let effector = (inverse * effector.extend(1.0)).truncate();
+        let destination = (inverse * destination.extend(1.0)).truncate();
+        if effector.magnitude2() < 1e-10 || destination.magnitude2() < 1e-10 {
+            return None;
+        }
+        Some((effector.normalize(), destination.normalize()))
+    }
+
+    fn solve_link(&mut self, link: &IkLink, effector: Vector3<f32>, destination: Vector3<f32>, limit_angle: f32) {
+        let (e, d) = match self.local_directions(link.bone, effector, destination) {
+            Some(v) => v,
+            None => return,
+        };
+        let angle = e.dot(d).clamp(-1.0, 1.0).acos();
+        let axis = e.cross(d);
+        if angle < 1e-5 || axis.magnitude2() < 1e-10 {
+            return;
+        }
+        let delta = Quaternion::from_axis_angle(axis.normalize(), Rad(angle.min(limit_angle)));
+
+        let bone = &mut self.bones[link.bone];
+        let mut rotation = bone.ik_rotation * bone.rotation * delta;
+        if let Some((min, max)) = link.angle_limit {
+            rotation = clamp_euler(rotation, min, max);
+        }
+        bone.ik_rotation = rotation * bone.rotation.invert();
+    }
+
+    // links limited to one axis only bend in a plane, which keeps knees from twisting
+    fn solve_plane(&mut self, link: &mut IkLink, (axis, min, max): (usize, f32, f32), effector: Vector3<f32>, destination: Vector3<f32>, limit_angle: f32, iteration: u32) {
+        let (e, d) = match self.local_directions(link.bone, effector, destination) {
+            Some(v) => v,
+            None => return,
+        };
+        let angle = e.dot(d).clamp(-1.0, 1.0).acos().min(limit_angle);
+
+        let mut rotate_axis = Vector3::new(0.0, 0.0, 0.0);
+        rotate_axis[axis] = 1.0;
+        let dot1 = (Quaternion::from_axis_angle(rotate_axis, Rad(angle)) * e).dot(d);
+        let dot2 = (Quaternion::from_axis_angle(rotate_axis, Rad(-angle)) * e).dot(d);
+        let mut new_angle = link.plane_angle + if dot1 > dot2 { angle } else { -angle };
+
+        // a straight leg can start bending either way, pick the side the limit allows
+        if iteration == 0 && (new_angle < min || new_angle > max) && -new_angle >= min && -new_angle <= max {
+            new_angle = -new_angle;
+        }
+        new_angle = new_angle.clamp(min, max);
+        link.plane_angle = new_angle;
+
+        let bone = &mut self.bones[link.bone];
+        let rotation = bone.rotation * Quaternion::from_axis_angle(rotate_axis, Rad(new_angle));
+        bone.ik_rotation = rotation * bone.rotation.invert();
+    }
+
+    fn reset_chain(&mut self, chain: &mut IkChain) {
+        for link in chain.links.iter_mut() {
+            link.plane_angle = 0.0;
+            self.bones[link.bone].ik_rotation = Quaternion::one();
+        }
+        if let Some(root) = chain.links.last() {
+            self.update_subtree(root.bone);
+        }
+    }
+
+    /// Takes back the rotations of a disabled chain
+    pub fn clear_ik(&mut self, ik_bone: usize) {
+        if let Some(mut chain) = self.bones[ik_bone].ik.take() {
+            self.reset_chain(&mut chain);
+            self.bones[ik_bone].ik = Some(chain);
+        }
+    }
+
+    /// Runs the chain of `ik_bone`, its world matrix must already be up to date
+    pub fn solve_ik(&mut self, ik_bone: usize) {
+        let mut chain = match self.bones[ik_bone].ik.take() {
+            Some(chain) => chain,
+            None => return,
+        };
+        self.reset_chain(&mut chain);
+
+        let destination = world_position(&self.bones[ik_bone].world_matrix);
+        for iteration in 0..chain.loop_count {
+            let effector = world_position(&self.bones[chain.target].world_matrix);
+            if (effector - destination).magnitude() < 1e-3 {
+                break;
+            }
+
+            for link in chain.links.iter_mut() {
+                let effector = world_position(&self.bones[chain.target].world_matrix);
+                match link.single_axis() {
+                    Some(plane) => self.solve_plane(link, plane, effector, destination, chain.limit_angle, iteration),
+                    None => self.solve_link(link, effector, destination, chain.limit_angle),
+                }
+                self.update_subtree(link.bone);
+            }
+        }
+
+        self.bones[ik_bone].ik = Some(chain);
+    }
+}
@@ -1,17 +1,26 @@
 use std::{rc::Rc, cell::RefCell};
 
-use crate::{material::material::DruvisMaterial, mesh::mesh::DruvisMesh, rendering::render_state::RenderState, game_object::{DruvisComponent, TransformComponentData}};
+use crate::{material::material::DruvisMaterial, mesh::mesh::DruvisMesh, rendering::{render_state::RenderState, skinning::SkinningBindState}, game_object::{DruvisComponent, TransformComponentData}};
+
+use super::{SkeletonData, MorphControllerData};
 
 pub struct MeshRendererData {
     pub mesh: Option<Rc<RefCell<DruvisMesh>>>,
     pub materials: Vec<Rc<RefCell<DruvisMaterial>>>,
+    // outline material per submesh, drawn after every submesh when present
+    pub edge_materials: Vec<Option<Rc<RefCell<DruvisMaterial>>>>,
+    // bone matrices and morphs for skinned meshes, filled from the `SkeletonData`
+    // and `MorphControllerData` components
+    pub skinning: Option<SkinningBindState>,
 }
 
 impl Default for MeshRendererData {
     fn default() -> Self {
         Self {
             materials: Vec::new(),
+            edge_materials: Vec::new(),
             mesh: None,
+            skinning: None,
         }
     }
 }
@@ -32,15 +41,30 @@ impl DruvisComponent<MeshRendererData> {
             panic!("Cannot draw mesh renderer without transform component");
         }
 
+        let morph_controller = self.get_component::<MorphControllerData>();
+        if let Some(skinning) = self.data.skinning.as_ref() {
+            if let Some(skeleton) = self.get_component::<SkeletonData>() {
+                skinning.write_matrices(queue, &skeleton.borrow().data.skinning_matrices);
+            }
+            if let Some(morph_controller) = morph_controller.as_ref() {
+                skinning.write_morph_weights(queue, &morph_controller.borrow().data.effective_weights());
+            }
+        }
+        if let Some(morph_controller) = morph_controller.as_ref() {
+            morph_controller.borrow().data.apply_material_morphs(&self.data.materials);
+            morph_controller.borrow().data.apply_optional_material_morphs(&self.data.edge_materials);
+        }
+
         let mesh = self.data.mesh.as_ref().unwrap().clone();
         if mesh.borrow().get_submesh_count() == 1 {
-            render_state.draw_mesh(
+            render_state.draw_skinned_mesh(
                 device,
                 queue,
                 &mesh.borrow(),
                 &self.data.materials[0].borrow(),
                 transform.as_ref().unwrap().borrow().data.get_model_matrix(),
-                None
+                None,
+                self.data.skinning.as_ref()
             )
         } else {
             let submesh_count = mesh.borrow().get_submesh_count();
@@ -56,13 +80,30 @@ impl DruvisComponent<MeshRendererData> {
             // )
 
             for i in 0..submesh_count {
-                render_state.draw_mesh(
+                render_state.draw_skinned_mesh(
                     device,
                     queue,
                     &mesh.borrow(),
                     &self.data.materials[i].borrow(),
                     transform_matrix,
-                    Some(i)
+                    Some(i),
+                    self.data.skinning.as_ref()
+                )
+            }
+        }
+
+        let submesh_count = mesh.borrow().get_submesh_count();
+        let transform_matrix = transform.as_ref().unwrap().borrow().data.get_model_matrix();
+        for (i, edge_material) in self.data.edge_materials.iter().enumerate().take(submesh_count) {
+            if let Some(edge_material) = edge_material {
+                render_state.draw_skinned_mesh(
+                    device,
+                    queue,
+                    &mesh.borrow(),
+                    &edge_material.borrow(),
+                    transform_matrix,
+                    if submesh_count == 1 { None } else { Some(i) },
+                    self.data.skinning.as_ref()
                 )
             }
         }
@@ -1,3 +1,13 @@
 mod mesh_renderer;
+mod skeleton;
+mod ik;
+mod morph_controller;
+mod physics;
+mod animator;
 
 pub use mesh_renderer::MeshRendererData;
+pub use skeleton::{SkeletonData, Bone, BoneInherit};
+pub use ik::{IkChain, IkLink};
+pub use morph_controller::{MorphControllerData, MaterialMorphOffset, MaterialMorphMethod};
+pub use physics::{PhysicsData, PhysicsMode, RigidBodyBinding};
+pub use animator::{Animation, AnimatorData};
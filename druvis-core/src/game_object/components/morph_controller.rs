@@ -0,0 +1,157 @@
+use std::{rc::Rc, cell::RefCell};
+
+use cgmath::Vector4;
+
+use crate::{game_object::DruvisComponent, material::material::DruvisMaterial, shader::shader_property::ShaderPropertyValue};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaterialMorphMethod {
+    // value * lerp(1, offset, weight)
+    Multiply,
+    // value + offset * weight
+    Add,
+}
+
+#[derive(Clone, Debug)]
+pub struct MaterialMorphOffset {
+    // None applies to every material
+    pub material: Option<usize>,
+    pub property: String,
+    pub method: MaterialMorphMethod,
+    pub value: Vector4<f32>,
+}
+
+pub struct MorphControllerData {
+    pub names: Vec<String>,
+    pub weights: Vec<f32>,
+    // group morphs as (morph index, ratio) lists, empty for other morphs
+    pub groups: Vec<Vec<(usize, f32)>>,
+    // per morph, empty for morphs that do not touch materials
+    pub material_offsets: Vec<Vec<MaterialMorphOffset>>,
+    // unmorphed vec4 properties per material, only these are written back
+    pub material_base: Vec<Vec<(String, Vector4<f32>)>>,
+}
+
+impl Default for MorphControllerData {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl MorphControllerData {
+    pub fn new(names: Vec<String>) -> Self {
+        let count = names.len();
+        Self {
+            names,
+            weights: vec![0.0; count],
+            groups: vec![Vec::new(); count],
+            material_offsets: vec![Vec::new(); count],
+            material_base: Vec::new(),
+        }
+    }
+
+    pub fn find_morph(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// Weights with group morphs spread onto their members
+    pub fn effective_weights(&self) -> Vec<f32> {
+        let mut result = self.weights.clone();
+        for (weight, group) in self.weights.iter().zip(self.groups.iter()) {
+            if *weight == 0.0 {
+                continue;
+            }
+            for &(morph, ratio) in group.iter() {
+                if let Some(w) = result.get_mut(morph) {
+                    *w += weight * ratio;
+                }
+            }
+        }
+        result
+    }
+
+    /// Base properties of `material` with every active material morph applied
+    pub fn material_properties(&self, material: usize, weights: &[f32]) -> Vec<(String, Vector4<f32>)> {
+        let mut properties = match self.material_base.get(material) {
+            Some(base) => base.clone(),
+            None => return Vec::new(),
+        };
+
+        for (offsets, &weight) in self.material_offsets.iter().zip(weights.iter()) {
+            if weight == 0.0 {
+                continue;
+            }
+            for offset in offsets.iter().filter(|o| o.material.map(|m| m == material).unwrap_or(true)) {
+                let value = match properties.iter_mut().find(|(name, _)| *name == offset.property) {
+                    Some((_, value)) => value,
+                    None => continue,
+                };
+                match offset.method {
+                    MaterialMorphMethod::Multiply => {
+                        let factor = Vector4::new(1.0, 1.0, 1.0, 1.0) + (offset.value - Vector4::new(1.0, 1.0, 1.0, 1.0)) * weight;
+                        *value = Vector4::new(value.x * factor.x, value.y * factor.y, value.z * factor.z, value.w * factor.w);
+                    },
+                    MaterialMorphMethod::Add => *value += offset.value * weight,
+                }
+            }
+        }
+
+        properties
+    }
+
+    /// Writes the morphed properties into `materials`, in the order of `material_base`
+    pub fn apply_material_morphs(&self, materials: &[Rc<RefCell<DruvisMaterial>>]) {
+        if self.material_base.is_empty() {
+            return;
+        }
+
+        let weights = self.effective_weights();
+        for (i, material) in materials.iter().enumerate() {
+            self.write_material_properties(i, &weights, &mut material.borrow_mut());
+        }
+    }
+
+    /// Same as `apply_material_morphs` for materials that only some submeshes have,
+    /// e.g. outlines, each one takes the properties its shader declares
+    pub fn apply_optional_material_morphs(&self, materials: &[Option<Rc<RefCell<DruvisMaterial>>>]) {
+        if self.material_base.is_empty() {
+            return;
+        }
+
+        let weights = self.effective_weights();
+        for (i, material) in materials.iter().enumerate() {
+            if let Some(material) = material {
+                self.write_material_properties(i, &weights, &mut material.borrow_mut());
+            }
+        }
+    }
+
+    fn write_material_properties(&self, index: usize, weights: &[f32], material: &mut DruvisMaterial) {
+        for (name, value) in self.material_properties(index, weights) {
+            if material.has_property(&name) {
+                material.set_property(&name, ShaderPropertyValue::Vec4(value));
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.weights.iter_mut().for_each(|w| *w = 0.0);
+    }
+}
+
+impl DruvisComponent<MorphControllerData> {
+    /// Returns false if the model has no morph called `name`
+    pub fn set_weight(&mut self, name: &str, weight: f32) -> bool {
+        match self.data.find_morph(name) {
+            Some(index) => {
+                self.data.weights[index] = weight;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn get_weight(&self, name: &str) -> Option<f32> {
+        self.data.find_morph(name).map(|i| self.data.weights[i])
+    }
+}
@@ -0,0 +1,120 @@
+use cgmath::{Vector3, Vector4, Quaternion, Matrix3, Matrix4, SquareMatrix, InnerSpace};
+use druvis_physics::PhysicsWorld;
+
+use super::SkeletonData;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhysicsMode {
+    // the body is moved by its bone
+    FollowBone,
+    // the bone is moved by the body
+    Physics,
+    // like `Physics`, but the bone keeps its animated position
+    PhysicsWithBonePosition,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RigidBodyBinding {
+    pub bone: Option<usize>,
+    pub mode: PhysicsMode,
+    // body transform in the bind pose, moved along by the skinning matrix of the bone
+    pub offset: Matrix4<f32>,
+}
+
+/// Rigid bodies of a model, driven by and driving the bones of the `SkeletonData` component
+pub struct PhysicsData {
+    pub world: PhysicsWorld,
+    // one per body in `world.bodies`
+    pub bindings: Vec<RigidBodyBinding>,
+    pub enabled: bool,
+}
+
+impl Default for PhysicsData {
+    fn default() -> Self {
+        Self::new(PhysicsWorld::new(), Vec::new())
+    }
+}
+
+fn bone_transform(skeleton: &SkeletonData, bone: usize) -> Matrix4<f32> {
+    skeleton.bones[bone].world_matrix * Matrix4::from_translation(-skeleton.bones[bone].bind_position)
+}
+
+fn decompose(matrix: Matrix4<f32>) -> (Vector3<f32>, Quaternion<f32>) {
+    let rotation = Matrix3::from_cols(matrix.x.truncate(), matrix.y.truncate(), matrix.z.truncate());
+    (matrix.w.truncate(), Quaternion::from(rotation).normalize())
+}
+
+impl PhysicsData {
+    pub fn new(world: PhysicsWorld, bindings: Vec<RigidBodyBinding>) -> Self {
+        Self {
+            world,
+            bindings,
+            enabled: true,
+        }
+    }
+
+    fn body_target(&self, skeleton: &SkeletonData, index: usize) -> Option<Matrix4<f32>> {
+        let binding = &self.bindings[index];
+        let bone = binding.bone.filter(|&b| b < skeleton.bones.len())?;
+        Some(bone_transform(skeleton, bone) * binding.offset)
+    }
+
+    /// Moves every body to where its bone currently puts it and stops all motion,
+    /// used after loading and when a motion jumps
+    pub fn reset(&mut self, skeleton: &SkeletonData) {
+        for i in 0..self.bindings.len() {
+            if let Some(target) = self.body_target(skeleton, i) {
+                let (position, rotation) = decompose(target);
+                self.world.bodies[i].teleport(position, rotation);
+            }
+        }
+    }
+
+    /// Steps the simulation by `dt` seconds from the posed skeleton and writes the
+    /// simulated bones back, then updates the bones deformed after physics
+    pub fn update(&mut self, skeleton: &mut SkeletonData, dt: f32) {
+        if !self.enabled {
+            skeleton.update_world_matrices_in(true);
+            return;
+        }
+
+        for i in 0..self.bindings.len() {
+            if self.bindings[i].mode != PhysicsMode::FollowBone {
+                continue;
+            }
+            if let Some(target) = self.body_target(skeleton, i) {
+                let (position, rotation) = decompose(target);
+                self.world.set_kinematic_target(i, position, rotation);
+            }
+        }
+        self.world.step(dt);
+
+        let mut bodies = vec![None; skeleton.bones.len()];
+        for (i, binding) in self.bindings.iter().enumerate() {
+            match binding.bone {
+                Some(bone) if bone < bodies.len() && binding.mode != PhysicsMode::FollowBone => bodies[bone] = Some(i),
+                _ => {},
+            }
+        }
+        // parents first, so writing a bone does not undo its simulated children
+        for i in 0..skeleton.deform_order.len() {
+            let bone = skeleton.deform_order[i];
+            let body = match bodies[bone] {
+                Some(body) => body,
+                None => continue,
+            };
+            let binding = &self.bindings[body];
+            let inverse_offset = match binding.offset.invert() {
+                Some(inverse) => inverse,
+                None => continue,
+            };
+            let mut world = self.world.bodies[body].transform() * inverse_offset * Matrix4::from_translation(skeleton.bones[bone].bind_position);
+            if binding.mode == PhysicsMode::PhysicsWithBonePosition {
+                let position = skeleton.bones[bone].world_matrix.w;
+                world.w = Vector4::new(position.x, position.y, position.z, 1.0);
+            }
+            skeleton.set_world_matrix(bone, world);
+        }
+        skeleton.update_world_matrices_in(true);
+    }
+}
@@ -0,0 +1,301 @@
+use std::time::Duration;
+
+use cgmath::{Vector3, Quaternion, Matrix4, One, SquareMatrix, Zero, InnerSpace, Rotation3, Rad};
+
+use crate::game_object::DruvisComponent;
+
+use super::{IkChain, AnimatorData, MorphControllerData, PhysicsData};
+
+/// Grants a fraction of another bone's rotation and/or translation, MMD's append transform
+#[derive(Clone, Copy, Debug)]
+pub struct BoneInherit {
+    pub parent: usize,
+    // may be negative, e.g. -1 for shoulder cancel bones
+    pub ratio: f32,
+    pub rotation: bool,
+    pub translation: bool,
+    // take the animated value of the parent instead of what it inherited itself
+    pub local: bool,
+}
+
+// rotation by `t` times the angle of `q`, works for negative and > 1 ratios unlike slerp
+fn scale_rotation(q: Quaternion<f32>, t: f32) -> Quaternion<f32> {
+    let q = if q.s < 0.0 { -q } else { q };
+    let sin_half = q.v.magnitude();
+    if sin_half < 1e-6 {
+        return Quaternion::one();
+    }
+    let angle = 2.0 * sin_half.atan2(q.s);
+    Quaternion::from_axis_angle(q.v / sin_half, Rad(angle * t))
+}
+
+pub struct Bone {
+    pub name: String,
+    pub parent: Option<usize>,
+    // model space position in the bind pose
+    pub bind_position: Vector3<f32>,
+    pub deform_layer: i32,
+    pub deform_after_physics: bool,
+
+    // local pose relative to the bind pose, this is what animation writes
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    // extra rotation applied on top of `rotation` by IK chains
+    pub ik_rotation: Quaternion<f32>,
+
+    pub ik: Option<IkChain>,
+    // IK can be switched off per bone by motions
+    pub ik_enabled: bool,
+
+    pub inherit: Option<BoneInherit>,
+    // granted by `inherit`, updated before the world matrix
+    pub inherit_rotation: Quaternion<f32>,
+    pub inherit_translation: Vector3<f32>,
+
+    // model space transform, updated by `SkeletonData::update_world_matrices`
+    pub world_matrix: Matrix4<f32>,
+}
+
+impl Bone {
+    pub fn new(name: &str, parent: Option<usize>, bind_position: Vector3<f32>) -> Self {
+        Self {
+            name: String::from(name),
+            parent,
+            bind_position,
+            deform_layer: 0,
+            deform_after_physics: false,
+            translation: Vector3::zero(),
+            rotation: Quaternion::one(),
+            ik_rotation: Quaternion::one(),
+            ik: None,
+            ik_enabled: true,
+            inherit: None,
+            inherit_rotation: Quaternion::one(),
+            inherit_translation: Vector3::zero(),
+            world_matrix: Matrix4::from_translation(bind_position),
+        }
+    }
+
+    /// Translation of the bone relative to its parent in the bind pose
+    pub fn bind_offset(&self, bones: &[Bone]) -> Vector3<f32> {
+        match self.parent {
+            Some(parent) => self.bind_position - bones[parent].bind_position,
+            None => self.bind_position,
+        }
+    }
+}
+
+pub struct SkeletonData {
+    pub bones: Vec<Bone>,
+    // bone indices sorted by after physics flag, then deform layer, then index
+    pub deform_order: Vec<usize>,
+    // world matrix times inverse bind matrix, ready for skinning
+    pub skinning_matrices: Vec<Matrix4<f32>>,
+    pub children: Vec<Vec<usize>>,
+}
+
+impl Default for SkeletonData {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl SkeletonData {
+    pub fn new(bones: Vec<Bone>) -> Self {
+        let mut bones = bones;
+        // a bone can not be its own ancestor through an invalid index
+        let bone_count = bones.len();
+        for (i, bone) in bones.iter_mut().enumerate() {
+            if bone.parent.map(|p| p >= bone_count || p == i).unwrap_or(false) {
+                bone.parent = None;
+            }
+        }
+        // break parent cycles, a chain longer than the bone count must loop
+        for i in 0..bone_count {
+            let mut current = bones[i].parent;
+            let mut steps = 0;
+            while let Some(p) = current {
+                steps += 1;
+                if steps > bone_count {
+                    bones[i].parent = None;
+                    break;
+                }
+                current = bones[p].parent;
+            }
+        }
+        // drop IK chains and grants that point at missing bones
+        for (i, bone) in bones.iter_mut().enumerate() {
+            if bone.inherit.map(|h| h.parent >= bone_count || h.parent == i).unwrap_or(false) {
+                bone.inherit = None;
+            }
+            if let Some(chain) = bone.ik.as_mut() {
+                chain.links.retain(|l| l.bone < bone_count);
+                if chain.target >= bone_count {
+                    bone.ik = None;
+                }
+            }
+        }
+
+        let mut children = vec![Vec::new(); bone_count];
+        for (i, bone) in bones.iter().enumerate() {
+            if let Some(parent) = bone.parent {
+                children[parent].push(i);
+            }
+        }
+
+        let mut deform_order = (0..bone_count).collect::<Vec<_>>();
+        // stable, so bones in the same layer keep their file order
+        deform_order.sort_by_key(|&i| (bones[i].deform_after_physics, bones[i].deform_layer));
+
+        let mut result = Self {
+            bones,
+            deform_order,
+            skinning_matrices: vec![Matrix4::identity(); bone_count],
+            children,
+        };
+        result.update_world_matrices();
+        result
+    }
+
+    pub fn find_bone(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|b| b.name == name)
+    }
+
+    pub fn set_bone_transform(&mut self, index: usize, translation: Vector3<f32>, rotation: Quaternion<f32>) {
+        let bone = &mut self.bones[index];
+        bone.translation = translation;
+        bone.rotation = rotation;
+    }
+
+    /// Puts every bone back into the bind pose
+    pub fn reset_pose(&mut self) {
+        for bone in self.bones.iter_mut() {
+            bone.translation = Vector3::zero();
+            bone.rotation = Quaternion::one();
+            bone.ik_rotation = Quaternion::one();
+            bone.inherit_rotation = Quaternion::one();
+            bone.inherit_translation = Vector3::zero();
+        }
+    }
+
+    fn update_inherit(&mut self, index: usize) {
+        let inherit = match self.bones[index].inherit {
+            Some(inherit) => inherit,
+            None => return,
+        };
+        let source = &self.bones[inherit.parent];
+        let source_inherit = source.inherit.filter(|_| !inherit.local);
+
+        let rotation = match source_inherit {
+            Some(h) if h.rotation => source.inherit_rotation,
+            _ => source.rotation,
+        };
+        let rotation = scale_rotation(source.ik_rotation * rotation, inherit.ratio);
+        let translation = match source_inherit {
+            Some(h) if h.translation => source.inherit_translation,
+            _ => source.translation,
+        };
+        let translation = translation * inherit.ratio;
+
+        let bone = &mut self.bones[index];
+        if inherit.rotation {
+            bone.inherit_rotation = rotation;
+        }
+        if inherit.translation {
+            bone.inherit_translation = translation;
+        }
+    }
+
+    fn update_bone(&mut self, index: usize) {
+        self.update_inherit(index);
+
+        let bone = &self.bones[index];
+        let local = Matrix4::from_translation(bone.bind_offset(&self.bones) + bone.translation + bone.inherit_translation)
+            * Matrix4::from(bone.ik_rotation * bone.rotation * bone.inherit_rotation);
+        let world = match bone.parent {
+            Some(parent) => self.bones[parent].world_matrix * local,
+            None => local,
+        };
+
+        self.bones[index].world_matrix = world;
+        self.skinning_matrices[index] = world * Matrix4::from_translation(-self.bones[index].bind_position);
+    }
+
+    /// Updates `index` and every bone below it
+    pub fn update_subtree(&mut self, index: usize) {
+        let mut stack = vec![index];
+        while let Some(i) = stack.pop() {
+            self.update_bone(i);
+            stack.extend_from_slice(&self.children[i]);
+        }
+    }
+
+    /// Overrides the world matrix of `index`, e.g. with a simulated rigid body,
+    /// and moves every bone below it along
+    pub fn set_world_matrix(&mut self, index: usize, world: Matrix4<f32>) {
+        self.bones[index].world_matrix = world;
+        self.skinning_matrices[index] = world * Matrix4::from_translation(-self.bones[index].bind_position);
+        for i in 0..self.children[index].len() {
+            let child = self.children[index][i];
+            self.update_subtree(child);
+        }
+    }
+
+    /// Updates the bones deformed before or after physics in deform order,
+    /// solving IK chains when their IK bone is reached
+    pub fn update_world_matrices_in(&mut self, after_physics: bool) {
+        for i in 0..self.deform_order.len() {
+            let index = self.deform_order[i];
+            let bone = &self.bones[index];
+            if bone.deform_after_physics != after_physics {
+                continue;
+            }
+
+            let (has_ik, ik_enabled) = (bone.ik.is_some(), bone.ik_enabled);
+            self.update_bone(index);
+            if has_ik && ik_enabled {
+                self.solve_ik(index);
+            } else if has_ik {
+                self.clear_ik(index);
+            }
+        }
+    }
+
+    pub fn update_world_matrices(&mut self) {
+        self.update_world_matrices_in(false);
+        self.update_world_matrices_in(true);
+    }
+}
+
+impl DruvisComponent<SkeletonData> {
+    /// One frame of the model in a fixed order: the animation poses the bones, the bones
+    /// deformed before physics are updated, physics steps and writes the simulated bones,
+    /// then the bones deformed after physics follow them
+    pub fn update(&mut self, dt: Duration) {
+        let animator = self.get_component::<AnimatorData>();
+        let morphs = self.get_component::<MorphControllerData>();
+        let physics = self.get_component::<PhysicsData>();
+
+        let mut jumped = false;
+        if let Some(animator) = animator {
+            if let Some(animation) = animator.borrow_mut().data.animation.as_mut() {
+                jumped = animation.advance(dt);
+                let mut morphs = morphs.as_ref().map(|m| m.borrow_mut());
+                animation.pose(&mut self.data, morphs.as_mut().map(|m| &mut m.data));
+            }
+        }
+
+        self.data.update_world_matrices_in(false);
+        match physics {
+            Some(physics) => {
+                let physics = &mut physics.borrow_mut().data;
+                // bodies would be dragged across the scene to the new pose
+                if jumped {
+                    physics.reset(&self.data);
+                }
+                physics.update(&mut self.data, dt.as_secs_f32());
+            },
+            None => self.data.update_world_matrices_in(true),
+        }
+    }
+}
@@ -7,7 +7,11 @@ pub use game_object::DruvisGameObject;
 pub use component::DruvisComponent;
 pub use transform::TransformComponentData;
 
-use self::components::MeshRendererData;
+use self::components::{MeshRendererData, SkeletonData, MorphControllerData, PhysicsData, AnimatorData};
 
 pub type MeshRenderer = DruvisComponent<MeshRendererData>;
-pub type Transform = DruvisComponent<TransformComponentData>;
\ No newline at end of file
+pub type Transform = DruvisComponent<TransformComponentData>;
+pub type Skeleton = DruvisComponent<SkeletonData>;
+pub type MorphController = DruvisComponent<MorphControllerData>;
+pub type Physics = DruvisComponent<PhysicsData>;
+pub type Animator = DruvisComponent<AnimatorData>;
\ No newline at end of file
@@ -2,7 +2,7 @@ use std::path::Path;
 
 use winit::{window::{Window, WindowBuilder}, event_loop::{EventLoop, ControlFlow}, event::{Event, WindowEvent, KeyboardInput, MouseButton, ElementState, VirtualKeyCode, DeviceEvent}, dpi::PhysicalSize};
 
-use crate::{camera::{perspective_camera::{PerspectiveCamera, SimplePerspectiveCameraController}, camera::{GetCameraUniform, CameraController}, camera_uniform::CameraUniform}, render_pipeline::{simple_render_pipeline::SimpleRenderPipeline, render_pipeline::{DruvisRenderPipeline}}, scene::scene::DruvisScene, binding::data_binding_state::DataBindingState, common::transformation_uniform::TransformationUniform, shader::shader_manager::ShaderManager, rendering::{render_state::RenderState, uniform::{PerFrameUniform, PerObjectUniform}}, material::material_manager::MaterialManager};
+use crate::{camera::{perspective_camera::{PerspectiveCamera, SimplePerspectiveCameraController}, camera::{GetCameraUniform, CameraController}, camera_uniform::CameraUniform}, render_pipeline::{simple_render_pipeline::SimpleRenderPipeline, render_pipeline::{DruvisRenderPipeline}}, scene::scene::DruvisScene, binding::data_binding_state::DataBindingState, common::transformation_uniform::TransformationUniform, shader::shader_manager::ShaderManager, rendering::{render_state::RenderState, uniform::{PerFrameUniform, PerObjectUniform}}, material::material_manager::MaterialManager, game_object::components::SkeletonData};
 
 pub struct DruvisInstance {
     // device and surface
@@ -24,7 +24,8 @@ pub struct DruvisInstance {
     pub scene: Option<DruvisScene>,
 
     // camera control
-    pub camera_controller: SimplePerspectiveCameraController,
+    // a VMD camera motion can replace the mouse controller
+    pub camera_controller: Box<dyn CameraController<PerspectiveCamera>>,
     pub mouse_pressed: bool,
 
     // resource managers
@@ -79,7 +80,14 @@ impl DruvisInstance {
     }
 
     pub fn update(&mut self, delta_time: instant::Duration) {
-        self.camera_controller.update_camera(&mut self.camera, delta_time)
+        self.camera_controller.update_camera(&mut self.camera, delta_time);
+
+        // animation and physics of every model
+        if let Some(scene) = self.scene.as_ref() {
+            for skeleton in scene.get_components::<SkeletonData>() {
+                skeleton.borrow_mut().update(delta_time);
+            }
+        }
     }
 
     pub fn render(&mut self, pipeline: &SimpleRenderPipeline) -> Result<(), wgpu::SurfaceError> {
@@ -151,7 +159,7 @@ impl DruvisInstance {
             0.1,
             100.0
         );
-        let camera_controller = SimplePerspectiveCameraController::new(4.0, 1.0);
+        let camera_controller = Box::new(SimplePerspectiveCameraController::new(4.0, 1.0));
 
         // let render_pipeline = SimpleRenderPipeline;
 
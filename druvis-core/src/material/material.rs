@@ -16,6 +16,15 @@ pub struct DruvisMaterial {
     pub texture_properties: HashMap<String, Rc<DruvisTextureAndSampler>>,
     // pub textures: Vec<Rc<DruvisTextureAndSampler>>,
     pub bind_state: MaterialBindState,
+
+    // start out as the shader's, None draws both sides
+    pub cull_mode: Option<wgpu::Face>,
+    pub topology: wgpu::PrimitiveTopology,
+    // for shadow passes, which are not rendered yet, casting is into the shadow map
+    pub cast_shadow: bool,
+    pub receive_shadow: bool,
+    // flattened onto the ground plane, independent of the shadow map
+    pub ground_shadow: bool,
 }
 
 impl DruvisMaterial {
@@ -42,6 +51,10 @@ impl DruvisMaterial {
         Some(mat)
     }
     
+    pub fn has_property(&self, key: &str) -> bool {
+        self.shader.shader_value_layout.iter().any(|item| item.name == key)
+    }
+
     pub fn set_property(&mut self, key: &str, value: ShaderPropertyValue) {
         let mut flag = false;
         for item in self.shader.shader_value_layout.iter() {
@@ -69,7 +82,7 @@ impl DruvisMaterial {
         color_format: wgpu::TextureFormat,
         depth_format: Option<wgpu::TextureFormat>,
     ) where 'a: 'b {
-        self.shader.use_shader(device, render_pass, color_format, depth_format);
+        self.shader.use_shader(device, render_pass, color_format, depth_format, self.cull_mode, self.topology);
         render_pass.set_bind_group(BIND_GROUP_SHADER_PROPERTIES, &self.bind_state.shader_properties_bind_group, &[]);
     }
  
@@ -125,6 +138,11 @@ impl DruvisMaterial {
                 )
             },
             name: String::from(name),
+            cull_mode: shader.cull_mode,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cast_shadow: true,
+            receive_shadow: true,
+            ground_shadow: true,
             shader,
             properties: HashMap::new(),
             // textures: textures.iter().cloned().collect(),
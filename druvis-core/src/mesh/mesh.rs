@@ -1,7 +1,7 @@
 use cgmath::Point3;
 use wgpu::util::DeviceExt;
 
-use crate::{vertex::vertex::ModelVertex, common::util_traits::AsBytes, utils};
+use crate::{vertex::vertex::{ModelVertex, Vertex}, common::util_traits::AsBytes, utils};
 
 pub struct DruvisMesh {
     pub vertex_buffer: wgpu::Buffer,
@@ -37,17 +37,17 @@ impl DruvisMesh {
         // self.index_buffer.slice(..)
     }
 
-    pub fn new(
+    pub fn new<V: Vertex>(
         device: &wgpu::Device,
         label: &str,
-        vertices: Vec<ModelVertex>,
+        vertices: Vec<V>,
         indices: Vec<u32>,
         submeshes: Vec<(u64, u64)>
     ) -> Self {
         let vertex_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some((String::from(label) + "_vertex_buffer").as_str()),
-                contents: utils::reinterpret_slice::<ModelVertex, u8>(&vertices),
+                contents: utils::reinterpret_slice::<V, u8>(&vertices),
                 usage: wgpu::BufferUsages::VERTEX
             }
         );
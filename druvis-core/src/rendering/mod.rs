@@ -1,3 +1,5 @@
 pub mod rendering;
 pub mod render_state;
 pub mod uniform;
+pub mod skinning;
+pub mod morph;
@@ -0,0 +1,62 @@
+/// One vertex offset of one morph, laid out for the skinned shaders
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MorphOffset {
+    // xyz used
+    pub position: [f32; 4],
+    // xy are added to the texture coordinates
+    pub uv: [f32; 4],
+    pub morph_index: u32,
+    _padding: [u32; 3],
+}
+
+impl MorphOffset {
+    pub fn new(morph_index: usize, position: [f32; 3], uv: [f32; 4]) -> Self {
+        Self {
+            position: [position[0], position[1], position[2], 0.0],
+            uv,
+            morph_index: morph_index as u32,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Morph offsets grouped by vertex, `vertex_ranges[v]` is (start, count) into `offsets`
+pub struct MorphTargets {
+    pub morph_count: usize,
+    pub vertex_ranges: Vec<[u32; 2]>,
+    pub offsets: Vec<MorphOffset>,
+}
+
+impl MorphTargets {
+    /// `offsets` are (vertex index, offset) pairs in any order, vertices out of range are dropped
+    pub fn new(vertex_count: usize, morph_count: usize, offsets: Vec<(usize, MorphOffset)>) -> Self {
+        let mut offsets = offsets.into_iter()
+            .filter(|(v, o)| *v < vertex_count && (o.morph_index as usize) < morph_count)
+            .collect::<Vec<_>>();
+        offsets.sort_by_key(|(v, _)| *v);
+
+        let mut vertex_ranges = vec![[0, 0]; vertex_count];
+        for (i, (v, _)) in offsets.iter().enumerate() {
+            let range = &mut vertex_ranges[*v];
+            if range[1] == 0 {
+                range[0] = i as u32;
+            }
+            range[1] += 1;
+        }
+
+        Self {
+            morph_count,
+            vertex_ranges,
+            offsets: offsets.into_iter().map(|(_, o)| o).collect(),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            morph_count: 0,
+            vertex_ranges: Vec::new(),
+            offsets: Vec::new(),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use cgmath::Matrix4;
 
-use crate::{mesh::mesh::DruvisMesh, material::material::DruvisMaterial, binding::{data_binding_state::DataBindingState, bind_index::{BIND_GROUP_INDEX_PER_FRAME, BIND_GROUP_INDEX_PER_OBJECT}}, common::util_traits::AsBytes};
+use crate::{mesh::mesh::DruvisMesh, material::material::DruvisMaterial, binding::{data_binding_state::DataBindingState, bind_index::{BIND_GROUP_INDEX_PER_FRAME, BIND_GROUP_INDEX_PER_OBJECT, BIND_GROUP_INDEX_SKINNING}}, common::util_traits::AsBytes};
 
-use super::uniform::{PerFrameUniform, PerObjectUniform};
+use super::{uniform::{PerFrameUniform, PerObjectUniform}, skinning::SkinningBindState};
 
 pub struct RenderState {
     pub color_target: Option<wgpu::TextureView>,
@@ -140,6 +140,21 @@ impl RenderState {
         material: &DruvisMaterial,
         transform_matrix: Matrix4<f32>,
         submesh_index: Option<usize>
+    ) {
+        self.draw_skinned_mesh(device, queue, mesh, material, transform_matrix, submesh_index, None)
+    }
+
+    /// Same as `draw_mesh`, binding bone matrices for materials with a skinned shader
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_skinned_mesh(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mesh: &DruvisMesh,
+        material: &DruvisMaterial,
+        transform_matrix: Matrix4<f32>,
+        submesh_index: Option<usize>,
+        skinning: Option<&SkinningBindState>
     ) {
         let mut encoder = device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor {
@@ -194,6 +209,13 @@ impl RenderState {
             render_pass.set_bind_group(BIND_GROUP_INDEX_PER_FRAME, &self.per_frame_bind_state.bind_group, &[]);
             // bind per object bind group
             render_pass.set_bind_group(BIND_GROUP_INDEX_PER_OBJECT, &self.per_object_bind_state.bind_group, &[]);
+            // bind bone matrices
+            if material.shader.is_skinned {
+                match skinning {
+                    Some(s) => render_pass.set_bind_group(BIND_GROUP_INDEX_SKINNING, &s.bind_group, &[]),
+                    None => panic!("Cannot draw skinned shader {} without bone matrices", material.shader.name),
+                }
+            }
 
             // set vertex buffer
             render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
@@ -0,0 +1,174 @@
+use cgmath::{Matrix4, Matrix3, Quaternion};
+use wgpu::util::DeviceExt;
+
+use crate::utils;
+
+use super::morph::{MorphTargets, MorphOffset};
+
+/// Rotation and translation of a bone as a unit dual quaternion, both parts as x, y, z, w
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct DualQuaternion {
+    pub real: [f32; 4],
+    pub dual: [f32; 4],
+}
+
+impl DualQuaternion {
+    /// Assumes `matrix` has no scale, which holds for MMD bones
+    pub fn from_rigid_matrix(matrix: &Matrix4<f32>) -> Self {
+        let rotation = Matrix3::from_cols(matrix.x.truncate(), matrix.y.truncate(), matrix.z.truncate());
+        let real = Quaternion::from(rotation);
+        let translation = Quaternion::new(0.0, matrix.w.x, matrix.w.y, matrix.w.z);
+        let dual = translation * real * 0.5;
+
+        Self {
+            real: [real.v.x, real.v.y, real.v.z, real.s],
+            dual: [dual.v.x, dual.v.y, dual.v.z, dual.s],
+        }
+    }
+}
+
+/// Bone transforms and morph targets of one skinned mesh, bound at `BIND_GROUP_INDEX_SKINNING`.
+/// Binding 0 holds matrices for linear and SDEF skinning, binding 1 dual quaternions for SDEF and QDEF,
+/// bindings 2 to 4 the morph ranges, offsets and weights
+pub struct SkinningBindState {
+    pub bone_count: usize,
+    pub buffer: wgpu::Buffer,
+    pub dual_quaternion_buffer: wgpu::Buffer,
+    pub morph_count: usize,
+    pub morph_range_buffer: wgpu::Buffer,
+    pub morph_offset_buffer: wgpu::Buffer,
+    pub morph_weight_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl SkinningBindState {
+    fn storage_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+
+    pub fn get_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    Self::storage_entry(0),
+                    Self::storage_entry(1),
+                    Self::storage_entry(2),
+                    Self::storage_entry(3),
+                    Self::storage_entry(4),
+                ],
+                label: Some("skinning_bind_group_layout"),
+            }
+        )
+    }
+
+    fn create_storage_buffer(device: &wgpu::Device, size: usize, label: &str) -> wgpu::Buffer {
+        device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some(label),
+                size: size as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        )
+    }
+
+    // storage bindings can not be empty, so empty data gets one zeroed element
+    fn create_storage_buffer_init<T>(device: &wgpu::Device, data: &[T], label: &str) -> wgpu::Buffer {
+        let zeroed = utils::create_buffer(std::mem::size_of::<T>());
+        device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: if data.is_empty() { &zeroed } else { utils::reinterpret_slice::<T, u8>(data) },
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            }
+        )
+    }
+
+    pub fn new(device: &wgpu::Device, bone_count: usize, morph_targets: &MorphTargets, label: &str) -> Self {
+        // a model without bones still needs a valid binding
+        let count = bone_count.max(1);
+        let buffer = Self::create_storage_buffer(
+            device,
+            std::mem::size_of::<Matrix4<f32>>() * count,
+            (String::from(label) + "_skinning_buffer").as_str()
+        );
+        let dual_quaternion_buffer = Self::create_storage_buffer(
+            device,
+            std::mem::size_of::<DualQuaternion>() * count,
+            (String::from(label) + "_skinning_dual_quaternion_buffer").as_str()
+        );
+
+        let morph_range_buffer = Self::create_storage_buffer_init(
+            device,
+            &morph_targets.vertex_ranges,
+            (String::from(label) + "_morph_range_buffer").as_str()
+        );
+        let morph_offset_buffer = Self::create_storage_buffer_init::<MorphOffset>(
+            device,
+            &morph_targets.offsets,
+            (String::from(label) + "_morph_offset_buffer").as_str()
+        );
+        let morph_weight_buffer = Self::create_storage_buffer_init(
+            device,
+            &vec![0.0_f32; morph_targets.morph_count],
+            (String::from(label) + "_morph_weight_buffer").as_str()
+        );
+
+        let buffers = [&buffer, &dual_quaternion_buffer, &morph_range_buffer, &morph_offset_buffer, &morph_weight_buffer];
+        let entries = buffers.iter().enumerate()
+            .map(|(i, b)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: b.as_entire_binding()
+            })
+            .collect::<Vec<_>>();
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &Self::get_bind_group_layout(device),
+                entries: &entries,
+                label: Some((String::from(label) + "_skinning_bind_group").as_str())
+            }
+        );
+
+        Self {
+            bone_count,
+            buffer,
+            dual_quaternion_buffer,
+            morph_count: morph_targets.morph_count,
+            morph_range_buffer,
+            morph_offset_buffer,
+            morph_weight_buffer,
+            bind_group,
+        }
+    }
+
+    /// Only the per morph weights are uploaded, the targets stay on the GPU
+    pub fn write_morph_weights(&self, queue: &wgpu::Queue, weights: &[f32]) {
+        let count = weights.len().min(self.morph_count);
+        if count == 0 {
+            return;
+        }
+        queue.write_buffer(&self.morph_weight_buffer, 0, utils::reinterpret_slice::<f32, u8>(&weights[..count]));
+    }
+
+    pub fn write_matrices(&self, queue: &wgpu::Queue, matrices: &[Matrix4<f32>]) {
+        let count = matrices.len().min(self.bone_count);
+        if count == 0 {
+            return;
+        }
+        let matrices = &matrices[..count];
+        let dual_quaternions = matrices.iter().map(DualQuaternion::from_rigid_matrix).collect::<Vec<_>>();
+
+        queue.write_buffer(&self.buffer, 0, utils::reinterpret_slice::<Matrix4<f32>, u8>(matrices));
+        queue.write_buffer(&self.dual_quaternion_buffer, 0, utils::reinterpret_slice::<DualQuaternion, u8>(&dual_quaternions));
+    }
+}
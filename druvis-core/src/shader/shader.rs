@@ -1,7 +1,7 @@
 use std::{collections::HashMap, rc::Rc, cell::{RefCell, Ref}};
 use serde::{Serialize, Deserialize};
 
-use crate::vertex::vertex::{ModelVertex, Vertex};
+use crate::{vertex::vertex::{ModelVertex, Vertex, SkinnedModelVertex}, rendering::skinning::SkinningBindState};
 
 use super::{shader_property::{ShaderPropertyLayoutEntry, ShaderTextureLayoutEntry}, shader_descriptor::ShaderDescriptor};
 
@@ -29,6 +29,9 @@ pub struct ShaderBindState {
 pub struct WGPURenderPipelineKey {
     pub color_format: wgpu::TextureFormat,
     pub depth_format: Option<wgpu::TextureFormat>,
+    // materials can override the shader's cull mode and draw points or lines
+    pub cull_mode: Option<wgpu::Face>,
+    pub topology: wgpu::PrimitiveTopology,
 }
 
 pub struct WGPURenderPipelineCollection {
@@ -45,6 +48,7 @@ pub struct DruvisShader {
     pub blend_state: Option<wgpu::BlendState>,
     pub is_instancing: bool,
     pub instancing_vertex_buffer_layout: Option<OwnedVertexBufferLayout>,
+    pub is_skinned: bool,
     
     pub shader_value_layout: Vec<ShaderPropertyLayoutEntry>,
     pub shader_texture_layout: Vec<ShaderTextureLayoutEntry>,
@@ -59,8 +63,10 @@ impl DruvisShader {
         render_pass: &mut wgpu::RenderPass<'a>,
         color_format: wgpu::TextureFormat,
         depth_format: Option<wgpu::TextureFormat>,
+        cull_mode: Option<wgpu::Face>,
+        topology: wgpu::PrimitiveTopology,
     ) where 'b: 'a {
-        let rp = self.get_render_pipeline(device, color_format, depth_format);
+        let rp = self.get_render_pipeline(device, color_format, depth_format, cull_mode, topology);
         render_pass.set_pipeline(&rp);
         // render_pass.set_bind_group(10, &self.shader_bind_state.value_bind_group, &[]);
     }
@@ -69,18 +75,22 @@ impl DruvisShader {
         &self,
         device: &wgpu::Device,
         color_format: wgpu::TextureFormat,
-        depth_format: Option<wgpu::TextureFormat>
+        depth_format: Option<wgpu::TextureFormat>,
+        cull_mode: Option<wgpu::Face>,
+        topology: wgpu::PrimitiveTopology,
     ) -> &wgpu::RenderPipeline {
         let key = WGPURenderPipelineKey {
             color_format,
-            depth_format
+            depth_format,
+            cull_mode,
+            topology,
         };
         if self.render_pipeline_collection.data.contains_key(&key) {
             return self.render_pipeline_collection.data.get(&key).unwrap();
             // return ;
         }
 
-        let rp = self.create_render_pipeline(device, color_format, depth_format);
+        let rp = self.create_render_pipeline(device, &key);
 
         unsafe {
             let ptr = &self.render_pipeline_collection as *const WGPURenderPipelineCollection;
@@ -111,6 +121,7 @@ impl DruvisShader {
             desc.cull_mode,
             desc.is_instancing,
             desc.instancing_vertex_buffer_layout.clone(),
+            desc.is_skinned,
             desc.shader_value_layout.clone(),
             desc.shader_texture_layout.clone(),
             ShaderBindState {
@@ -124,8 +135,7 @@ impl DruvisShader {
     pub fn create_render_pipeline(
         &self,
         device: &wgpu::Device,
-        color_format: wgpu::TextureFormat,
-        depth_format: Option<wgpu::TextureFormat>
+        key: &WGPURenderPipelineKey
     ) -> wgpu::RenderPipeline {
         let render_pipeline = device.create_render_pipeline(
             &wgpu::RenderPipelineDescriptor {
@@ -135,7 +145,7 @@ impl DruvisShader {
                     module: &self.shader_module,
                     entry_point: "vs_main",
                     buffers: &[
-                        ModelVertex::desc(),
+                        if self.is_skinned { SkinnedModelVertex::desc() } else { ModelVertex::desc() },
                         // todo instancing
                     ]
                 },
@@ -144,22 +154,22 @@ impl DruvisShader {
                     entry_point: "fs_main",
                     targets: &[
                         Some(wgpu::ColorTargetState {
-                            format: color_format,
+                            format: key.color_format,
                             blend: self.blend_state,
                             write_mask: wgpu::ColorWrites::ALL,
                         })
                     ]
                 }),
                 primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    topology: key.topology,
                     strip_index_format: None,
                     front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: self.cull_mode,
+                    cull_mode: key.cull_mode,
                     polygon_mode: wgpu::PolygonMode::Fill,
                     unclipped_depth: false,
                     conservative: false,
                 },
-                depth_stencil: depth_format.map(|format| {
+                depth_stencil: key.depth_format.map(|format| {
                     wgpu::DepthStencilState {
                         format,
                         depth_write_enabled: true,
@@ -191,6 +201,7 @@ impl DruvisShader {
         cull_mode: Option<wgpu::Face>,
         is_instancing: bool,
         instancing_vertex_buffer_layout: Option<OwnedVertexBufferLayout>,
+        is_skinned: bool,
         shader_value_layout: Vec<ShaderPropertyLayoutEntry>,
         shader_texture_layout: Vec<ShaderTextureLayoutEntry>,
         shader_bind_state: ShaderBindState,
@@ -204,6 +215,11 @@ impl DruvisShader {
         }
         // add shader specific bind group layouts
         bind_group_layouts.push(&shader_bind_state.value_bind_group_layout);
+        // add bone matrices for skinned shaders
+        let skinning_bind_group_layout = SkinningBindState::get_bind_group_layout(device);
+        if is_skinned {
+            bind_group_layouts.push(&skinning_bind_group_layout);
+        }
         // println!("{:?}", bind_group_layouts);
         // if shader_bind_state.texture_bind_group_layout.is_some() {
         //     bind_group_layouts.push(shader_bind_state.texture_bind_group_layout.as_ref().unwrap());
@@ -231,6 +247,7 @@ impl DruvisShader {
             blend_state: blend_state.clone(),
             is_instancing,
             instancing_vertex_buffer_layout: instancing_vertex_buffer_layout.clone(),
+            is_skinned,
             shader_bind_state,
             shader_value_layout,
             shader_texture_layout,
@@ -12,6 +12,12 @@ pub struct ShaderDescriptor {
     pub cull_mode: Option<wgpu::Face>,
     pub blend_mode: Option<wgpu::BlendState>,
     pub is_instancing: bool,
+    // skinned shaders take `SkinnedModelVertex` and bone matrices at group 3
+    #[serde(default)]
+    pub is_skinned: bool,
+    // sources put in front of this one, by file name without ".wgsl" in the same directory
+    #[serde(default)]
+    pub includes: Vec<String>,
     pub instancing_vertex_buffer_layout: Option<OwnedVertexBufferLayout>,
     pub shader_value_layout: Vec<ShaderPropertyLayoutEntry>,
     pub shader_texture_layout: Vec<ShaderTextureLayoutEntry>,
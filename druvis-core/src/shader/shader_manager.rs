@@ -37,12 +37,19 @@ impl ShaderManager {
         println!("wgsl: {:?}", wgsl);
         
         let meta = std::fs::read_to_string(path)?;
-        let source = std::fs::read_to_string(wgsl)?;
+        let source = std::fs::read_to_string(&wgsl)?;
         // println!("source: {}", source);
         // println!("meta: {}", meta);
 
         let mut shader_desc = serde_json::from_str::<ShaderDescriptor>(&meta).unwrap();
-        shader_desc.source = source;
+        let mut full_source = String::new();
+        for include in shader_desc.includes.iter() {
+            let include = wgsl.parent().unwrap().join(include.clone() + ".wgsl");
+            full_source += &std::fs::read_to_string(include)?;
+            full_source += "\n";
+        }
+        full_source += &source;
+        shader_desc.source = full_source;
 
         let shader = DruvisShader::from_shader_descriptor(device, builtin_bind_group_layouts, &shader_desc);
 
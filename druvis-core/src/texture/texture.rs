@@ -1,5 +1,6 @@
 use std::path::{PathBuf, Path};
 
+use anyhow::Result;
 use wgpu::util::DeviceExt;
 
 pub struct DruvisSampler {
@@ -25,13 +26,15 @@ pub struct DruvisTexture {
 }
 
 impl DruvisTexture {
+    /// Loads an image file, the format is guessed from the content when the
+    /// extension does not tell, e.g. for MMD's .sph and .spa sphere maps
     pub fn from_path(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         path: &Path,
         format: wgpu::TextureFormat,
-    ) -> Self {
-        let img = image::open(path).unwrap();
+    ) -> Result<Self> {
+        let img = image::io::Reader::open(path)?.with_guessed_format()?.decode()?;
         let rgba = img.to_rgba8();
 
         let width = img.width();
@@ -42,16 +45,16 @@ impl DruvisTexture {
             depth_or_array_layers: 1,
         };
 
-        let filename = path.file_name().unwrap().to_str();
+        let filename = path.file_name().and_then(|f| f.to_str()).unwrap_or("texture");
 
-        Self::new_2d(
+        Ok(Self::new_2d(
             device,
             queue,
             &rgba,
             extent,
             format,
-            filename.unwrap(),
-        )
+            filename,
+        ))
     }
 
     pub fn new_2d(
@@ -133,13 +136,13 @@ impl DruvisTextureAndSampler {
         format: wgpu::TextureFormat,
         sampler_desc: &wgpu::SamplerDescriptor,
         sampler_binding_type: wgpu::SamplerBindingType,
-    ) -> Self {
-        let druvis_texture = DruvisTexture::from_path(device, queue, path, format);
+    ) -> Result<Self> {
+        let druvis_texture = DruvisTexture::from_path(device, queue, path, format)?;
         let druvis_sampler = DruvisSampler::new(device, sampler_desc, sampler_binding_type);
 
-        Self {
+        Ok(Self {
             sampler: druvis_sampler,
             texture: druvis_texture
-        }
+        })
     }
 }
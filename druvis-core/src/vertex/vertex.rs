@@ -60,3 +60,125 @@ impl Vertex for ModelVertex {
         }
     }
 }
+
+// values of `SkinnedModelVertex::deform_type`
+pub const DEFORM_TYPE_LINEAR: u32 = 0;
+pub const DEFORM_TYPE_SDEF: u32 = 1;
+pub const DEFORM_TYPE_QDEF: u32 = 2;
+
+#[repr(C)]
+pub struct SkinnedModelVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 3],
+    pub bitangent: [f32; 3],
+    pub bone_indices: [u32; 4],
+    // weights of unused slots are 0
+    pub bone_weights: [f32; 4],
+    pub deform_type: u32,
+    // SDEF center and the precomputed rotation centers of bone 1 and 2
+    pub sdef_c: [f32; 3],
+    pub sdef_r0: [f32; 3],
+    pub sdef_r1: [f32; 3],
+    // first PMX additional vec4, sphere map coordinates in sub texture mode
+    pub additional_uv: [f32; 4],
+    // outline width multiplier, 0 leaves the vertex without an edge
+    pub edge_scale: f32,
+}
+
+impl Default for SkinnedModelVertex {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0, 0.0],
+            tex_coords: [0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
+            tangent: [0.0, 0.0, 0.0],
+            bitangent: [0.0, 0.0, 0.0],
+            bone_indices: [0, 0, 0, 0],
+            bone_weights: [0.0, 0.0, 0.0, 0.0],
+            deform_type: DEFORM_TYPE_LINEAR,
+            sdef_c: [0.0, 0.0, 0.0],
+            sdef_r0: [0.0, 0.0, 0.0],
+            sdef_r1: [0.0, 0.0, 0.0],
+            additional_uv: [0.0, 0.0, 0.0, 0.0],
+            edge_scale: 1.0,
+        }
+    }
+}
+
+impl Vertex for SkinnedModelVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<SkinnedModelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x3
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 14]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Uint32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 18]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 22]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 23]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 26]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 29]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 32]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 36]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ]
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::f32::consts::PI;
+
+use cgmath::{Vector3, Quaternion, InnerSpace, Rotation};
+use druvis_core::game_object::components::{SkeletonData, Bone, IkChain, IkLink};
+
+const HIP: usize = 0;
+const KNEE: usize = 1;
+const ANKLE: usize = 2;
+const LEG_IK: usize = 3;
+
+fn knee_limit() -> (Vector3<f32>, Vector3<f32>) {
+    (Vector3::new(-PI, 0.0, 0.0), Vector3::new(-0.5f32.to_radians(), 0.0, 0.0))
+}
+
+// a straight leg of two 5 unit bones standing on its IK bone, the knee limited like PMD knees
+fn leg(loop_count: u32, limit_angle: f32) -> SkeletonData {
+    let mut ik = Bone::new("左足ＩＫ", None, Vector3::new(0.0, 0.0, 0.0));
+    ik.ik = Some(IkChain {
+        target: ANKLE,
+        loop_count,
+        limit_angle,
+        links: vec![IkLink::new(KNEE, Some(knee_limit())), IkLink::new(HIP, None)],
+    });
+    SkeletonData::new(vec![
+        Bone::new("左足", None, Vector3::new(0.0, 10.0, 0.0)),
+        Bone::new("左ひざ", Some(HIP), Vector3::new(0.0, 5.0, 0.0)),
+        Bone::new("左足首", Some(KNEE), Vector3::new(0.0, 0.0, 0.0)),
+        ik,
+    ])
+}
+
+fn move_ik(skeleton: &mut SkeletonData, position: Vector3<f32>) {
+    skeleton.bones[LEG_IK].translation = position;
+    skeleton.update_world_matrices();
+}
+
+fn position(skeleton: &SkeletonData, bone: usize) -> Vector3<f32> {
+    skeleton.bones[bone].world_matrix.w.truncate()
+}
+
+// signed angle of a rotation about x, None when it also turns about another axis
+fn x_angle(q: Quaternion<f32>) -> Option<f32> {
+    if q.v.y.abs() > 1e-4 || q.v.z.abs() > 1e-4 {
+        return None;
+    }
+    Some(2.0 * q.v.x.atan2(q.s))
+}
+
+#[test]
+fn leg_reaches_the_ik_bone() {
+    let mut skeleton = leg(40, 2.0);
+    let target = Vector3::new(0.0, 3.0, 1.0);
+    move_ik(&mut skeleton, target);
+
+    let ankle = position(&skeleton, ANKLE);
+    assert!((ankle - target).magnitude() < 0.01, "{:?}", ankle);
+    // bones keep their length
+    assert!(((position(&skeleton, KNEE) - position(&skeleton, HIP)).magnitude() - 5.0).abs() < 1e-3);
+    assert!(((ankle - position(&skeleton, KNEE)).magnitude() - 5.0).abs() < 1e-3);
+}
+
+#[test]
+fn straight_knee_bends_the_way_its_limit_allows() {
+    let mut skeleton = leg(40, 2.0);
+    // straight below the hip, the first iteration could bend either way
+    let target = Vector3::new(0.0, 4.0, 0.0);
+    move_ik(&mut skeleton, target);
+
+    let (min, max) = knee_limit();
+    let angle = x_angle(skeleton.bones[KNEE].ik_rotation).expect("knee twisted");
+    assert!(angle >= min.x - 1e-4 && angle <= max.x + 1e-4, "{}", angle);
+    assert!((position(&skeleton, ANKLE) - target).magnitude() < 0.01, "{:?}", position(&skeleton, ANKLE));
+    // the knee points forward, to -z
+    assert!(position(&skeleton, KNEE).z < 0.0, "{:?}", position(&skeleton, KNEE));
+}
+
+#[test]
+fn out_of_reach_keeps_the_knee_in_its_limits() {
+    let mut skeleton = leg(40, 2.0);
+    move_ik(&mut skeleton, Vector3::new(0.0, -5.0, 3.0));
+
+    let (_, max) = knee_limit();
+    let angle = x_angle(skeleton.bones[KNEE].ik_rotation).expect("knee twisted");
+    // as straight as the limit allows, but never bent backwards
+    assert!(angle <= max.x + 1e-4, "{}", angle);
+    assert!(angle > -0.1, "{}", angle);
+}
+
+#[test]
+fn limit_angle_caps_each_step() {
+    let mut skeleton = leg(1, 0.01);
+    move_ik(&mut skeleton, Vector3::new(0.0, 3.0, 1.0));
+
+    for bone in [KNEE, HIP] {
+        let rotation = skeleton.bones[bone].ik_rotation;
+        let angle = 2.0 * rotation.v.magnitude().atan2(rotation.s.abs());
+        assert!(angle <= 0.01 + 1e-4, "{} turned {}", bone, angle);
+    }
+}
+
+#[test]
+fn zero_loops_and_disabled_chains_leave_the_leg_alone() {
+    let mut skeleton = leg(0, 2.0);
+    move_ik(&mut skeleton, Vector3::new(0.0, 3.0, 1.0));
+    assert_eq!(position(&skeleton, ANKLE), Vector3::new(0.0, 0.0, 0.0));
+
+    let mut skeleton = leg(40, 2.0);
+    move_ik(&mut skeleton, Vector3::new(0.0, 3.0, 1.0));
+    skeleton.bones[LEG_IK].ik_enabled = false;
+    skeleton.update_world_matrices();
+    assert!((position(&skeleton, ANKLE) - Vector3::new(0.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert!(skeleton.bones[KNEE].ik_rotation.rotate_vector(Vector3::unit_y()).y > 1.0 - 1e-5);
+}
+
+#[test]
+fn first_iteration_turns_a_knee_bending_the_wrong_way() {
+    // in front of the leg, plain CCD would bend the knee forward against its limit
+    let mut skeleton = leg(1, 2.0);
+    move_ik(&mut skeleton, Vector3::new(0.0, 6.0, -2.0));
+
+    // the whole step is taken the other way instead of being clamped away
+    let angle = x_angle(skeleton.bones[KNEE].ik_rotation).expect("knee twisted");
+    assert!((angle - -2.0).abs() < 1e-4, "{}", angle);
+}
@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use cgmath::{Vector3, Quaternion, Matrix3, Matrix4, InnerSpace, Rotation3, Rad, One, Zero};
+use druvis_core::game_object::{DruvisGameObject, DruvisComponent, game_object::DruvisGameObjectExt, components::{SkeletonData, Bone, MorphControllerData, PhysicsData, PhysicsMode, RigidBodyBinding, Animation, AnimatorData}};
+use druvis_physics::{PhysicsWorld, RigidBody, Shape};
+
+const ROOT: usize = 0;
+const HAIR: usize = 1;
+const TIP: usize = 2;
+const AFTER: usize = 3;
+
+const EPSILON: f32 = 1e-4;
+
+fn assert_vector(a: Vector3<f32>, b: Vector3<f32>) {
+    assert!((a - b).magnitude() < EPSILON, "{:?} != {:?}", a, b);
+}
+
+// a root with a hair bone hanging from it, the tip below the hair and a bone deformed after physics
+fn skeleton() -> SkeletonData {
+    let mut after = Bone::new("after", Some(HAIR), Vector3::new(1.0, 8.0, 0.0));
+    after.deform_after_physics = true;
+    SkeletonData::new(vec![
+        Bone::new("root", None, Vector3::new(0.0, 10.0, 0.0)),
+        Bone::new("hair", Some(ROOT), Vector3::new(0.0, 8.0, 0.0)),
+        Bone::new("tip", Some(HAIR), Vector3::new(0.0, 6.0, 0.0)),
+        after,
+    ])
+}
+
+// a kinematic body on the root and a free falling one on the hair, unless it follows the bone
+fn physics(hair_mode: PhysicsMode) -> PhysicsData {
+    let mut world = PhysicsWorld::new();
+    let mut root = RigidBody::new(Shape::Sphere { radius: 0.5 }, 0.0, Vector3::new(0.0, 10.0, 0.0), Quaternion::one());
+    root.kinematic = true;
+    world.add_body(root);
+    let mut hair = RigidBody::new(Shape::Sphere { radius: 0.5 }, 1.0, Vector3::new(0.0, 8.0, 0.0), Quaternion::one());
+    hair.kinematic = hair_mode == PhysicsMode::FollowBone;
+    world.add_body(hair);
+
+    PhysicsData::new(world, vec![
+        RigidBodyBinding {
+            bone: Some(ROOT),
+            mode: PhysicsMode::FollowBone,
+            offset: Matrix4::from_translation(Vector3::new(0.0, 10.0, 0.0)),
+        },
+        RigidBodyBinding {
+            bone: Some(HAIR),
+            mode: hair_mode,
+            offset: Matrix4::from_translation(Vector3::new(0.0, 8.0, 0.0)),
+        },
+    ])
+}
+
+fn position(skeleton: &SkeletonData, bone: usize) -> Vector3<f32> {
+    skeleton.bones[bone].world_matrix.w.truncate()
+}
+
+fn rotation(matrix: &Matrix4<f32>) -> Matrix3<f32> {
+    Matrix3::from_cols(matrix.x.truncate(), matrix.y.truncate(), matrix.z.truncate())
+}
+
+fn run(skeleton: &mut SkeletonData, physics: &mut PhysicsData, steps: u32) {
+    let dt = physics.world.fixed_time_step;
+    for _ in 0..steps {
+        skeleton.update_world_matrices_in(false);
+        physics.update(skeleton, dt);
+    }
+}
+
+#[test]
+fn follow_bone_bodies_track_their_bone() {
+    let mut skeleton = skeleton();
+    let mut physics = physics(PhysicsMode::FollowBone);
+    skeleton.bones[ROOT].translation = Vector3::new(5.0, 0.0, 0.0);
+    skeleton.bones[ROOT].rotation = Quaternion::from_angle_y(Rad(0.5));
+    run(&mut skeleton, &mut physics, 1);
+
+    let root = &physics.world.bodies[0];
+    assert_vector(root.position, Vector3::new(5.0, 10.0, 0.0));
+    assert!(root.rotation.dot(Quaternion::from_angle_y(Rad(0.5))).abs() > 1.0 - EPSILON);
+    // the hair body follows its bone too and the bones keep their animated pose
+    assert_vector(physics.world.bodies[1].position, Vector3::new(5.0, 8.0, 0.0));
+    assert_vector(position(&skeleton, HAIR), Vector3::new(5.0, 8.0, 0.0));
+}
+
+#[test]
+fn physics_bodies_drive_their_bone_and_its_children() {
+    let mut skeleton = skeleton();
+    let mut physics = physics(PhysicsMode::Physics);
+    physics.world.bodies[1].angular_velocity = Vector3::new(0.0, 0.0, 1.0);
+    run(&mut skeleton, &mut physics, 30);
+
+    let body = &physics.world.bodies[1];
+    assert!(body.position.y < 7.0, "{:?}", body.position);
+    let hair = skeleton.bones[HAIR].world_matrix;
+    assert_vector(hair.w.truncate(), body.position);
+    let difference = rotation(&hair) - Matrix3::from(body.rotation);
+    assert!(difference.x.magnitude() + difference.y.magnitude() + difference.z.magnitude() < EPSILON);
+
+    // children keep their offset in the rotated bone, before and after physics alike
+    assert_vector(position(&skeleton, TIP), body.position + body.rotation * Vector3::new(0.0, -2.0, 0.0));
+    assert_vector(position(&skeleton, AFTER), body.position + body.rotation * Vector3::new(1.0, 0.0, 0.0));
+    // the kinematic root stays with its bone
+    assert_vector(position(&skeleton, ROOT), Vector3::new(0.0, 10.0, 0.0));
+}
+
+#[test]
+fn bone_position_mode_only_takes_the_rotation() {
+    let mut skeleton = skeleton();
+    let mut physics = physics(PhysicsMode::PhysicsWithBonePosition);
+    physics.world.bodies[1].angular_velocity = Vector3::new(0.0, 0.0, 1.0);
+    skeleton.bones[HAIR].translation = Vector3::new(0.0, 0.5, 0.0);
+    run(&mut skeleton, &mut physics, 30);
+
+    let body = &physics.world.bodies[1];
+    assert!(body.position.y < 7.0, "{:?}", body.position);
+    let hair = skeleton.bones[HAIR].world_matrix;
+    // the animated position, with the simulated rotation
+    assert_vector(hair.w.truncate(), Vector3::new(0.0, 8.5, 0.0));
+    let difference = rotation(&hair) - Matrix3::from(body.rotation);
+    assert!(difference.x.magnitude() + difference.y.magnitude() + difference.z.magnitude() < EPSILON);
+    assert_vector(position(&skeleton, TIP), Vector3::new(0.0, 8.5, 0.0) + body.rotation * Vector3::new(0.0, -2.0, 0.0));
+}
+
+#[test]
+fn disabled_physics_keeps_the_animated_pose() {
+    let mut skeleton = skeleton();
+    let mut physics = physics(PhysicsMode::Physics);
+    physics.enabled = false;
+    skeleton.bones[HAIR].rotation = Quaternion::from_angle_z(Rad(0.5));
+    run(&mut skeleton, &mut physics, 30);
+
+    assert_vector(physics.world.bodies[1].position, Vector3::new(0.0, 8.0, 0.0));
+    assert_vector(position(&skeleton, HAIR), Vector3::new(0.0, 8.0, 0.0));
+    assert_vector(position(&skeleton, AFTER), Vector3::new(0.0, 8.0, 0.0) + Quaternion::from_angle_z(Rad(0.5)) * Vector3::new(1.0, 0.0, 0.0));
+}
+
+// moves the root sideways, `jump_at` teleports it back to the start once
+struct Slide {
+    frame: u32,
+    jump_at: u32,
+}
+
+impl Animation for Slide {
+    fn advance(&mut self, _dt: Duration) -> bool {
+        self.frame += 1;
+        if self.frame == self.jump_at {
+            self.frame = 0;
+            return true;
+        }
+        false
+    }
+
+    fn pose(&self, skeleton: &mut SkeletonData, _morphs: Option<&mut MorphControllerData>) {
+        skeleton.reset_pose();
+        skeleton.bones[ROOT].translation = Vector3::new(self.frame as f32, 0.0, 0.0);
+    }
+}
+
+#[test]
+fn frame_update_animates_before_physics_and_resets_on_jumps() {
+    let go = DruvisGameObject::new();
+    go.add_component(DruvisComponent::new(skeleton()));
+    go.add_component(DruvisComponent::new(physics(PhysicsMode::Physics)));
+    go.add_component(DruvisComponent::new(AnimatorData::new(Box::new(Slide { frame: 0, jump_at: 20 }))));
+    let skeleton = go.get_component::<SkeletonData>().unwrap();
+    let physics = go.get_component::<PhysicsData>().unwrap();
+    let dt = Duration::from_secs_f32(physics.borrow().data.world.fixed_time_step);
+
+    for _ in 0..10 {
+        skeleton.borrow_mut().update(dt);
+    }
+    {
+        let skeleton = &skeleton.borrow().data;
+        let bodies = &physics.borrow().data.world.bodies;
+        // the animation reached the kinematic body, and the simulated bone was not posed over
+        assert_vector(bodies[0].position, Vector3::new(10.0, 10.0, 0.0));
+        assert_vector(position(skeleton, ROOT), Vector3::new(10.0, 10.0, 0.0));
+        assert!(bodies[1].position.y < 7.9, "{:?}", bodies[1].position);
+        assert_vector(position(skeleton, HAIR), bodies[1].position);
+    }
+
+    for _ in 10..20 {
+        skeleton.borrow_mut().update(dt);
+    }
+    // back at the start, the falling body was put back under its bone instead of flying there
+    let bodies = &physics.borrow().data.world.bodies;
+    assert_vector(bodies[0].position, Vector3::new(0.0, 10.0, 0.0));
+    assert!((bodies[1].position - Vector3::new(0.0, 8.0, 0.0)).magnitude() < 0.05, "{:?}", bodies[1].position);
+    // a single step of gravity since then
+    assert!(bodies[1].linear_velocity.magnitude() < 2.0, "{:?}", bodies[1].linear_velocity);
+    assert_eq!(bodies[1].angular_velocity, Vector3::zero());
+}
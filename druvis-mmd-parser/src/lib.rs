@@ -1,4 +1,13 @@
-mod pmx;
+pub mod pmx;
+pub mod pmd;
+pub mod vmd;
+pub mod vpd;
 pub mod utils;
 
-pub use pmx::pmx_parser::PmxParser;
\ No newline at end of file
+pub use pmx::pmx_parser::PmxParser;
+pub use pmx::pmx_writer::PmxWriter;
+pub use pmd::pmd_parser::PmdParser;
+pub use vmd::vmd_parser::VmdParser;
+pub use vmd::vmd_player::VMDPlayer;
+pub use vmd::vmd_camera::VMDCameraController;
+pub use vpd::vpd_parser::VpdParser;
@@ -0,0 +1,2 @@
+pub mod pmd_parser;
+pub mod structs;
@@ -0,0 +1,634 @@
+use std::{collections::HashMap, path::{PathBuf, Path}, io::Read, fs::File, f32::consts::PI};
+
+use crate::{utils::{self, ReadError}, pmx::{pmx_parser::PMXFormat, pmx_error::{PmxResult, PmxError, PmxErrorKind, PmxSection, PmxLoadResult}, structs::*}};
+
+use super::structs::*;
+
+#[derive(Clone, Debug)]
+pub struct PMDFormat {
+    pub header: PMDHeader,
+    pub vertices: Vec<PMDVertexData>,
+    pub indices: Vec<u16>,
+    pub materials: Vec<PMDMaterialData>,
+    pub bones: Vec<PMDBoneData>,
+    pub iks: Vec<PMDIKData>,
+    pub skins: Vec<PMDSkinData>,
+    pub skin_display_list: Vec<u16>,
+    pub bone_display_names: Vec<String>,
+    pub bone_display_list: Vec<PMDBoneDisplayData>,
+    // the following sections are optional extensions
+    pub english_names: Option<PMDEnglishNames>,
+    pub toon_texture_names: Vec<String>,
+    pub rigidbodies: Vec<PMDRigidBodyData>,
+    pub joints: Vec<PMDJointData>,
+}
+
+fn bone_index(index: u16) -> i32 {
+    if index == PMD_NO_BONE {
+        -1
+    } else {
+        index as i32
+    }
+}
+
+impl PMDFormat {
+    fn default_toon_texture_names() -> Vec<String> {
+        (1..=10).map(|i| format!("toon{:02}.bmp", i)).collect()
+    }
+
+    /// Converts to PMX, textures are resolved relative to `model_path`
+    pub fn to_pmx_format(self, model_path: PathBuf) -> PMXFormat {
+        let english = self.english_names.clone().unwrap_or_default();
+
+        let header = PMXHeader {
+            signature: PMX_SIGNATURE,
+            version: PMXVersion::V2_0,
+            globals_count: 8,
+            globals: vec![0, 0, 4, 4, 4, 4, 4, 4],
+            model_name_local: self.header.model_name.clone(),
+            model_name_universal: english.model_name.clone(),
+            comments_local: self.header.comment.clone(),
+            comments_universal: english.comment.clone(),
+        };
+        let globals = PMXGlobals {
+            text_encoding: TextEncodingType::UTF16LE,
+            additional_vec4_count: 0,
+            vertex_index_size: PMXIndexType::B4,
+            texture_index_size: PMXIndexType::B4,
+            material_index_size: PMXIndexType::B4,
+            bone_index_size: PMXIndexType::B4,
+            morph_index_size: PMXIndexType::B4,
+            rigidbody_index_size: PMXIndexType::B4,
+        };
+
+        let vertices = self.vertices.iter().map(|v| {
+            let weight = v.bone_weight.min(100) as f32 / 100.0;
+            let [bone1, bone2] = v.bone_index;
+            let weight_deform = if bone1 == bone2 || weight >= 1.0 {
+                PMXWeightDeformData::BDEF1(BDEF1Data { bone_index: bone_index(bone1) })
+            } else if weight <= 0.0 {
+                PMXWeightDeformData::BDEF1(BDEF1Data { bone_index: bone_index(bone2) })
+            } else {
+                PMXWeightDeformData::BDEF2(BDEF2Data {
+                    bone_index1: bone_index(bone1),
+                    bone_index2: bone_index(bone2),
+                    bone1_weight: weight,
+                })
+            };
+            let weight_deform_type = match weight_deform {
+                PMXWeightDeformData::BDEF1(_) => PMXWeightDeformType::BDEF1,
+                _ => PMXWeightDeformType::BDEF2,
+            };
+
+            PMXVertexData {
+                position: v.position,
+                normal: v.normal,
+                uv: v.uv,
+                additional_vec4: Vec::new(),
+                weight_deform_type,
+                weight_deform,
+                edge_scale: if v.edge_flag == 0 { 1.0 } else { 0.0 },
+            }
+        }).collect::<Vec<_>>();
+
+        let surfaces = self.indices.chunks_exact(3).map(|t| PMXSurfaceData {
+            triangle: [t[0] as i32, t[1] as i32, t[2] as i32]
+        }).collect::<Vec<_>>();
+
+        // textures, sphere maps and custom toons are all collected into the texture list
+        let mut texture_paths: Vec<String> = Vec::new();
+        let mut add_texture = |name: &str| -> i32 {
+            match texture_paths.iter().position(|p| p == name) {
+                Some(index) => index as i32,
+                None => {
+                    texture_paths.push(name.to_string());
+                    texture_paths.len() as i32 - 1
+                }
+            }
+        };
+
+        let toon_names = if self.toon_texture_names.is_empty() {
+            Self::default_toon_texture_names()
+        } else {
+            self.toon_texture_names.clone()
+        };
+        let default_toon_names = Self::default_toon_texture_names();
+
+        let mut materials = Vec::new();
+        for (i, mat) in self.materials.iter().enumerate() {
+            let mut texture_index = -1;
+            let mut environment_index = -1;
+            let mut environment_blend_mode = PMXEnvironmentBlendMode::Disabled;
+            for name in mat.texture_file_name.split('*').filter(|s| !s.is_empty()) {
+                let extension = Path::new(name).extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                match extension.as_str() {
+                    "sph" => {
+                        environment_index = add_texture(name);
+                        environment_blend_mode = PMXEnvironmentBlendMode::Multiply;
+                    },
+                    "spa" => {
+                        environment_index = add_texture(name);
+                        environment_blend_mode = PMXEnvironmentBlendMode::Additive;
+                    },
+                    _ => texture_index = add_texture(name),
+                }
+            }
+
+            // the shared toons are referenced internally, anything else becomes a texture
+            let (toon_reference, toon_value) = match toon_names.get(mat.toon_index as usize) {
+                Some(name) => match default_toon_names.iter().position(|d| d.eq_ignore_ascii_case(name)) {
+                    Some(shared) => (PMXToonReference::Internal, PMXToonValue::Internal(shared as i8)),
+                    None => (PMXToonReference::Texture, PMXToonValue::Texture(add_texture(name))),
+                },
+                None => (PMXToonReference::Texture, PMXToonValue::Texture(-1)),
+            };
+
+            let alpha = mat.diffuse_color[3];
+            let mut drawing_flags = PMXMaterialFlags::GROUND_SHADOW | PMXMaterialFlags::DRAW_SHADOW | PMXMaterialFlags::RECEIVE_SHADOW;
+            if alpha < 1.0 {
+                drawing_flags |= PMXMaterialFlags::NO_CULL;
+            }
+            // MMD disables self shadow for this exact alpha
+            if (alpha - 0.98).abs() < 1e-6 {
+                drawing_flags &= !(PMXMaterialFlags::DRAW_SHADOW | PMXMaterialFlags::RECEIVE_SHADOW);
+            }
+            if mat.edge_flag != 0 {
+                drawing_flags |= PMXMaterialFlags::EDGE;
+            }
+
+            materials.push(PMXMaterialData {
+                material_name_local: format!("材質{}", i + 1),
+                material_name_universal: format!("material{}", i + 1),
+                diffuse_color: mat.diffuse_color,
+                specular_color: mat.specular_color,
+                specular_strength: mat.specular_strength,
+                ambient_color: mat.ambient_color,
+                drawing_flags,
+                edge_color: [0.0, 0.0, 0.0, 1.0],
+                edge_scale: 1.0,
+                texture_index,
+                environment_index,
+                environment_blend_mode,
+                toon_reference,
+                toon_value,
+                meta_data: String::new(),
+                surface_count: mat.surface_count as i32,
+            });
+        }
+
+        let bone_count = self.bones.len();
+        let mut bones = Vec::new();
+        for (i, bone) in self.bones.iter().enumerate() {
+            let mut flags = PMXBoneFlags::ROTATABLE | PMXBoneFlags::IS_VISIBLE | PMXBoneFlags::ENABLED;
+            let mut inherit = None;
+            let mut fixed_axis = None;
+
+            let has_tail = bone.bone_type != PMDBoneType::RotationFollow
+                && bone.tail_index != 0
+                && (bone.tail_index as usize) < bone_count;
+            let tail = if has_tail {
+                flags |= PMXBoneFlags::INDEXED_TAIL_POSITION;
+                PMXBoneTail::BoneIndex(bone.tail_index as i32)
+            } else {
+                PMXBoneTail::Position([0.0; 3])
+            };
+
+            match bone.bone_type {
+                PMDBoneType::RotateAndMove => flags |= PMXBoneFlags::TRANSLATABLE,
+                PMDBoneType::IK => flags |= PMXBoneFlags::TRANSLATABLE | PMXBoneFlags::IK,
+                PMDBoneType::Unknown | PMDBoneType::IKTarget | PMDBoneType::Invisible => flags.remove(PMXBoneFlags::IS_VISIBLE),
+                PMDBoneType::Twist => {
+                    if has_tail {
+                        let tail_position = self.bones[bone.tail_index as usize].position;
+                        let axis = [
+                            tail_position[0] - bone.position[0],
+                            tail_position[1] - bone.position[1],
+                            tail_position[2] - bone.position[2],
+                        ];
+                        let length = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+                        if length > 0.0 {
+                            flags |= PMXBoneFlags::FIXED_AXIS;
+                            fixed_axis = Some([axis[0] / length, axis[1] / length, axis[2] / length]);
+                        }
+                    }
+                },
+                PMDBoneType::RotationAffected | PMDBoneType::RotationFollow => {
+                    let ratio = if bone.bone_type == PMDBoneType::RotationFollow {
+                        bone.tail_index as f32 * 0.01
+                    } else {
+                        1.0
+                    };
+                    flags |= PMXBoneFlags::INHERIT_ROTATION;
+                    if bone.bone_type == PMDBoneType::RotationFollow {
+                        flags.remove(PMXBoneFlags::IS_VISIBLE);
+                    }
+                    inherit = Some(PMXBoneInheritData {
+                        parent_index: bone_index(bone.ik_parent_index),
+                        ratio,
+                    });
+                },
+                PMDBoneType::Rotate | PMDBoneType::IKAffected => {},
+            }
+
+            bones.push(PMXBoneData {
+                bone_name_local: bone.bone_name.clone(),
+                bone_name_universal: english.bone_names.get(i).cloned().unwrap_or_default(),
+                position: bone.position,
+                parent_index: bone_index(bone.parent_index),
+                layer: 0,
+                flags,
+                tail,
+                inherit,
+                fixed_axis,
+                local_axes: None,
+                external_parent_key: None,
+                ik: None,
+            });
+        }
+
+        for ik in self.iks.iter() {
+            let links = ik.child_bone_indices.iter().map(|&index| {
+                // knees only bend one way, PMD hard codes this by bone name
+                let is_knee = self.bones.get(index as usize)
+                    .map(|b| b.bone_name.contains("ひざ"))
+                    .unwrap_or(false);
+                PMXIKLinkData {
+                    bone_index: bone_index(index),
+                    angle_limit: if is_knee {
+                        Some(PMXIKAngleLimit {
+                            min: [-PI, 0.0, 0.0],
+                            max: [-0.5f32.to_radians(), 0.0, 0.0],
+                        })
+                    } else {
+                        None
+                    },
+                }
+            }).collect();
+
+            let bone = match bones.get_mut(ik.ik_bone_index as usize) {
+                Some(bone) => bone,
+                None => continue,
+            };
+            bone.flags |= PMXBoneFlags::IK;
+            bone.ik = Some(PMXIKData {
+                target_index: bone_index(ik.target_bone_index),
+                loop_count: ik.iterations as i32,
+                limit_angle: ik.control_weight * 4.0,
+                links,
+            });
+        }
+
+        // the base skin holds absolute positions, every other skin is relative to it
+        let base_skin = self.skins.iter().find(|s| s.skin_type == PMDSkinType::Base);
+        let mut morphs = Vec::new();
+        let mut morph_indices = HashMap::new();
+        for (i, skin) in self.skins.iter().enumerate() {
+            if skin.skin_type == PMDSkinType::Base {
+                continue;
+            }
+            let base_vertices = base_skin.map(|s| s.vertices.as_slice()).unwrap_or(&[]);
+            let offsets = skin.vertices.iter().filter_map(|v| {
+                base_vertices.get(v.index as usize).map(|base| PMXVertexMorphOffset {
+                    vertex_index: base.index as i32,
+                    translation: v.position,
+                })
+            }).collect();
+
+            let panel = match skin.skin_type {
+                PMDSkinType::Eyebrows => PMXMorphPanel::Eyebrows,
+                PMDSkinType::Eyes => PMXMorphPanel::Eyes,
+                PMDSkinType::Mouth => PMXMorphPanel::Mouth,
+                PMDSkinType::Base | PMDSkinType::Other => PMXMorphPanel::Other,
+            };
+
+            morph_indices.insert(i, morphs.len() as i32);
+            morphs.push(PMXMorphData {
+                morph_name_local: skin.skin_name.clone(),
+                morph_name_universal: english.skin_names.get(morphs.len()).cloned().unwrap_or_default(),
+                panel,
+                morph_type: PMXMorphType::Vertex,
+                offsets: PMXMorphOffsetData::Vertex(offsets),
+            });
+        }
+
+        let mut display_frames = Vec::new();
+        display_frames.push(PMXDisplayFrameData {
+            display_name_local: String::from("Root"),
+            display_name_universal: String::from("Root"),
+            is_special: true,
+            frames: if bones.is_empty() { Vec::new() } else { vec![PMXFrameData::Bone(0)] },
+        });
+        display_frames.push(PMXDisplayFrameData {
+            display_name_local: String::from("表情"),
+            display_name_universal: String::from("Exp"),
+            is_special: true,
+            frames: self.skin_display_list.iter()
+                .filter_map(|&i| morph_indices.get(&(i as usize)))
+                .map(|&i| PMXFrameData::Morph(i))
+                .collect(),
+        });
+        for (i, name) in self.bone_display_names.iter().enumerate() {
+            let frame_index = i + 1;
+            display_frames.push(PMXDisplayFrameData {
+                display_name_local: name.trim_end().to_string(),
+                display_name_universal: english.bone_display_names.get(i)
+                    .map(|n| n.trim_end().to_string())
+                    .unwrap_or_default(),
+                is_special: false,
+                frames: self.bone_display_list.iter()
+                    .filter(|d| d.frame_index as usize == frame_index)
+                    .map(|d| PMXFrameData::Bone(d.bone_index as i32))
+                    .collect(),
+            });
+        }
+
+        let rigidbodies = self.rigidbodies.iter().map(|rb| {
+            // positions are relative to the bone, rigid bodies without a bone follow the first bone
+            let bone = if rb.bone_index == PMD_NO_BONE { 0 } else { rb.bone_index as usize };
+            let bone_position = self.bones.get(bone).map(|b| b.position).unwrap_or([0.0; 3]);
+            PMXRigidBodyData {
+                rigidbody_name_local: rb.rigidbody_name.clone(),
+                rigidbody_name_universal: String::new(),
+                bone_index: bone_index(rb.bone_index),
+                group_id: rb.group_id,
+                non_collision_mask: rb.non_collision_mask,
+                shape: rb.shape,
+                shape_size: rb.shape_size,
+                shape_position: [
+                    rb.shape_position[0] + bone_position[0],
+                    rb.shape_position[1] + bone_position[1],
+                    rb.shape_position[2] + bone_position[2],
+                ],
+                shape_rotation: rb.shape_rotation,
+                mass: rb.mass,
+                move_attenuation: rb.move_attenuation,
+                rotation_damping: rb.rotation_damping,
+                repulsion: rb.repulsion,
+                friction_force: rb.friction_force,
+                physics_mode: rb.physics_mode,
+            }
+        }).collect();
+
+        let joints = self.joints.iter().map(|joint| PMXJointData {
+            joint_name_local: joint.joint_name.clone(),
+            joint_name_universal: String::new(),
+            joint_type: PMXJointType::Spring6DOF,
+            rigidbody_index_a: joint.rigidbody_index_a as i32,
+            rigidbody_index_b: joint.rigidbody_index_b as i32,
+            position: joint.position,
+            rotation: joint.rotation,
+            position_min: joint.position_min,
+            position_max: joint.position_max,
+            rotation_min: joint.rotation_min,
+            rotation_max: joint.rotation_max,
+            position_spring: joint.position_spring,
+            rotation_spring: joint.rotation_spring,
+        }).collect();
+
+        PMXFormat {
+            header,
+            globals,
+            vertices,
+            surfaces,
+            texture_paths,
+            materials,
+            bones,
+            morphs,
+            display_frames,
+            rigidbodies,
+            joints,
+            soft_bodies: Vec::new(),
+
+            model_path,
+            toon_directory: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PmdParser {
+
+}
+
+impl PmdParser {
+    pub fn new() -> Self {
+        PmdParser {  }
+    }
+}
+
+impl PmdParser {
+    /// Parses a model file, textures are resolved relative to the directory containing it
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> PmxLoadResult<PMXFormat> {
+        let path = path.as_ref();
+        let model_path = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let file = File::open(path)?;
+        self.parse_reader(file, model_path)
+    }
+
+    /// Parses a model from any reader, textures are resolved relative to `model_path`
+    pub fn parse_reader<R: Read>(&self, mut reader: R, model_path: PathBuf) -> PmxLoadResult<PMXFormat> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let result = self.parse(&data, model_path)?;
+        Ok(result)
+    }
+
+    /// Parses a PMD model and converts it to PMX
+    pub fn parse(&self, data: &[u8], model_path: PathBuf) -> PmxResult<PMXFormat> {
+        let pmd = self.parse_pmd(data)?;
+        Ok(pmd.to_pmx_format(model_path))
+    }
+
+    pub fn parse_pmd(&self, data: &[u8]) -> PmxResult<PMDFormat> {
+        let mut cursor: usize = 0;
+
+        let section_error = |section: PmxSection| move |e: ReadError| e.in_section(section, None);
+        let out_of_range = |field: &'static str, index: usize, count: usize, offset: usize, section: PmxSection, element: usize| {
+            PmxError::new(
+                PmxErrorKind::IndexOutOfRange { field, index: index as i64, count },
+                offset,
+                section,
+                Some(element)
+            )
+        };
+
+        let signature = utils::read::<[i8; 3]>(data, &mut cursor).map_err(section_error(PmxSection::Header))?;
+        if signature != PMD_SIGNATURE {
+            let signature = [signature[0], signature[1], signature[2], 0];
+            return Err(PmxError::new(PmxErrorKind::InvalidSignature(signature), 0, PmxSection::Header, None));
+        }
+        let header = PMDHeader::parse(data, &mut cursor).map_err(section_error(PmxSection::Header))?;
+
+        let vertex_count = utils::read::<u32>(data, &mut cursor).map_err(section_error(PmxSection::Vertices))?;
+        let mut vertices = Vec::new();
+        for i in 0..vertex_count as usize {
+            vertices.push(PMDVertexData::parse(data, &mut cursor)
+                .map_err(|e| e.in_section(PmxSection::Vertices, Some(i)))?);
+        }
+
+        let index_count = utils::read::<u32>(data, &mut cursor).map_err(section_error(PmxSection::Surfaces))? as usize;
+        if !index_count.is_multiple_of(3) {
+            return Err(ReadError::new(utils::ReadErrorKind::InvalidLength(index_count as i64), cursor - 4)
+                .in_section(PmxSection::Surfaces, None));
+        }
+        let index_offset = cursor;
+        let indices = utils::read_var::<u16>(data, &mut cursor, index_count).map_err(section_error(PmxSection::Surfaces))?;
+        for (i, &index) in indices.iter().enumerate() {
+            if index as usize >= vertices.len() {
+                return Err(out_of_range("vertex index", index as usize, vertices.len(), index_offset + i * 2, PmxSection::Surfaces, i / 3));
+            }
+        }
+
+        let material_count = utils::read::<u32>(data, &mut cursor).map_err(section_error(PmxSection::Materials))?;
+        let mut materials = Vec::new();
+        let mut material_index_total: usize = 0;
+        for i in 0..material_count as usize {
+            let offset = cursor;
+            let material = PMDMaterialData::parse(data, &mut cursor)
+                .map_err(|e| e.in_section(PmxSection::Materials, Some(i)))?;
+            material_index_total += material.surface_count as usize;
+            if material_index_total > index_count {
+                return Err(out_of_range("material index end", material_index_total, index_count, offset, PmxSection::Materials, i));
+            }
+            materials.push(material);
+        }
+
+        let bone_count = utils::read::<u16>(data, &mut cursor).map_err(section_error(PmxSection::Bones))?;
+        let mut bones = Vec::new();
+        for i in 0..bone_count as usize {
+            bones.push(PMDBoneData::parse(data, &mut cursor)
+                .map_err(|e| e.in_section(PmxSection::Bones, Some(i)))?);
+        }
+
+        let ik_count = utils::read::<u16>(data, &mut cursor).map_err(section_error(PmxSection::IkChains))?;
+        let mut iks = Vec::new();
+        for i in 0..ik_count as usize {
+            let offset = cursor;
+            let ik = PMDIKData::parse(data, &mut cursor)
+                .map_err(|e| e.in_section(PmxSection::IkChains, Some(i)))?;
+            if ik.ik_bone_index as usize >= bones.len() {
+                return Err(out_of_range("IK bone index", ik.ik_bone_index as usize, bones.len(), offset, PmxSection::IkChains, i));
+            }
+            iks.push(ik);
+        }
+
+        let skin_count = utils::read::<u16>(data, &mut cursor).map_err(section_error(PmxSection::Morphs))?;
+        let mut skins: Vec<PMDSkinData> = Vec::new();
+        let mut base_skin_size: usize = 0;
+        for i in 0..skin_count as usize {
+            let offset = cursor;
+            let skin = PMDSkinData::parse(data, &mut cursor)
+                .map_err(|e| e.in_section(PmxSection::Morphs, Some(i)))?;
+            // the base skin comes first, later skins index into it
+            let (field, count) = if skin.skin_type == PMDSkinType::Base {
+                base_skin_size = skin.vertices.len();
+                ("vertex index", vertices.len())
+            } else {
+                ("base skin index", base_skin_size)
+            };
+            if let Some(v) = skin.vertices.iter().find(|v| v.index as usize >= count) {
+                return Err(out_of_range(field, v.index as usize, count, offset, PmxSection::Morphs, i));
+            }
+            skins.push(skin);
+        }
+
+        let skin_display_count = utils::read::<u8>(data, &mut cursor).map_err(section_error(PmxSection::DisplayFrames))?;
+        let skin_display_list = utils::read_var::<u16>(data, &mut cursor, skin_display_count as usize)
+            .map_err(section_error(PmxSection::DisplayFrames))?;
+
+        let bone_display_name_count = utils::read::<u8>(data, &mut cursor).map_err(section_error(PmxSection::DisplayFrames))?;
+        let mut bone_display_names = Vec::new();
+        for i in 0..bone_display_name_count as usize {
+            bone_display_names.push(utils::read_sjis(data, &mut cursor, 50)
+                .map_err(|e| e.in_section(PmxSection::DisplayFrames, Some(i)))?);
+        }
+
+        let bone_display_count = utils::read::<u32>(data, &mut cursor).map_err(section_error(PmxSection::DisplayFrames))?;
+        let mut bone_display_list = Vec::new();
+        for i in 0..bone_display_count as usize {
+            let read_display = |cursor: &mut usize| -> utils::ReadResult<PMDBoneDisplayData> {
+                Ok(PMDBoneDisplayData {
+                    bone_index: utils::read::<u16>(data, cursor)?,
+                    frame_index: utils::read::<u8>(data, cursor)?,
+                })
+            };
+            bone_display_list.push(read_display(&mut cursor)
+                .map_err(|e| e.in_section(PmxSection::DisplayFrames, Some(i)))?);
+        }
+
+        // everything after this point was added by later versions of MMD and may be missing
+        let mut english_names = None;
+        if cursor < data.len() {
+            let has_english = utils::read::<u8>(data, &mut cursor).map_err(section_error(PmxSection::Header))?;
+            if has_english != 0 {
+                let read_english = |cursor: &mut usize| -> utils::ReadResult<PMDEnglishNames> {
+                    let model_name = utils::read_sjis(data, cursor, 20)?;
+                    let comment = utils::read_sjis(data, cursor, 256)?;
+                    let mut bone_names = Vec::new();
+                    for _ in 0..bones.len() {
+                        bone_names.push(utils::read_sjis(data, cursor, 20)?);
+                    }
+                    let mut skin_names = Vec::new();
+                    for _ in 0..skins.len().saturating_sub(1) {
+                        skin_names.push(utils::read_sjis(data, cursor, 20)?);
+                    }
+                    let mut display_names = Vec::new();
+                    for _ in 0..bone_display_names.len() {
+                        display_names.push(utils::read_sjis(data, cursor, 50)?);
+                    }
+                    Ok(PMDEnglishNames {
+                        model_name,
+                        comment,
+                        bone_names,
+                        skin_names,
+                        bone_display_names: display_names,
+                    })
+                };
+                english_names = Some(read_english(&mut cursor).map_err(section_error(PmxSection::Header))?);
+            }
+        }
+
+        let mut toon_texture_names = Vec::new();
+        if cursor < data.len() {
+            for i in 0..10 {
+                toon_texture_names.push(utils::read_sjis(data, &mut cursor, 100)
+                    .map_err(|e| e.in_section(PmxSection::ToonTextures, Some(i)))?);
+            }
+        }
+
+        let mut rigidbodies = Vec::new();
+        let mut joints = Vec::new();
+        if cursor < data.len() {
+            let rigidbody_count = utils::read::<u32>(data, &mut cursor).map_err(section_error(PmxSection::RigidBodies))?;
+            for i in 0..rigidbody_count as usize {
+                rigidbodies.push(PMDRigidBodyData::parse(data, &mut cursor)
+                    .map_err(|e| e.in_section(PmxSection::RigidBodies, Some(i)))?);
+            }
+
+            let joint_count = utils::read::<u32>(data, &mut cursor).map_err(section_error(PmxSection::Joints))?;
+            for i in 0..joint_count as usize {
+                joints.push(PMDJointData::parse(data, &mut cursor)
+                    .map_err(|e| e.in_section(PmxSection::Joints, Some(i)))?);
+            }
+        }
+
+        Ok(PMDFormat {
+            header,
+            vertices,
+            indices,
+            materials,
+            bones,
+            iks,
+            skins,
+            skin_display_list,
+            bone_display_names,
+            bone_display_list,
+            english_names,
+            toon_texture_names,
+            rigidbodies,
+            joints,
+        })
+    }
+}
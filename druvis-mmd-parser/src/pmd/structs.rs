@@ -0,0 +1,316 @@
+use crate::{utils::{self, ReadResult, ReadError}, pmx::structs::{PMXRigidBodyShape, PMXPhysicsMode}};
+
+pub const PMD_SIGNATURE: [i8; 3] = [b'P' as i8, b'm' as i8, b'd' as i8];
+
+// sentinel for "no bone" in 16 bit bone indices
+pub const PMD_NO_BONE: u16 = 0xffff;
+
+#[derive(Debug, Clone)]
+pub struct PMDHeader {
+    pub version: f32,
+    pub model_name: String,
+    pub comment: String,
+}
+
+impl PMDHeader {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            version: utils::read::<f32>(data, cursor)?,
+            model_name: utils::read_sjis(data, cursor, 20)?,
+            comment: utils::read_sjis(data, cursor, 256)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDVertexData {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    pub bone_index: [u16; 2],
+    // weight of the first bone, 0 to 100
+    pub bone_weight: u8,
+    // 0 draws the edge, 1 disables it
+    pub edge_flag: u8,
+}
+
+impl PMDVertexData {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            position: utils::read::<[f32; 3]>(data, cursor)?,
+            normal: utils::read::<[f32; 3]>(data, cursor)?,
+            uv: utils::read::<[f32; 2]>(data, cursor)?,
+            bone_index: utils::read::<[u16; 2]>(data, cursor)?,
+            bone_weight: utils::read::<u8>(data, cursor)?,
+            edge_flag: utils::read::<u8>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDMaterialData {
+    pub diffuse_color: [f32; 4],
+    pub specular_strength: f32,
+    pub specular_color: [f32; 3],
+    pub ambient_color: [f32; 3],
+    // index into the toon texture list, 0xff for none
+    pub toon_index: u8,
+    pub edge_flag: u8,
+    pub surface_count: u32,
+    // texture and sphere map, separated by '*'
+    pub texture_file_name: String,
+}
+
+impl PMDMaterialData {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            diffuse_color: utils::read::<[f32; 4]>(data, cursor)?,
+            specular_strength: utils::read::<f32>(data, cursor)?,
+            specular_color: utils::read::<[f32; 3]>(data, cursor)?,
+            ambient_color: utils::read::<[f32; 3]>(data, cursor)?,
+            toon_index: utils::read::<u8>(data, cursor)?,
+            edge_flag: utils::read::<u8>(data, cursor)?,
+            surface_count: utils::read::<u32>(data, cursor)?,
+            texture_file_name: utils::read_sjis(data, cursor, 20)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PMDBoneType {
+    Rotate,
+    RotateAndMove,
+    IK,
+    Unknown,
+    IKAffected,
+    RotationAffected,
+    IKTarget,
+    Invisible,
+    Twist,
+    RotationFollow,
+}
+
+impl PMDBoneType {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<u8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::Rotate,
+            1 => Self::RotateAndMove,
+            2 => Self::IK,
+            3 => Self::Unknown,
+            4 => Self::IKAffected,
+            5 => Self::RotationAffected,
+            6 => Self::IKTarget,
+            7 => Self::Invisible,
+            8 => Self::Twist,
+            9 => Self::RotationFollow,
+            _ => return Err(ReadError::invalid_value("PMD bone type", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDBoneData {
+    pub bone_name: String,
+    pub parent_index: u16,
+    // for RotationFollow bones this is the follow ratio in percent
+    pub tail_index: u16,
+    pub bone_type: PMDBoneType,
+    // IK bone for IKAffected, rotation source for RotationAffected and RotationFollow
+    pub ik_parent_index: u16,
+    pub position: [f32; 3],
+}
+
+impl PMDBoneData {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            bone_name: utils::read_sjis(data, cursor, 20)?,
+            parent_index: utils::read::<u16>(data, cursor)?,
+            tail_index: utils::read::<u16>(data, cursor)?,
+            bone_type: PMDBoneType::parse(data, cursor)?,
+            ik_parent_index: utils::read::<u16>(data, cursor)?,
+            position: utils::read::<[f32; 3]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDIKData {
+    pub ik_bone_index: u16,
+    pub target_bone_index: u16,
+    pub iterations: u16,
+    // rotation limit per iteration, in units of 4 radians
+    pub control_weight: f32,
+    pub child_bone_indices: Vec<u16>,
+}
+
+impl PMDIKData {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let ik_bone_index = utils::read::<u16>(data, cursor)?;
+        let target_bone_index = utils::read::<u16>(data, cursor)?;
+        let chain_length = utils::read::<u8>(data, cursor)?;
+        let iterations = utils::read::<u16>(data, cursor)?;
+        let control_weight = utils::read::<f32>(data, cursor)?;
+        let child_bone_indices = utils::read_var::<u16>(data, cursor, chain_length as usize)?;
+
+        Ok(Self {
+            ik_bone_index,
+            target_bone_index,
+            iterations,
+            control_weight,
+            child_bone_indices
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PMDSkinType {
+    Base,
+    Eyebrows,
+    Eyes,
+    Mouth,
+    Other,
+}
+
+impl PMDSkinType {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<u8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::Base,
+            1 => Self::Eyebrows,
+            2 => Self::Eyes,
+            3 => Self::Mouth,
+            4 => Self::Other,
+            _ => return Err(ReadError::invalid_value("PMD skin type", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDSkinVertexData {
+    // for the base skin this indexes the model vertices, otherwise the base skin vertices
+    pub index: u32,
+    // absolute position for the base skin, otherwise an offset
+    pub position: [f32; 3],
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDSkinData {
+    pub skin_name: String,
+    pub skin_type: PMDSkinType,
+    pub vertices: Vec<PMDSkinVertexData>,
+}
+
+impl PMDSkinData {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let skin_name = utils::read_sjis(data, cursor, 20)?;
+        let vertex_count = utils::read::<u32>(data, cursor)? as usize;
+        let skin_type = PMDSkinType::parse(data, cursor)?;
+
+        let mut vertices = Vec::new();
+        for _ in 0..vertex_count {
+            vertices.push(PMDSkinVertexData {
+                index: utils::read::<u32>(data, cursor)?,
+                position: utils::read::<[f32; 3]>(data, cursor)?,
+            });
+        }
+
+        Ok(Self {
+            skin_name,
+            skin_type,
+            vertices
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDBoneDisplayData {
+    pub bone_index: u16,
+    // 1 based index into the bone display names
+    pub frame_index: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PMDEnglishNames {
+    pub model_name: String,
+    pub comment: String,
+    pub bone_names: Vec<String>,
+    // excludes the base skin
+    pub skin_names: Vec<String>,
+    pub bone_display_names: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDRigidBodyData {
+    pub rigidbody_name: String,
+    pub bone_index: u16,
+    pub group_id: u8,
+    pub non_collision_mask: u16,
+    pub shape: PMXRigidBodyShape,
+    pub shape_size: [f32; 3],
+    // relative to the bone position
+    pub shape_position: [f32; 3],
+    pub shape_rotation: [f32; 3],
+    pub mass: f32,
+    pub move_attenuation: f32,
+    pub rotation_damping: f32,
+    pub repulsion: f32,
+    pub friction_force: f32,
+    pub physics_mode: PMXPhysicsMode,
+}
+
+impl PMDRigidBodyData {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            rigidbody_name: utils::read_sjis(data, cursor, 20)?,
+            bone_index: utils::read::<u16>(data, cursor)?,
+            group_id: utils::read::<u8>(data, cursor)?,
+            non_collision_mask: utils::read::<u16>(data, cursor)?,
+            shape: PMXRigidBodyShape::parse(data, cursor)?,
+            shape_size: utils::read::<[f32; 3]>(data, cursor)?,
+            shape_position: utils::read::<[f32; 3]>(data, cursor)?,
+            shape_rotation: utils::read::<[f32; 3]>(data, cursor)?,
+            mass: utils::read::<f32>(data, cursor)?,
+            move_attenuation: utils::read::<f32>(data, cursor)?,
+            rotation_damping: utils::read::<f32>(data, cursor)?,
+            repulsion: utils::read::<f32>(data, cursor)?,
+            friction_force: utils::read::<f32>(data, cursor)?,
+            physics_mode: PMXPhysicsMode::parse(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PMDJointData {
+    pub joint_name: String,
+    pub rigidbody_index_a: u32,
+    pub rigidbody_index_b: u32,
+    pub position: [f32; 3],
+    pub rotation: [f32; 3],
+    pub position_min: [f32; 3],
+    pub position_max: [f32; 3],
+    pub rotation_min: [f32; 3],
+    pub rotation_max: [f32; 3],
+    pub position_spring: [f32; 3],
+    pub rotation_spring: [f32; 3],
+}
+
+impl PMDJointData {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            joint_name: utils::read_sjis(data, cursor, 20)?,
+            rigidbody_index_a: utils::read::<u32>(data, cursor)?,
+            rigidbody_index_b: utils::read::<u32>(data, cursor)?,
+            position: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation: utils::read::<[f32; 3]>(data, cursor)?,
+            position_min: utils::read::<[f32; 3]>(data, cursor)?,
+            position_max: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation_min: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation_max: utils::read::<[f32; 3]>(data, cursor)?,
+            position_spring: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation_spring: utils::read::<[f32; 3]>(data, cursor)?,
+        })
+    }
+}
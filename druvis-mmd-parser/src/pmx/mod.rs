@@ -1,3 +1,6 @@
 pub mod pmx_parser;
+pub mod pmx_writer;
+pub mod pmx_error;
 pub mod structs;
-
+pub mod shared_toon;
+pub mod texture_path;
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::utils::{ReadError, ReadErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PmxSection {
+    Header,
+    Vertices,
+    Surfaces,
+    Textures,
+    Materials,
+    Bones,
+    Morphs,
+    DisplayFrames,
+    RigidBodies,
+    Joints,
+    SoftBodies,
+    // PMD only
+    IkChains,
+    ToonTextures,
+}
+
+impl fmt::Display for PmxSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            Self::Header => "header",
+            Self::Vertices => "vertices",
+            Self::Surfaces => "surfaces",
+            Self::Textures => "textures",
+            Self::Materials => "materials",
+            Self::Bones => "bones",
+            Self::Morphs => "morphs",
+            Self::DisplayFrames => "display frames",
+            Self::RigidBodies => "rigid bodies",
+            Self::Joints => "joints",
+            Self::SoftBodies => "soft bodies",
+            Self::IkChains => "IK chains",
+            Self::ToonTextures => "toon textures",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PmxErrorKind {
+    Read(ReadErrorKind),
+    InvalidSignature([i8; 4]),
+    UnsupportedVersion(f32),
+    IndexOutOfRange { field: &'static str, index: i64, count: usize },
+}
+
+impl fmt::Display for PmxErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(kind) => write!(f, "{}", kind),
+            Self::InvalidSignature(signature) => write!(f, "invalid PMX signature {:?}", signature),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported PMX version {}, only 2.0 and 2.1 are supported", version),
+            Self::IndexOutOfRange { field, index, count } => write!(f, "{} {} out of range, count is {}", field, index, count),
+        }
+    }
+}
+
+/// A PMX parse failure, `index` is the element within `section` that failed, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct PmxError {
+    pub kind: PmxErrorKind,
+    pub offset: usize,
+    pub section: PmxSection,
+    pub index: Option<usize>,
+}
+
+pub type PmxResult<T> = Result<T, PmxError>;
+
+impl PmxError {
+    pub fn new(kind: PmxErrorKind, offset: usize, section: PmxSection, index: Option<usize>) -> Self {
+        Self {
+            kind,
+            offset,
+            section,
+            index
+        }
+    }
+}
+
+impl fmt::Display for PmxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "{} at byte {} (section {}, element {})", self.kind, self.offset, self.section, index),
+            None => write!(f, "{} at byte {} (section {})", self.kind, self.offset, self.section),
+        }
+    }
+}
+
+impl std::error::Error for PmxError {}
+
+impl ReadError {
+    pub fn in_section(self, section: PmxSection, index: Option<usize>) -> PmxError {
+        PmxError::new(PmxErrorKind::Read(self.kind), self.offset, section, index)
+    }
+}
+
+/// Failure when loading a PMX model from a file or reader
+#[derive(Debug)]
+pub enum PmxLoadError {
+    Io(std::io::Error),
+    Parse(PmxError),
+}
+
+pub type PmxLoadResult<T> = Result<T, PmxLoadError>;
+
+impl fmt::Display for PmxLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read PMX data: {}", e),
+            Self::Parse(e) => write!(f, "failed to parse PMX data: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PmxLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for PmxLoadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<PmxError> for PmxLoadError {
+    fn from(e: PmxError) -> Self {
+        Self::Parse(e)
+    }
+}
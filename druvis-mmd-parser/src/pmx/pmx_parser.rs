@@ -1,9 +1,141 @@
-use std::{mem, collections::HashMap, path::PathBuf, rc::Rc, cell::RefCell};
-use anyhow::Result;
-use druvis_core::{mesh::mesh::DruvisMesh, vertex::vertex::ModelVertex, material::material::DruvisMaterial, texture::texture::DruvisTextureAndSampler, shader::shader_manager::ShaderManager, game_object::{DruvisGameObject, DruvisComponent, components::MeshRendererData, game_object::DruvisGameObjectExt}};
-use crate::{utils, pmx::structs::{PMXVertexData, PMXMaterialData}};
+use std::{mem, collections::HashMap, path::{PathBuf, Path}, rc::Rc, cell::RefCell, io::Read, fs::File};
+use cgmath::{Vector3, Vector4, Quaternion, Rotation3, Rad};
+use druvis_core::{mesh::mesh::DruvisMesh, vertex::vertex::{SkinnedModelVertex, DEFORM_TYPE_SDEF, DEFORM_TYPE_QDEF}, rendering::{skinning::SkinningBindState, morph::{MorphTargets, MorphOffset}}, material::material::DruvisMaterial, texture::texture::DruvisTextureAndSampler, shader::{shader_manager::ShaderManager, shader_property::ShaderPropertyValue}, game_object::{DruvisGameObject, DruvisComponent, components::{MeshRendererData, SkeletonData, Bone, BoneInherit, IkChain, IkLink, MorphControllerData, MaterialMorphOffset, MaterialMorphMethod, PhysicsData, PhysicsMode, RigidBodyBinding}, game_object::DruvisGameObjectExt}};
+use crate::{utils::{self, ReadError, ReadErrorKind}, pmx::structs::{PMXVertexData, PMXWeightDeformData, SDEFData, PMXMaterialData, PMXMaterialFlags, PMXBoneData, PMXBoneFlags, PMXMorphData, PMXMorphType, PMXMorphOffsetData, PMXMaterialMorphOffset, PMXMaterialMorphMethod, PMXDisplayFrameData, PMXEnvironmentBlendMode, PMXToonValue, PMXRigidBodyData, PMXRigidBodyShape, PMXPhysicsMode, PMXJointData}};
+use druvis_physics::{PhysicsWorld, RigidBody, Shape, SpringJoint};
 
-use super::structs::{PMXHeaderRaw, PMXGlobalsRaw, PMXGlobals, PMXHeader, PMXSurfaceData};
+use super::{shared_toon, texture_path::{self, TextureWarning, TextureWarningKind}, structs::{PMXHeaderRaw, PMXGlobals, PMXHeader, PMXSurfaceData, PMXVersion, PMXSoftBodyData, PMX_SIGNATURE}, pmx_error::{PmxResult, PmxError, PmxErrorKind, PmxSection, PmxLoadResult}};
+
+/// PMX 2.1 materials can draw their vertices as points or their triangle edges as lines
+pub fn material_topology(flags: PMXMaterialFlags) -> wgpu::PrimitiveTopology {
+    if flags.contains(PMXMaterialFlags::POINT_DRAWING) {
+        wgpu::PrimitiveTopology::PointList
+    } else if flags.contains(PMXMaterialFlags::LINE_DRAWING) {
+        wgpu::PrimitiveTopology::LineList
+    } else {
+        wgpu::PrimitiveTopology::TriangleList
+    }
+}
+
+/// The shader's cull mode, unless the material is double sided or not drawn as triangles
+pub fn material_cull_mode(flags: PMXMaterialFlags, shader_cull_mode: Option<wgpu::Face>) -> Option<wgpu::Face> {
+    if flags.contains(PMXMaterialFlags::NO_CULL) || material_topology(flags) != wgpu::PrimitiveTopology::TriangleList {
+        None
+    } else {
+        shader_cull_mode
+    }
+}
+
+/// Index buffer and the index range of every material, line drawing materials get each triangle edge as a line
+pub fn material_indices(materials: &[PMXMaterialData], surfaces: &[PMXSurfaceData]) -> (Vec<u32>, Vec<(u64, u64)>) {
+    let mut indices: Vec<u32> = Vec::new();
+    let mut submeshes: Vec<(u64, u64)> = Vec::new();
+
+    let mut surfaces = surfaces.iter();
+    for mat in materials.iter() {
+        let start = indices.len() as u64;
+        let line_drawing = material_topology(mat.drawing_flags) == wgpu::PrimitiveTopology::LineList;
+        for surface in surfaces.by_ref().take(mat.surface_count.max(0) as usize / 3) {
+            let [a, b, c] = surface.triangle.map(|i| i as u32);
+            // a line list needs every edge of the triangle as its own pair
+            if line_drawing {
+                indices.extend_from_slice(&[a, b, b, c, c, a]);
+            } else {
+                indices.extend_from_slice(&[a, b, c]);
+            }
+        }
+        submeshes.push((start, indices.len() as u64));
+    }
+    // surfaces past the last material
+    for surface in surfaces {
+        indices.extend(surface.triangle.map(|i| i as u32));
+    }
+
+    (indices, submeshes)
+}
+
+// vec4 shader properties of a material, named like druvis.mmd
+fn material_properties(mat: &PMXMaterialData) -> Vec<(String, Vector4<f32>)> {
+    let [sr, sg, sb] = mat.specular_color;
+    let [ar, ag, ab] = mat.ambient_color;
+    vec![
+        (String::from("diffuse"), mat.diffuse_color.into()),
+        (String::from("specular"), Vector4::new(sr, sg, sb, mat.specular_strength)),
+        (String::from("ambient"), Vector4::new(ar, ag, ab, 0.0)),
+        (String::from("edge_color"), mat.edge_color.into()),
+        (String::from("edge_size"), Vector4::new(mat.edge_scale, 0.0, 0.0, 0.0)),
+        // tints only change through material morphs
+        (String::from("texture_mul"), Vector4::new(1.0, 1.0, 1.0, 1.0)),
+        (String::from("texture_add"), Vector4::new(0.0, 0.0, 0.0, 0.0)),
+        (String::from("sphere_mul"), Vector4::new(1.0, 1.0, 1.0, 1.0)),
+        (String::from("sphere_add"), Vector4::new(0.0, 0.0, 0.0, 0.0)),
+        (String::from("toon_mul"), Vector4::new(1.0, 1.0, 1.0, 1.0)),
+        (String::from("toon_add"), Vector4::new(0.0, 0.0, 0.0, 0.0)),
+    ]
+}
+
+fn white_texture(device: &wgpu::Device, queue: &wgpu::Queue, sampler_desc: &wgpu::SamplerDescriptor) -> DruvisTextureAndSampler {
+    DruvisTextureAndSampler::new_2d(
+        device,
+        queue,
+        &[255, 255, 255, 255],
+        wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        sampler_desc,
+        wgpu::SamplerBindingType::Filtering,
+        "white"
+    )
+}
+
+// PMX euler angles are applied in Y, X, Z order
+fn euler_rotation(angles: [f32; 3]) -> Quaternion<f32> {
+    Quaternion::from_angle_y(Rad(angles[1])) * Quaternion::from_angle_x(Rad(angles[0])) * Quaternion::from_angle_z(Rad(angles[2]))
+}
+
+fn rigid_body(data: &PMXRigidBodyData) -> RigidBody {
+    let [x, y, z] = data.shape_size;
+    let shape = match data.shape {
+        PMXRigidBodyShape::Sphere => Shape::Sphere { radius: x },
+        PMXRigidBodyShape::Box => Shape::Box { half_extents: Vector3::new(x, y, z) },
+        PMXRigidBodyShape::Capsule => Shape::Capsule { radius: x, height: y },
+    };
+    let mut body = RigidBody::new(shape, data.mass, data.shape_position.into(), euler_rotation(data.shape_rotation));
+    body.linear_damping = data.move_attenuation;
+    body.angular_damping = data.rotation_damping;
+    body.restitution = data.repulsion;
+    body.friction = data.friction_force;
+    body.group = data.group_id;
+    body.collision_mask = data.non_collision_mask;
+    body.kinematic = data.physics_mode == PMXPhysicsMode::FollowBone;
+    body
+}
+
+// one PMX material morph offset as per property offsets
+fn material_morph_offsets(offset: &PMXMaterialMorphOffset) -> Vec<MaterialMorphOffset> {
+    let material = if offset.material_index >= 0 { Some(offset.material_index as usize) } else { None };
+    let (method, unused, suffix) = match offset.method {
+        PMXMaterialMorphMethod::Multiply => (MaterialMorphMethod::Multiply, 1.0, "_mul"),
+        PMXMaterialMorphMethod::Additive => (MaterialMorphMethod::Add, 0.0, "_add"),
+    };
+    let [sr, sg, sb] = offset.specular_color;
+    let [ar, ag, ab] = offset.ambient_color;
+
+    [
+        (String::from("diffuse"), offset.diffuse_color.into()),
+        (String::from("specular"), Vector4::new(sr, sg, sb, offset.specular_strength)),
+        (String::from("ambient"), Vector4::new(ar, ag, ab, unused)),
+        (String::from("edge_color"), offset.edge_color.into()),
+        (String::from("edge_size"), Vector4::new(offset.edge_scale, unused, unused, unused)),
+        (String::from("texture") + suffix, offset.texture_tint.into()),
+        (String::from("sphere") + suffix, offset.environment_tint.into()),
+        (String::from("toon") + suffix, offset.toon_tint.into()),
+    ].into_iter().map(|(property, value)| MaterialMorphOffset {
+        material,
+        property,
+        method,
+        value,
+    }).collect()
+}
 
 #[derive(Clone, Debug)]
 pub struct PMXFormat {
@@ -13,17 +145,33 @@ pub struct PMXFormat {
     pub surfaces: Vec<PMXSurfaceData>,
     pub texture_paths: Vec<String>,
     pub materials: Vec<PMXMaterialData>,
+    pub bones: Vec<PMXBoneData>,
+    pub morphs: Vec<PMXMorphData>,
+    pub display_frames: Vec<PMXDisplayFrameData>,
+    pub rigidbodies: Vec<PMXRigidBodyData>,
+    pub joints: Vec<PMXJointData>,
+    // only present in PMX 2.1
+    pub soft_bodies: Vec<PMXSoftBodyData>,
 
-    model_path: PathBuf,
+    pub(crate) model_path: PathBuf,
+    // shared toons are loaded from here when present instead of the built-in ramps
+    pub toon_directory: Option<PathBuf>,
 }
 
 impl PMXFormat {
+    /// Directory that texture paths are relative to
+    pub fn model_path(&self) -> &Path {
+        &self.model_path
+    }
+
+    /// Textures that can not be loaded are sampled as white and reported in `texture_warnings`
     pub fn create_game_object(
         self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         shader_manager: &ShaderManager,
         builtin_bind_group_layouts: &[&wgpu::BindGroupLayout],
+        texture_warnings: &mut Vec<TextureWarning>,
     ) -> Rc<RefCell<DruvisGameObject>> {
         println!("vertex count: {}", self.vertices.len());
         println!("index count: {}", self.surfaces.len() * 3);
@@ -34,20 +182,267 @@ impl PMXFormat {
         let mesh = self.clone().to_druvis_mesh(device);
         let mut mesh_renderer = DruvisComponent::<MeshRendererData>::default();
         mesh_renderer.data.mesh = Some(Rc::new(RefCell::new(mesh)));
+        mesh_renderer.data.skinning = Some(SkinningBindState::new(
+            device,
+            self.bones.len(),
+            &self.create_morph_targets(),
+            &self.header.model_name_local
+        ));
 
         let mut mats = Vec::new();
         let material_count = self.materials.len();
         for i in 0..material_count {
-            let mat = self.create_material(device, queue, i, shader_manager, builtin_bind_group_layouts);
+            let mat = self.create_material(device, queue, i, shader_manager, builtin_bind_group_layouts, texture_warnings);
             mats.push(Rc::new(RefCell::new(mat.unwrap())));
         }
         mesh_renderer.data.materials = mats;
+        mesh_renderer.data.edge_materials = (0..material_count)
+            .map(|i| self.create_edge_material(device, i, shader_manager, builtin_bind_group_layouts).map(|m| Rc::new(RefCell::new(m))))
+            .collect();
 
         go.add_component(mesh_renderer);
+        go.add_component(DruvisComponent::new(self.create_skeleton()));
+        go.add_component(DruvisComponent::new(self.create_morph_controller()));
+        go.add_component(DruvisComponent::new(self.create_physics()));
 
         go
     }
 
+    /// Vertex and base UV morphs as GPU morph targets, indexed like `self.morphs`
+    pub fn create_morph_targets(&self) -> MorphTargets {
+        let mut offsets = Vec::new();
+        for (i, morph) in self.morphs.iter().enumerate() {
+            match &morph.offsets {
+                PMXMorphOffsetData::Vertex(vertex_offsets) => {
+                    for o in vertex_offsets.iter() {
+                        offsets.push((o.vertex_index as usize, MorphOffset::new(i, o.translation, [0.0; 4])));
+                    }
+                },
+                // additional UVs have no vertex attribute to apply to
+                PMXMorphOffsetData::UV(uv_offsets) if morph.morph_type == PMXMorphType::UV => {
+                    for o in uv_offsets.iter() {
+                        offsets.push((o.vertex_index as usize, MorphOffset::new(i, [0.0; 3], o.offset)));
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        MorphTargets::new(self.vertices.len(), self.morphs.len(), offsets)
+    }
+
+    pub fn create_morph_controller(&self) -> MorphControllerData {
+        let mut controller = MorphControllerData::new(
+            self.morphs.iter().map(|m| m.morph_name_local.clone()).collect()
+        );
+        for (i, morph) in self.morphs.iter().enumerate() {
+            if let PMXMorphOffsetData::Group(group) = &morph.offsets {
+                controller.groups[i] = group.iter()
+                    .filter(|o| o.morph_index >= 0)
+                    .map(|o| (o.morph_index as usize, o.influence))
+                    .collect();
+            }
+            if let PMXMorphOffsetData::Material(offsets) = &morph.offsets {
+                controller.material_offsets[i] = offsets.iter().flat_map(material_morph_offsets).collect();
+            }
+        }
+        controller.material_base = self.materials.iter().map(material_properties).collect();
+        controller
+    }
+
+    pub fn create_skeleton(&self) -> SkeletonData {
+        let bones = self.bones.iter().map(|b| {
+            let parent = if b.parent_index >= 0 { Some(b.parent_index as usize) } else { None };
+            let mut bone = Bone::new(&b.bone_name_local, parent, b.position.into());
+            bone.deform_layer = b.layer;
+            bone.deform_after_physics = b.flags.contains(PMXBoneFlags::PHYSICS_AFTER_DEFORM);
+            bone.ik = b.ik.as_ref().filter(|ik| ik.target_index >= 0).map(|ik| IkChain {
+                target: ik.target_index as usize,
+                loop_count: ik.loop_count.max(0) as u32,
+                limit_angle: ik.limit_angle,
+                links: ik.links.iter()
+                    .filter(|l| l.bone_index >= 0)
+                    .map(|l| IkLink::new(
+                        l.bone_index as usize,
+                        l.angle_limit.as_ref().map(|a| (a.min.into(), a.max.into()))
+                    ))
+                    .collect(),
+            });
+            bone.inherit = b.inherit.as_ref().filter(|h| h.parent_index >= 0).map(|h| BoneInherit {
+                parent: h.parent_index as usize,
+                ratio: h.ratio,
+                rotation: b.flags.contains(PMXBoneFlags::INHERIT_ROTATION),
+                translation: b.flags.contains(PMXBoneFlags::INHERIT_TRANSLATION),
+                local: b.flags.contains(PMXBoneFlags::INHERIT_LOCAL),
+            });
+            bone
+        }).collect::<Vec<_>>();
+
+        SkeletonData::new(bones)
+    }
+
+    /// Rigid bodies and joints in the bind pose, bodies are indexed like `self.rigidbodies`
+    pub fn create_physics(&self) -> PhysicsData {
+        let mut world = PhysicsWorld::new();
+        let mut bindings = Vec::new();
+        for data in self.rigidbodies.iter() {
+            let body = rigid_body(data);
+            bindings.push(RigidBodyBinding {
+                bone: if data.bone_index >= 0 { Some(data.bone_index as usize) } else { None },
+                mode: match data.physics_mode {
+                    PMXPhysicsMode::FollowBone => PhysicsMode::FollowBone,
+                    PMXPhysicsMode::Physics => PhysicsMode::Physics,
+                    PMXPhysicsMode::PhysicsAndBone => PhysicsMode::PhysicsWithBonePosition,
+                },
+                offset: body.transform(),
+            });
+            world.add_body(body);
+        }
+
+        let body_count = world.bodies.len();
+        for data in self.joints.iter() {
+            let (a, b) = (data.rigidbody_index_a, data.rigidbody_index_b);
+            if a < 0 || b < 0 || a as usize >= body_count || b as usize >= body_count || a == b {
+                continue;
+            }
+            let mut joint = SpringJoint::new(a as usize, b as usize, &world.bodies, data.position.into(), euler_rotation(data.rotation));
+            joint.linear_lower = data.position_min.into();
+            joint.linear_upper = data.position_max.into();
+            joint.angular_lower = data.rotation_min.into();
+            joint.angular_upper = data.rotation_max.into();
+            joint.linear_stiffness = data.position_spring.into();
+            joint.angular_stiffness = data.rotation_spring.into();
+            world.add_joint(joint);
+        }
+
+        PhysicsData::new(world, bindings)
+    }
+
+    /// File of texture `index` in `self.texture_paths`, None for -1 which means no texture
+    pub fn resolve_texture(&self, index: i32) -> Result<Option<PathBuf>, TextureWarning> {
+        if index < 0 {
+            return Ok(None);
+        }
+        let raw = self.texture_paths.get(index as usize).ok_or(TextureWarning {
+            texture_index: index,
+            path: String::new(),
+            kind: TextureWarningKind::IndexOutOfRange { count: self.texture_paths.len() },
+        })?;
+        match texture_path::resolve_texture_path(&self.model_path, raw) {
+            Some(path) => Ok(Some(path)),
+            None => Err(TextureWarning {
+                texture_index: index,
+                path: raw.clone(),
+                kind: TextureWarningKind::NotFound,
+            }),
+        }
+    }
+
+    /// Texture references of the materials that can not be found, without loading any image.
+    /// Files that exist but fail to decode are only reported by `create_material`
+    pub fn texture_warnings(&self) -> Vec<TextureWarning> {
+        let mut warnings: Vec<TextureWarning> = Vec::new();
+        for mat in self.materials.iter() {
+            let toon = match mat.toon_value {
+                PMXToonValue::Texture(index) => index,
+                PMXToonValue::Internal(_) => -1,
+            };
+            for index in [mat.texture_index, mat.environment_index, toon] {
+                if let Err(warning) = self.resolve_texture(index) {
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Texture `index` of `self.texture_paths`, None for -1 and for textures that can not
+    /// be loaded, which are pushed to `warnings` and left to the caller's fallback
+    fn load_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: i32,
+        sampler_desc: &wgpu::SamplerDescriptor,
+        warnings: &mut Vec<TextureWarning>,
+    ) -> Option<DruvisTextureAndSampler> {
+        let texture = self.resolve_texture(index).and_then(|path| match path {
+            Some(path) => DruvisTextureAndSampler::from_path(
+                device,
+                queue,
+                &path,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                sampler_desc,
+                wgpu::SamplerBindingType::Filtering
+            ).map(Some).map_err(|e| TextureWarning {
+                texture_index: index,
+                path: self.texture_paths[index as usize].clone(),
+                kind: TextureWarningKind::Unreadable(e.to_string()),
+            }),
+            None => Ok(None),
+        });
+        match texture {
+            Ok(texture) => texture,
+            Err(warning) => {
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+                None
+            },
+        }
+    }
+
+    /// MMD's shared toon `index`, from `self.toon_directory` when it has the file,
+    /// otherwise the built-in ramp. A file that can not be read is pushed to `warnings`
+    fn load_shared_toon(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: i8,
+        sampler_desc: &wgpu::SamplerDescriptor,
+        warnings: &mut Vec<TextureWarning>,
+    ) -> Option<DruvisTextureAndSampler> {
+        let index = usize::try_from(index).ok().filter(|&i| i < shared_toon::SHARED_TOON_COUNT)?;
+        let toon_path = self.toon_directory.as_ref()
+            .and_then(|d| texture_path::resolve_texture_path(d, &shared_toon::file_name(index)));
+        if let Some(toon_path) = toon_path {
+            let texture = DruvisTextureAndSampler::from_path(
+                device,
+                queue,
+                &toon_path,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                sampler_desc,
+                wgpu::SamplerBindingType::Filtering
+            );
+            match texture {
+                Ok(texture) => return Some(texture),
+                Err(e) => {
+                    let warning = TextureWarning {
+                        texture_index: -1,
+                        path: toon_path.to_string_lossy().into_owned(),
+                        kind: TextureWarningKind::Unreadable(e.to_string()),
+                    };
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                },
+            }
+        }
+
+        Some(DruvisTextureAndSampler::new_2d(
+            device,
+            queue,
+            &shared_toon::pixels(index)?,
+            wgpu::Extent3d { width: 1, height: shared_toon::SHARED_TOON_HEIGHT, depth_or_array_layers: 1 },
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            sampler_desc,
+            wgpu::SamplerBindingType::Filtering,
+            &shared_toon::file_name(index)
+        ))
+    }
+
     pub fn create_material(
         &self,
         device: &wgpu::Device,
@@ -55,68 +450,139 @@ impl PMXFormat {
         mat_index: usize,
         shader_manager: &ShaderManager,
         builtin_bind_group_layouts: &[&wgpu::BindGroupLayout],
+        texture_warnings: &mut Vec<TextureWarning>,
     ) -> Option<DruvisMaterial> {
         let mat = &self.materials[mat_index];
 
         let mut textures = HashMap::new();
-        let diffuse_texture_path = self.model_path.join(&self.texture_paths[mat.texture_index as usize]);
-        let diffuse_texture = DruvisTextureAndSampler::from_path(
-            device,
-            queue,
-            &diffuse_texture_path,
-            wgpu::TextureFormat::Rgba8UnormSrgb,
-            &wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::ClampToEdge,
-                address_mode_v: wgpu::AddressMode::ClampToEdge,
-                address_mode_w: wgpu::AddressMode::ClampToEdge,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Nearest,
-                mipmap_filter: wgpu::FilterMode::Linear,
-                ..Default::default()
-            },
-            wgpu::SamplerBindingType::Filtering
-        );
-        textures.insert(String::from("albedo_texture"), Rc::new(diffuse_texture));
+        let sampler_desc = wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        };
+        // missing textures, and materials without one (common in PMD models), sample plain white
+        let white = Rc::new(white_texture(device, queue, &sampler_desc));
+        let diffuse_texture = self.load_texture(device, queue, mat.texture_index, &sampler_desc, texture_warnings);
+        textures.insert(String::from("albedo_texture"), diffuse_texture.map(Rc::new).unwrap_or_else(|| white.clone()));
+
+        let sphere_mode = match mat.environment_blend_mode {
+            PMXEnvironmentBlendMode::Disabled => 0.0,
+            PMXEnvironmentBlendMode::Multiply => 1.0,
+            PMXEnvironmentBlendMode::Additive => 2.0,
+            PMXEnvironmentBlendMode::AdditionalVec4 => 3.0,
+        };
+        let sphere_texture = self.load_texture(device, queue, mat.environment_index, &sampler_desc, texture_warnings);
+        // a missing sphere map leaves the color alone
+        let sphere_mode = if sphere_texture.is_some() { sphere_mode } else { 0.0 };
+        textures.insert(String::from("sphere_texture"), sphere_texture.map(Rc::new).unwrap_or_else(|| white.clone()));
 
-        let shader = shader_manager.get_shader(device, builtin_bind_group_layouts, "druvis.albedo")?;
-        let druvis_mat = DruvisMaterial::create_material(
+        let toon_texture = match mat.toon_value {
+            PMXToonValue::Texture(index) => self.load_texture(device, queue, index, &sampler_desc, texture_warnings),
+            PMXToonValue::Internal(index) => self.load_shared_toon(device, queue, index, &sampler_desc, texture_warnings),
+        };
+        textures.insert(String::from("toon_texture"), toon_texture.map(Rc::new).unwrap_or(white));
+
+        let shader = shader_manager.get_shader(device, builtin_bind_group_layouts, "druvis.mmd")?;
+        let mut druvis_mat = DruvisMaterial::create_material(
             device,
             shader,
             textures,
             "mmd_mat"
-        );
+        )?;
+        for (name, value) in material_properties(mat) {
+            druvis_mat.set_property(&name, ShaderPropertyValue::Vec4(value));
+        }
+        druvis_mat.set_property("sphere_mode", ShaderPropertyValue::Vec4(Vector4::new(sphere_mode, 0.0, 0.0, 0.0)));
+
+        druvis_mat.topology = material_topology(mat.drawing_flags);
+        druvis_mat.cull_mode = material_cull_mode(mat.drawing_flags, druvis_mat.cull_mode);
+        druvis_mat.ground_shadow = mat.drawing_flags.contains(PMXMaterialFlags::GROUND_SHADOW);
+        druvis_mat.cast_shadow = mat.drawing_flags.contains(PMXMaterialFlags::DRAW_SHADOW);
+        druvis_mat.receive_shadow = mat.drawing_flags.contains(PMXMaterialFlags::RECEIVE_SHADOW);
 
-        druvis_mat
+        Some(druvis_mat)
+    }
+
+    /// Outline material of `mat_index`, None when the material does not draw an edge
+    pub fn create_edge_material(
+        &self,
+        device: &wgpu::Device,
+        mat_index: usize,
+        shader_manager: &ShaderManager,
+        builtin_bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Option<DruvisMaterial> {
+        let mat = &self.materials[mat_index];
+        // points and lines have no hull to extrude
+        if !mat.drawing_flags.contains(PMXMaterialFlags::EDGE)
+            || material_topology(mat.drawing_flags) != wgpu::PrimitiveTopology::TriangleList
+            || mat.edge_scale <= 0.0
+        {
+            return None;
+        }
+
+        let shader = shader_manager.get_shader(device, builtin_bind_group_layouts, "druvis.mmd.edge")?;
+        let mut druvis_mat = DruvisMaterial::create_material(
+            device,
+            shader,
+            HashMap::new(),
+            "mmd_edge_mat"
+        )?;
+        druvis_mat.set_property("edge_color", ShaderPropertyValue::Vec4(mat.edge_color.into()));
+        druvis_mat.set_property("edge_size", ShaderPropertyValue::Vec4(Vector4::new(mat.edge_scale, 0.0, 0.0, 0.0)));
+
+        Some(druvis_mat)
     }
 
     pub fn to_druvis_mesh(self, device: &wgpu::Device) -> DruvisMesh {
-        let mut vertices: Vec<ModelVertex> = Vec::new();
-        let mut indices: Vec<u32> = Vec::new();
-        let mut submeshes: Vec<(u64, u64)> = Vec::new();
+        let mut vertices: Vec<SkinnedModelVertex> = Vec::new();
 
+        let bone_count = self.bones.len();
         for v in self.vertices.iter() {
-            let model_vertex = ModelVertex {
+            let (bone_indices, mut bone_weights) = v.weight_deform.linear_blend_weights();
+            // unused or invalid bones point at bone 0 with no influence
+            let bone_indices = bone_indices.map(|i| if i >= 0 && (i as usize) < bone_count { i as u32 } else { u32::MAX });
+            for (w, i) in bone_weights.iter_mut().zip(bone_indices.iter()) {
+                if *i == u32::MAX {
+                    *w = 0.0;
+                }
+            }
+            let total = bone_weights.iter().sum::<f32>();
+            if total > 0.0 {
+                bone_weights = bone_weights.map(|w| w / total);
+            }
+
+            let mut model_vertex = SkinnedModelVertex {
                 position: v.position.clone(),
                 tex_coords: v.uv.clone(),
                 normal: v.normal.clone(),
                 // todo calculate tangents
                 tangent: [0.0, 0.0, 0.0],
-                bitangent: [0.0, 0.0, 0.0]
+                bitangent: [0.0, 0.0, 0.0],
+                bone_indices: bone_indices.map(|i| if i == u32::MAX { 0 } else { i }),
+                bone_weights,
+                additional_uv: v.additional_vec4.first().cloned().unwrap_or([0.0; 4]),
+                edge_scale: v.edge_scale,
+                ..Default::default()
             };
+            match &v.weight_deform {
+                // SDEF needs both bones, otherwise it is the same as linear
+                PMXWeightDeformData::SDEF(sdef) if bone_indices[0] != u32::MAX && bone_indices[1] != u32::MAX => {
+                    let (r0, r1) = sdef_rotation_centers(sdef, bone_weights[0], bone_weights[1]);
+                    model_vertex.deform_type = DEFORM_TYPE_SDEF;
+                    model_vertex.sdef_c = sdef.c;
+                    model_vertex.sdef_r0 = r0;
+                    model_vertex.sdef_r1 = r1;
+                },
+                PMXWeightDeformData::QDEF(_) => model_vertex.deform_type = DEFORM_TYPE_QDEF,
+                _ => {},
+            }
             vertices.push(model_vertex);
         }
-        for surface in self.surfaces.iter() {
-            indices.push(surface.triangle[0] as u32);
-            indices.push(surface.triangle[1] as u32);
-            indices.push(surface.triangle[2] as u32);
-        }
-
-        let mut start: u64 = 0;
-        for mat in self.materials.iter() {
-            // println!("start {}, {}, range {}", start, start + mat.surface_count as u64, mat.surface_count);
-            submeshes.push((start, start + mat.surface_count as u64));
-            start += mat.surface_count as u64;
-        }
+        let (indices, submeshes) = material_indices(&self.materials, &self.surfaces);
 
         DruvisMesh::new(
             device,
@@ -128,6 +594,20 @@ impl PMXFormat {
     }
 }
 
+// moves R0 and R1 so their weighted average is C, then takes the midpoints with C
+fn sdef_rotation_centers(sdef: &SDEFData, w0: f32, w1: f32) -> ([f32; 3], [f32; 3]) {
+    let mut cr0 = [0.0; 3];
+    let mut cr1 = [0.0; 3];
+    for i in 0..3 {
+        let rw = sdef.r0[i] * w0 + sdef.r1[i] * w1;
+        let r0 = sdef.c[i] + sdef.r0[i] - rw;
+        let r1 = sdef.c[i] + sdef.r1[i] - rw;
+        cr0[i] = (sdef.c[i] + r0) * 0.5;
+        cr1[i] = (sdef.c[i] + r1) * 0.5;
+    }
+    (cr0, cr1)
+}
+
 pub struct PmxParser {
 
 }
@@ -139,81 +619,209 @@ impl PmxParser {
 }
 
 impl PmxParser {
-    fn parse_header(&self, data: &[u8], cursor: &mut usize) -> Result<PMXHeaderRaw> {
-        let mut result = PMXHeaderRaw::new();
-        result.signature = utils::read::<[i8; 4]>(data, cursor);
-        result.version = utils::read::<f32>(data, cursor);
-        result.globals_count = utils::read::<i8>(data, cursor);
-        result.globals = utils::read_var::<i8>(data, cursor, result.globals_count as usize);
-        result.model_name_local = utils::read_text(data, cursor);
-        result.model_name_universal = utils::read_text(data, cursor);
-        result.comments_local = utils::read_text(data, cursor);
-        result.comments_universal = utils::read_text(data, cursor);
-        // println!("signature: {:?}", result.signature);
-        // println!("version: {:?}", result.version);
-        // println!("globals_count: {:?}", result.globals_count);
-        // println!("globals: {:?}", result.globals);
-        // println!("model_name_local: {:?}", result.model_name_local);
-        // println!("model_name_universal: {:?}", result.model_name_universal);
-        // println!("comments_local: {:?}", result.comments_local);
-        // println!("comments_universal: {:?}", result.comments_universal);
+    /// Parses a model file, textures are resolved relative to the directory containing it
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> PmxLoadResult<PMXFormat> {
+        let path = path.as_ref();
+        let model_path = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let file = File::open(path)?;
+        self.parse_reader(file, model_path)
+    }
+
+    /// Parses a model from any reader, textures are resolved relative to `model_path`
+    pub fn parse_reader<R: Read>(&self, mut reader: R, model_path: PathBuf) -> PmxLoadResult<PMXFormat> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let result = self.parse(&data, model_path)?;
         Ok(result)
     }
 
-    pub fn parse(&self, data: &[u8], model_path: PathBuf) -> Result<PMXFormat> {
-        let mut cursor: usize = 0;
+    fn parse_header(&self, data: &[u8], cursor: &mut usize) -> PmxResult<(PMXHeader, PMXGlobals)> {
+        let section_error = |e: ReadError| e.in_section(PmxSection::Header, None);
 
-        let header_raw = self.parse_header(data, &mut cursor)?;
+        let mut result = PMXHeaderRaw::new();
+        result.signature = utils::read::<[i8; 4]>(data, cursor).map_err(section_error)?;
+        if result.signature != PMX_SIGNATURE {
+            return Err(PmxError::new(PmxErrorKind::InvalidSignature(result.signature), 0, PmxSection::Header, None));
+        }
+        let version_offset = *cursor;
+        result.version = utils::read::<f32>(data, cursor).map_err(section_error)?;
+        // reject unknown versions before the globals are interpreted
+        if PMXVersion::from_f32(result.version).is_none() {
+            return Err(PmxError::new(PmxErrorKind::UnsupportedVersion(result.version), version_offset, PmxSection::Header, None));
+        }
+        result.globals_count = utils::read::<i8>(data, cursor).map_err(section_error)?;
+        let globals_offset = *cursor;
+        result.globals = utils::read_var::<i8>(data, cursor, result.globals_count.max(0) as usize).map_err(section_error)?;
+        let globals = PMXGlobals::try_from(&result.to_globals_raw())
+            .map_err(|kind| ReadError::new(kind, globals_offset).in_section(PmxSection::Header, None))?;
+
+        let text_offset = *cursor;
+        result.model_name_local = utils::read_text(data, cursor).map_err(section_error)?;
+        result.model_name_universal = utils::read_text(data, cursor).map_err(section_error)?;
+        result.comments_local = utils::read_text(data, cursor).map_err(section_error)?;
+        result.comments_universal = utils::read_text(data, cursor).map_err(section_error)?;
 
-        let global = PMXGlobals::from(&header_raw.to_globals_raw());
-        let header = PMXHeader::from_pmx_header_raw(&header_raw, &global)?;
+        let header = PMXHeader::from_pmx_header_raw(&result, &globals)
+            .map_err(|kind| ReadError::new(kind, text_offset).in_section(PmxSection::Header, None))?;
+
+        Ok((header, globals))
+    }
+
+    pub fn parse(&self, data: &[u8], model_path: PathBuf) -> PmxResult<PMXFormat> {
+        let mut cursor: usize = 0;
+
+        let (header, global) = self.parse_header(data, &mut cursor)?;
         println!("{:?}", header);
 
-        let vertex_count = utils::read::<i32>(data, &mut cursor);
+        let vertex_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::Vertices, None))?;
         let mut vertices: Vec<PMXVertexData> = Vec::new();
         println!("vertex count: {}", vertex_count);
-        for _ in 0..vertex_count {
+        for i in 0..vertex_count {
             vertices.push(PMXVertexData::parse(
                 data,
                 &mut cursor,
-                global.bone_index_size.to_usize(),
+                global.bone_index_size,
                 global.additional_vec4_count as usize
-            ));
+            ).map_err(|e| e.in_section(PmxSection::Vertices, Some(i)))?);
         }
 
-        let surface_count = utils::read::<i32>(data, &mut cursor) / 3;
+        let index_count_offset = cursor;
+        let index_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::Surfaces, None))?;
+        if !index_count.is_multiple_of(3) {
+            return Err(ReadError::new(ReadErrorKind::InvalidLength(index_count as i64), index_count_offset)
+                .in_section(PmxSection::Surfaces, None));
+        }
+        let surface_count = index_count / 3;
         let mut surfaces = Vec::new();
         println!("face count: {}", surface_count);
-        for _ in 0..surface_count {
-            surfaces.push(PMXSurfaceData::parse(
+        for i in 0..surface_count {
+            let offset = cursor;
+            let surface = PMXSurfaceData::parse(
                 data,
                 &mut cursor,
                 global.vertex_index_size
-            ))
+            ).map_err(|e| e.in_section(PmxSection::Surfaces, Some(i)))?;
+            for &index in surface.triangle.iter() {
+                if index < 0 || index as usize >= vertices.len() {
+                    return Err(PmxError::new(
+                        PmxErrorKind::IndexOutOfRange { field: "vertex index", index: index as i64, count: vertices.len() },
+                        offset,
+                        PmxSection::Surfaces,
+                        Some(i)
+                    ));
+                }
+            }
+            surfaces.push(surface);
         }
 
         // parse texture paths
-        let texture_path_count = utils::read::<i32>(data, &mut cursor);
+        let texture_path_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::Textures, None))?;
         let mut texture_paths = Vec::new();
         println!("texture path count: {}", texture_path_count);
-        for _ in 0..texture_path_count {
-            let t = utils::read_text(data, &mut cursor);
-            let s = global.text_encoding.parse_text(&t)?;
+        for i in 0..texture_path_count {
+            let s = global.text_encoding.read_text(data, &mut cursor)
+                .map_err(|e| e.in_section(PmxSection::Textures, Some(i)))?;
             println!("{}", s);
             texture_paths.push(s);
         }
 
         // materials
-        let material_count = utils::read::<i32>(data, &mut cursor);
+        let material_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::Materials, None))?;
         let mut materials = Vec::new();
+        let mut material_index_total: usize = 0;
         println!("material count: {}", material_count);
-        for _ in 0..material_count {
-            materials.push(PMXMaterialData::parse(
+        for i in 0..material_count {
+            let offset = cursor;
+            let material = PMXMaterialData::parse(
                 data,
                 &mut cursor,
                 global.texture_index_size,
                 global.text_encoding
-            )?);
+            ).map_err(|e| e.in_section(PmxSection::Materials, Some(i)))?;
+            // materials slice the index buffer in order, make sure they stay inside it
+            material_index_total += material.surface_count.max(0) as usize;
+            if material.surface_count < 0 || material_index_total > index_count {
+                return Err(PmxError::new(
+                    PmxErrorKind::IndexOutOfRange { field: "material index end", index: material_index_total as i64, count: index_count },
+                    offset,
+                    PmxSection::Materials,
+                    Some(i)
+                ));
+            }
+            materials.push(material);
+        }
+
+        // bones
+        let bone_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::Bones, None))?;
+        let mut bones = Vec::new();
+        for i in 0..bone_count {
+            bones.push(PMXBoneData::parse(
+                data,
+                &mut cursor,
+                global.bone_index_size,
+                global.text_encoding
+            ).map_err(|e| e.in_section(PmxSection::Bones, Some(i)))?);
+        }
+
+        // morphs
+        let morph_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::Morphs, None))?;
+        let mut morphs = Vec::new();
+        for i in 0..morph_count {
+            morphs.push(PMXMorphData::parse(data, &mut cursor, &global, header.version)
+                .map_err(|e| e.in_section(PmxSection::Morphs, Some(i)))?);
+        }
+
+        // display frames
+        let display_frame_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::DisplayFrames, None))?;
+        let mut display_frames = Vec::new();
+        for i in 0..display_frame_count {
+            display_frames.push(PMXDisplayFrameData::parse(data, &mut cursor, &global)
+                .map_err(|e| e.in_section(PmxSection::DisplayFrames, Some(i)))?);
+        }
+
+        // rigid bodies
+        let rigidbody_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::RigidBodies, None))?;
+        let mut rigidbodies = Vec::new();
+        for i in 0..rigidbody_count {
+            rigidbodies.push(PMXRigidBodyData::parse(
+                data,
+                &mut cursor,
+                global.bone_index_size,
+                global.text_encoding
+            ).map_err(|e| e.in_section(PmxSection::RigidBodies, Some(i)))?);
+        }
+
+        // joints
+        let joint_count = utils::read_count(data, &mut cursor)
+            .map_err(|e| e.in_section(PmxSection::Joints, None))?;
+        let mut joints = Vec::new();
+        for i in 0..joint_count {
+            joints.push(PMXJointData::parse(
+                data,
+                &mut cursor,
+                global.rigidbody_index_size,
+                global.text_encoding,
+                header.version
+            ).map_err(|e| e.in_section(PmxSection::Joints, Some(i)))?);
+        }
+
+        // soft bodies
+        let mut soft_bodies = Vec::new();
+        if header.version == PMXVersion::V2_1 {
+            let soft_body_count = utils::read_count(data, &mut cursor)
+                .map_err(|e| e.in_section(PmxSection::SoftBodies, None))?;
+            for i in 0..soft_body_count {
+                soft_bodies.push(PMXSoftBodyData::parse(data, &mut cursor, &global)
+                    .map_err(|e| e.in_section(PmxSection::SoftBodies, Some(i)))?);
+            }
         }
 
         Ok(PMXFormat {
@@ -223,8 +831,15 @@ impl PmxParser {
             surfaces,
             texture_paths,
             materials,
+            bones,
+            morphs,
+            display_frames,
+            rigidbodies,
+            joints,
+            soft_bodies,
 
             model_path,
+            toon_directory: None,
         })
     }
 }
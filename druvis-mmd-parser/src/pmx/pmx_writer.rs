@@ -0,0 +1,608 @@
+use std::{io::{self, Write}, path::Path, fs::File};
+
+use crate::utils;
+
+use super::{pmx_parser::PMXFormat, structs::*};
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// the smallest index type that can address `count` elements, -1 is reserved for signed indices
+fn index_type_for(count: usize, is_vertex: bool) -> PMXIndexType {
+    let (b1, b2) = if is_vertex {
+        (u8::MAX as usize + 1, u16::MAX as usize + 1)
+    } else {
+        (i8::MAX as usize + 1, i16::MAX as usize + 1)
+    };
+    if count <= b1 {
+        PMXIndexType::B1
+    } else if count <= b2 {
+        PMXIndexType::B2
+    } else {
+        PMXIndexType::B4
+    }
+}
+
+// serializes into a byte buffer with the chosen globals
+struct PmxByteWriter {
+    buffer: Vec<u8>,
+    globals: PMXGlobals,
+}
+
+impl PmxByteWriter {
+    fn put<T: utils::Pod>(&mut self, value: T) {
+        utils::write(&mut self.buffer, value);
+    }
+
+    fn put_count(&mut self, count: usize) -> io::Result<()> {
+        let count = i32::try_from(count).map_err(|_| invalid_data(format!("count {} is too large", count)))?;
+        self.put(count);
+        Ok(())
+    }
+
+    fn put_text(&mut self, text: &str) -> io::Result<()> {
+        let bytes = match self.globals.text_encoding {
+            TextEncodingType::UTF16LE => text.encode_utf16().flat_map(|c| c.to_le_bytes()).collect::<Vec<_>>(),
+            TextEncodingType::UTF8 => text.as_bytes().to_vec(),
+        };
+        self.put_count(bytes.len())?;
+        self.buffer.extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn put_index(&mut self, index_size: PMXIndexType, value: i32, is_vertex: bool) -> io::Result<()> {
+        let out_of_range = || invalid_data(format!("index {} does not fit in {} bytes", value, index_size.to_usize()));
+        match (index_size, is_vertex) {
+            (PMXIndexType::B1, true) => self.put(u8::try_from(value).map_err(|_| out_of_range())?),
+            (PMXIndexType::B2, true) => self.put(u16::try_from(value).map_err(|_| out_of_range())?),
+            (PMXIndexType::B1, false) => self.put(i8::try_from(value).map_err(|_| out_of_range())?),
+            (PMXIndexType::B2, false) => self.put(i16::try_from(value).map_err(|_| out_of_range())?),
+            (PMXIndexType::B4, _) => self.put(value),
+        }
+        Ok(())
+    }
+
+    fn put_vertex_index(&mut self, value: i32) -> io::Result<()> {
+        self.put_index(self.globals.vertex_index_size, value, true)
+    }
+
+    fn put_texture_index(&mut self, value: i32) -> io::Result<()> {
+        self.put_index(self.globals.texture_index_size, value, false)
+    }
+
+    fn put_material_index(&mut self, value: i32) -> io::Result<()> {
+        self.put_index(self.globals.material_index_size, value, false)
+    }
+
+    fn put_bone_index(&mut self, value: i32) -> io::Result<()> {
+        self.put_index(self.globals.bone_index_size, value, false)
+    }
+
+    fn put_morph_index(&mut self, value: i32) -> io::Result<()> {
+        self.put_index(self.globals.morph_index_size, value, false)
+    }
+
+    fn put_rigidbody_index(&mut self, value: i32) -> io::Result<()> {
+        self.put_index(self.globals.rigidbody_index_size, value, false)
+    }
+
+    fn put_header(&mut self, header: &PMXHeader) -> io::Result<()> {
+        self.put(PMX_SIGNATURE);
+        self.put(match header.version {
+            PMXVersion::V2_0 => 2.0f32,
+            PMXVersion::V2_1 => 2.1f32,
+        });
+
+        let index_size = |ty: PMXIndexType| ty.to_usize() as i8;
+        let globals = [
+            match self.globals.text_encoding {
+                TextEncodingType::UTF16LE => 0,
+                TextEncodingType::UTF8 => 1,
+            },
+            self.globals.additional_vec4_count,
+            index_size(self.globals.vertex_index_size),
+            index_size(self.globals.texture_index_size),
+            index_size(self.globals.material_index_size),
+            index_size(self.globals.bone_index_size),
+            index_size(self.globals.morph_index_size),
+            index_size(self.globals.rigidbody_index_size),
+        ];
+        self.put(globals.len() as i8);
+        self.put(globals);
+
+        self.put_text(&header.model_name_local)?;
+        self.put_text(&header.model_name_universal)?;
+        self.put_text(&header.comments_local)?;
+        self.put_text(&header.comments_universal)?;
+        Ok(())
+    }
+
+    fn put_vertex(&mut self, vertex: &PMXVertexData) -> io::Result<()> {
+        if vertex.additional_vec4.len() != self.globals.additional_vec4_count as usize {
+            return Err(invalid_data(format!(
+                "vertex has {} additional vec4s, expected {}",
+                vertex.additional_vec4.len(),
+                self.globals.additional_vec4_count
+            )));
+        }
+
+        self.put(vertex.position);
+        self.put(vertex.normal);
+        self.put(vertex.uv);
+        for v in vertex.additional_vec4.iter() {
+            self.put(*v);
+        }
+
+        match &vertex.weight_deform {
+            PMXWeightDeformData::BDEF1(w) => {
+                self.put(0i8);
+                self.put_bone_index(w.bone_index)?;
+            },
+            PMXWeightDeformData::BDEF2(w) => {
+                self.put(1i8);
+                self.put_bone_index(w.bone_index1)?;
+                self.put_bone_index(w.bone_index2)?;
+                self.put(w.bone1_weight);
+            },
+            PMXWeightDeformData::BDEF4(w) => {
+                self.put(2i8);
+                for index in [w.bone_index1, w.bone_index2, w.bone_index3, w.bone_index4] {
+                    self.put_bone_index(index)?;
+                }
+                self.put([w.bone1_weight, w.bone2_weight, w.bone3_weight, w.bone4_weight]);
+            },
+            PMXWeightDeformData::SDEF(w) => {
+                self.put(3i8);
+                self.put_bone_index(w.bone_index1)?;
+                self.put_bone_index(w.bone_index2)?;
+                self.put(w.bone1_weight);
+                self.put(w.c);
+                self.put(w.r0);
+                self.put(w.r1);
+            },
+            PMXWeightDeformData::QDEF(w) => {
+                self.put(4i8);
+                for index in [w.bone_index1, w.bone_index2, w.bone_index3, w.bone_index4] {
+                    self.put_bone_index(index)?;
+                }
+                self.put([w.bone1_weight, w.bone2_weight, w.bone3_weight, w.bone4_weight]);
+            },
+        }
+
+        self.put(vertex.edge_scale);
+        Ok(())
+    }
+
+    fn put_material(&mut self, material: &PMXMaterialData) -> io::Result<()> {
+        self.put_text(&material.material_name_local)?;
+        self.put_text(&material.material_name_universal)?;
+        self.put(material.diffuse_color);
+        self.put(material.specular_color);
+        self.put(material.specular_strength);
+        self.put(material.ambient_color);
+        self.put(material.drawing_flags.bits());
+        self.put(material.edge_color);
+        self.put(material.edge_scale);
+        self.put_texture_index(material.texture_index)?;
+        self.put_texture_index(material.environment_index)?;
+        self.put(match material.environment_blend_mode {
+            PMXEnvironmentBlendMode::Disabled => 0i8,
+            PMXEnvironmentBlendMode::Multiply => 1,
+            PMXEnvironmentBlendMode::Additive => 2,
+            PMXEnvironmentBlendMode::AdditionalVec4 => 3,
+        });
+        match material.toon_value {
+            PMXToonValue::Texture(index) => {
+                self.put(0i8);
+                self.put_texture_index(index)?;
+            },
+            PMXToonValue::Internal(index) => {
+                self.put(1i8);
+                self.put(index);
+            },
+        }
+        self.put_text(&material.meta_data)?;
+        self.put(material.surface_count);
+        Ok(())
+    }
+
+    fn put_bone(&mut self, bone: &PMXBoneData) -> io::Result<()> {
+        // the flags decide which optional fields follow, so they have to agree with the data
+        let missing = |field: &str| invalid_data(format!("bone {} has flags for {} but no data", bone.bone_name_local, field));
+
+        self.put_text(&bone.bone_name_local)?;
+        self.put_text(&bone.bone_name_universal)?;
+        self.put(bone.position);
+        self.put_bone_index(bone.parent_index)?;
+        self.put(bone.layer);
+        self.put(bone.flags.bits());
+
+        match (bone.flags.contains(PMXBoneFlags::INDEXED_TAIL_POSITION), bone.tail) {
+            (true, PMXBoneTail::BoneIndex(index)) => self.put_bone_index(index)?,
+            (false, PMXBoneTail::Position(position)) => self.put(position),
+            _ => return Err(invalid_data(format!("bone {} tail does not match its flags", bone.bone_name_local))),
+        }
+
+        if bone.flags.intersects(PMXBoneFlags::INHERIT_ROTATION | PMXBoneFlags::INHERIT_TRANSLATION) {
+            let inherit = bone.inherit.as_ref().ok_or_else(|| missing("inherit"))?;
+            self.put_bone_index(inherit.parent_index)?;
+            self.put(inherit.ratio);
+        }
+        if bone.flags.contains(PMXBoneFlags::FIXED_AXIS) {
+            self.put(bone.fixed_axis.ok_or_else(|| missing("fixed axis"))?);
+        }
+        if bone.flags.contains(PMXBoneFlags::LOCAL_COORDINATE) {
+            let axes = bone.local_axes.as_ref().ok_or_else(|| missing("local axes"))?;
+            self.put(axes.x_axis);
+            self.put(axes.z_axis);
+        }
+        if bone.flags.contains(PMXBoneFlags::EXTERNAL_PARENT_DEFORM) {
+            self.put(bone.external_parent_key.ok_or_else(|| missing("external parent"))?);
+        }
+        if bone.flags.contains(PMXBoneFlags::IK) {
+            let ik = bone.ik.as_ref().ok_or_else(|| missing("IK"))?;
+            self.put_bone_index(ik.target_index)?;
+            self.put(ik.loop_count);
+            self.put(ik.limit_angle);
+            self.put_count(ik.links.len())?;
+            for link in ik.links.iter() {
+                self.put_bone_index(link.bone_index)?;
+                match &link.angle_limit {
+                    Some(limit) => {
+                        self.put(1i8);
+                        self.put(limit.min);
+                        self.put(limit.max);
+                    },
+                    None => self.put(0i8),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn put_morph(&mut self, morph: &PMXMorphData, version: PMXVersion) -> io::Result<()> {
+        let morph_type = match morph.morph_type {
+            PMXMorphType::Group => 0i8,
+            PMXMorphType::Vertex => 1,
+            PMXMorphType::Bone => 2,
+            PMXMorphType::UV => 3,
+            PMXMorphType::AdditionalUV1 => 4,
+            PMXMorphType::AdditionalUV2 => 5,
+            PMXMorphType::AdditionalUV3 => 6,
+            PMXMorphType::AdditionalUV4 => 7,
+            PMXMorphType::Material => 8,
+            PMXMorphType::Flip => 9,
+            PMXMorphType::Impulse => 10,
+        };
+        if version == PMXVersion::V2_0 && morph_type > 8 {
+            return Err(invalid_data(format!("morph {} needs PMX 2.1", morph.morph_name_local)));
+        }
+        let offsets_match = matches!(
+            (morph.morph_type, &morph.offsets),
+            (PMXMorphType::Group, PMXMorphOffsetData::Group(_))
+                | (PMXMorphType::Vertex, PMXMorphOffsetData::Vertex(_))
+                | (PMXMorphType::Bone, PMXMorphOffsetData::Bone(_))
+                | (PMXMorphType::Material, PMXMorphOffsetData::Material(_))
+                | (PMXMorphType::Flip, PMXMorphOffsetData::Flip(_))
+                | (PMXMorphType::Impulse, PMXMorphOffsetData::Impulse(_))
+        ) || (morph.morph_type.uv_channel().is_some() && matches!(morph.offsets, PMXMorphOffsetData::UV(_)));
+        if !offsets_match {
+            return Err(invalid_data(format!("morph {} offsets do not match its type", morph.morph_name_local)));
+        }
+
+        self.put_text(&morph.morph_name_local)?;
+        self.put_text(&morph.morph_name_universal)?;
+        self.put(match morph.panel {
+            PMXMorphPanel::Hidden => 0i8,
+            PMXMorphPanel::Eyebrows => 1,
+            PMXMorphPanel::Eyes => 2,
+            PMXMorphPanel::Mouth => 3,
+            PMXMorphPanel::Other => 4,
+        });
+        self.put(morph_type);
+
+        match &morph.offsets {
+            PMXMorphOffsetData::Group(offsets) => {
+                self.put_count(offsets.len())?;
+                for o in offsets.iter() {
+                    self.put_morph_index(o.morph_index)?;
+                    self.put(o.influence);
+                }
+            },
+            PMXMorphOffsetData::Vertex(offsets) => {
+                self.put_count(offsets.len())?;
+                for o in offsets.iter() {
+                    self.put_vertex_index(o.vertex_index)?;
+                    self.put(o.translation);
+                }
+            },
+            PMXMorphOffsetData::Bone(offsets) => {
+                self.put_count(offsets.len())?;
+                for o in offsets.iter() {
+                    self.put_bone_index(o.bone_index)?;
+                    self.put(o.translation);
+                    self.put(o.rotation);
+                }
+            },
+            PMXMorphOffsetData::UV(offsets) => {
+                self.put_count(offsets.len())?;
+                for o in offsets.iter() {
+                    self.put_vertex_index(o.vertex_index)?;
+                    self.put(o.offset);
+                }
+            },
+            PMXMorphOffsetData::Material(offsets) => {
+                self.put_count(offsets.len())?;
+                for o in offsets.iter() {
+                    self.put_material_index(o.material_index)?;
+                    self.put(match o.method {
+                        PMXMaterialMorphMethod::Multiply => 0i8,
+                        PMXMaterialMorphMethod::Additive => 1,
+                    });
+                    self.put(o.diffuse_color);
+                    self.put(o.specular_color);
+                    self.put(o.specular_strength);
+                    self.put(o.ambient_color);
+                    self.put(o.edge_color);
+                    self.put(o.edge_scale);
+                    self.put(o.texture_tint);
+                    self.put(o.environment_tint);
+                    self.put(o.toon_tint);
+                }
+            },
+            PMXMorphOffsetData::Flip(offsets) => {
+                self.put_count(offsets.len())?;
+                for o in offsets.iter() {
+                    self.put_morph_index(o.morph_index)?;
+                    self.put(o.influence);
+                }
+            },
+            PMXMorphOffsetData::Impulse(offsets) => {
+                self.put_count(offsets.len())?;
+                for o in offsets.iter() {
+                    self.put_rigidbody_index(o.rigidbody_index)?;
+                    self.put(o.is_local as i8);
+                    self.put(o.movement_speed);
+                    self.put(o.rotation_torque);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn put_display_frame(&mut self, display_frame: &PMXDisplayFrameData) -> io::Result<()> {
+        self.put_text(&display_frame.display_name_local)?;
+        self.put_text(&display_frame.display_name_universal)?;
+        self.put(display_frame.is_special as i8);
+        self.put_count(display_frame.frames.len())?;
+        for frame in display_frame.frames.iter() {
+            match *frame {
+                PMXFrameData::Bone(index) => {
+                    self.put(0i8);
+                    self.put_bone_index(index)?;
+                },
+                PMXFrameData::Morph(index) => {
+                    self.put(1i8);
+                    self.put_morph_index(index)?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn put_rigidbody(&mut self, rigidbody: &PMXRigidBodyData) -> io::Result<()> {
+        self.put_text(&rigidbody.rigidbody_name_local)?;
+        self.put_text(&rigidbody.rigidbody_name_universal)?;
+        self.put_bone_index(rigidbody.bone_index)?;
+        self.put(rigidbody.group_id);
+        self.put(rigidbody.non_collision_mask);
+        self.put(match rigidbody.shape {
+            PMXRigidBodyShape::Sphere => 0i8,
+            PMXRigidBodyShape::Box => 1,
+            PMXRigidBodyShape::Capsule => 2,
+        });
+        self.put(rigidbody.shape_size);
+        self.put(rigidbody.shape_position);
+        self.put(rigidbody.shape_rotation);
+        self.put(rigidbody.mass);
+        self.put(rigidbody.move_attenuation);
+        self.put(rigidbody.rotation_damping);
+        self.put(rigidbody.repulsion);
+        self.put(rigidbody.friction_force);
+        self.put(match rigidbody.physics_mode {
+            PMXPhysicsMode::FollowBone => 0i8,
+            PMXPhysicsMode::Physics => 1,
+            PMXPhysicsMode::PhysicsAndBone => 2,
+        });
+        Ok(())
+    }
+
+    fn put_joint(&mut self, joint: &PMXJointData, version: PMXVersion) -> io::Result<()> {
+        let joint_type = match joint.joint_type {
+            PMXJointType::Spring6DOF => 0i8,
+            PMXJointType::SixDOF => 1,
+            PMXJointType::PointToPoint => 2,
+            PMXJointType::ConeTwist => 3,
+            PMXJointType::Slider => 4,
+            PMXJointType::Hinge => 5,
+        };
+        if version == PMXVersion::V2_0 && joint_type != 0 {
+            return Err(invalid_data(format!("joint {} needs PMX 2.1", joint.joint_name_local)));
+        }
+
+        self.put_text(&joint.joint_name_local)?;
+        self.put_text(&joint.joint_name_universal)?;
+        self.put(joint_type);
+        self.put_rigidbody_index(joint.rigidbody_index_a)?;
+        self.put_rigidbody_index(joint.rigidbody_index_b)?;
+        self.put(joint.position);
+        self.put(joint.rotation);
+        self.put(joint.position_min);
+        self.put(joint.position_max);
+        self.put(joint.rotation_min);
+        self.put(joint.rotation_max);
+        self.put(joint.position_spring);
+        self.put(joint.rotation_spring);
+        Ok(())
+    }
+
+    fn put_soft_body(&mut self, soft_body: &PMXSoftBodyData) -> io::Result<()> {
+        self.put_text(&soft_body.softbody_name_local)?;
+        self.put_text(&soft_body.softbody_name_universal)?;
+        self.put(match soft_body.shape {
+            PMXSoftBodyShape::TriMesh => 0i8,
+            PMXSoftBodyShape::Rope => 1,
+        });
+        self.put_material_index(soft_body.material_index)?;
+        self.put(soft_body.group_id);
+        self.put(soft_body.non_collision_mask);
+        self.put(soft_body.flags.bits());
+        self.put(soft_body.b_link_create_distance);
+        self.put(soft_body.cluster_count);
+        self.put(soft_body.total_mass);
+        self.put(soft_body.collision_margin);
+        self.put(match soft_body.aero_model {
+            PMXSoftBodyAeroModel::VertexPoint => 0i32,
+            PMXSoftBodyAeroModel::VertexTwoSided => 1,
+            PMXSoftBodyAeroModel::VertexOneSided => 2,
+            PMXSoftBodyAeroModel::FaceTwoSided => 3,
+            PMXSoftBodyAeroModel::FaceOneSided => 4,
+        });
+
+        let c = &soft_body.config;
+        self.put([c.vcf, c.dp, c.dg, c.lf, c.pr, c.vc, c.df, c.mt, c.chr, c.khr, c.shr, c.ahr]);
+        let c = &soft_body.cluster_config;
+        self.put([c.srhr_cl, c.skhr_cl, c.sshr_cl, c.sr_splt_cl, c.sk_splt_cl, c.ss_splt_cl]);
+        let c = &soft_body.iteration_config;
+        self.put([c.v_it, c.p_it, c.d_it, c.c_it]);
+        let c = &soft_body.material_config;
+        self.put([c.lst, c.ast, c.vst]);
+
+        self.put_count(soft_body.anchors.len())?;
+        for anchor in soft_body.anchors.iter() {
+            self.put_rigidbody_index(anchor.rigidbody_index)?;
+            self.put_vertex_index(anchor.vertex_index)?;
+            self.put(anchor.near_mode as i8);
+        }
+        self.put_count(soft_body.pin_vertices.len())?;
+        for &index in soft_body.pin_vertices.iter() {
+            self.put_vertex_index(index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes a `PMXFormat` to binary PMX, using the version in its header
+pub struct PmxWriter {
+    pub text_encoding: TextEncodingType,
+}
+
+impl Default for PmxWriter {
+    fn default() -> Self {
+        Self::new(TextEncodingType::UTF16LE)
+    }
+}
+
+impl PmxWriter {
+    pub fn new(text_encoding: TextEncodingType) -> Self {
+        PmxWriter {
+            text_encoding
+        }
+    }
+
+    /// The globals the model will be written with, index sizes are as small as the element counts allow
+    pub fn globals_for(&self, format: &PMXFormat) -> PMXGlobals {
+        PMXGlobals {
+            text_encoding: self.text_encoding,
+            additional_vec4_count: format.globals.additional_vec4_count,
+            vertex_index_size: index_type_for(format.vertices.len(), true),
+            texture_index_size: index_type_for(format.texture_paths.len(), false),
+            material_index_size: index_type_for(format.materials.len(), false),
+            bone_index_size: index_type_for(format.bones.len(), false),
+            morph_index_size: index_type_for(format.morphs.len(), false),
+            rigidbody_index_size: index_type_for(format.rigidbodies.len(), false),
+        }
+    }
+
+    pub fn to_bytes(&self, format: &PMXFormat) -> io::Result<Vec<u8>> {
+        let version = format.header.version;
+        if version == PMXVersion::V2_0 && !format.soft_bodies.is_empty() {
+            return Err(invalid_data(String::from("soft bodies need PMX 2.1")));
+        }
+
+        let mut w = PmxByteWriter {
+            buffer: Vec::new(),
+            globals: self.globals_for(format),
+        };
+
+        w.put_header(&format.header)?;
+
+        w.put_count(format.vertices.len())?;
+        for vertex in format.vertices.iter() {
+            w.put_vertex(vertex)?;
+        }
+
+        w.put_count(format.surfaces.len() * 3)?;
+        for surface in format.surfaces.iter() {
+            for &index in surface.triangle.iter() {
+                w.put_vertex_index(index)?;
+            }
+        }
+
+        w.put_count(format.texture_paths.len())?;
+        for path in format.texture_paths.iter() {
+            w.put_text(path)?;
+        }
+
+        w.put_count(format.materials.len())?;
+        for material in format.materials.iter() {
+            w.put_material(material)?;
+        }
+
+        w.put_count(format.bones.len())?;
+        for bone in format.bones.iter() {
+            w.put_bone(bone)?;
+        }
+
+        w.put_count(format.morphs.len())?;
+        for morph in format.morphs.iter() {
+            w.put_morph(morph, version)?;
+        }
+
+        w.put_count(format.display_frames.len())?;
+        for display_frame in format.display_frames.iter() {
+            w.put_display_frame(display_frame)?;
+        }
+
+        w.put_count(format.rigidbodies.len())?;
+        for rigidbody in format.rigidbodies.iter() {
+            w.put_rigidbody(rigidbody)?;
+        }
+
+        w.put_count(format.joints.len())?;
+        for joint in format.joints.iter() {
+            w.put_joint(joint, version)?;
+        }
+
+        if version == PMXVersion::V2_1 {
+            w.put_count(format.soft_bodies.len())?;
+            for soft_body in format.soft_bodies.iter() {
+                w.put_soft_body(soft_body)?;
+            }
+        }
+
+        Ok(w.buffer)
+    }
+
+    pub fn write<W: Write>(&self, format: &PMXFormat, mut writer: W) -> io::Result<()> {
+        let bytes = self.to_bytes(format)?;
+        writer.write_all(&bytes)
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, format: &PMXFormat, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        self.write(format, &mut file)?;
+        file.flush()
+    }
+}
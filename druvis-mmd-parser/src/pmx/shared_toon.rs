@@ -0,0 +1,41 @@
+/// Number of shared toons, `PMXToonValue::Internal(i)` refers to toon{i + 1:02}.bmp
+pub const SHARED_TOON_COUNT: usize = 10;
+/// Height of a generated ramp, it is one pixel wide
+pub const SHARED_TOON_HEIGHT: u32 = 32;
+
+// shadow color at the bottom of each ramp, close to the toons shipped with MMD
+const SHADOW_COLORS: [[u8; 3]; SHARED_TOON_COUNT] = [
+    [205, 205, 205],
+    [245, 220, 210],
+    [180, 180, 180],
+    [240, 215, 195],
+    [220, 210, 230],
+    [200, 200, 215],
+    [225, 225, 225],
+    [170, 170, 180],
+    [235, 230, 215],
+    [215, 215, 215],
+];
+
+/// File name MMD uses for shared toon `index`, e.g. toon01.bmp for 0
+pub fn file_name(index: usize) -> String {
+    format!("toon{:02}.bmp", index + 1)
+}
+
+/// RGBA8 sRGB pixels of shared toon `index`, white at the top fading into its shadow
+/// color just below the middle, None past the last toon
+pub fn pixels(index: usize) -> Option<Vec<u8>> {
+    let shadow = SHADOW_COLORS.get(index)?;
+    let mut result = Vec::with_capacity(SHARED_TOON_HEIGHT as usize * 4);
+    for y in 0..SHARED_TOON_HEIGHT {
+        let v = (y as f32 + 0.5) / SHARED_TOON_HEIGHT as f32;
+        // a narrow smoothstep, MMD's toons have a hard terminator
+        let t = ((v - 0.45) / 0.15).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        for &c in shadow.iter() {
+            result.push((255.0 + (c as f32 - 255.0) * t).round() as u8);
+        }
+        result.push(255);
+    }
+    Some(result)
+}
@@ -1,8 +1,9 @@
-use anyhow::Result;
-use crate::utils;
+use crate::utils::{self, ReadResult, ReadError, ReadErrorKind};
 
 type text = (i32, Vec<u8>);
 
+pub const PMX_SIGNATURE: [i8; 4] = [b'P' as i8, b'M' as i8, b'X' as i8, b' ' as i8];
+
 pub struct PMXHeaderRaw {
     pub signature: [i8; 4],
     pub version: f32,
@@ -40,8 +41,9 @@ impl PMXHeaderRaw {
             &mut result.morph_index_size,
             &mut result.rigidbody_index_size
         ];
-        for i in 0..self.globals_count as usize {
-            *temp[i] = Some(self.globals[i]);
+        // globals beyond the known 8 are ignored
+        for (slot, value) in temp.iter_mut().zip(self.globals.iter()) {
+            **slot = Some(*value);
         }
 
         result
@@ -51,7 +53,7 @@ impl PMXHeaderRaw {
 #[derive(Debug, Clone)]
 pub struct PMXHeader {
     pub signature: [i8; 4],
-    pub version: f32,
+    pub version: PMXVersion,
     pub globals_count: i8,
     pub globals: Vec<i8>,
     pub model_name_local: String,
@@ -61,10 +63,13 @@ pub struct PMXHeader {
 }
 
 impl PMXHeader {
-    pub fn from_pmx_header_raw(raw: &PMXHeaderRaw, globals: &PMXGlobals) -> Result<Self> {
+    /// Fails with the version the header carries if it is not supported, or the text decoding error
+    pub fn from_pmx_header_raw(raw: &PMXHeaderRaw, globals: &PMXGlobals) -> Result<Self, ReadErrorKind> {
+        let version = PMXVersion::from_f32(raw.version)
+            .ok_or(ReadErrorKind::InvalidValue { field: "PMX version", value: raw.version as i64 })?;
         let result = Self {
-            signature: raw.signature.clone(),
-            version: raw.version,
+            signature: raw.signature,
+            version,
             globals_count: raw.globals_count,
             globals: raw.globals.clone(),
             model_name_local: globals.text_encoding.parse_text(&raw.model_name_local)?,
@@ -76,6 +81,25 @@ impl PMXHeader {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PMXVersion {
+    V2_0,
+    V2_1,
+}
+
+impl PMXVersion {
+    pub fn from_f32(version: f32) -> Option<Self> {
+        // versions are stored as floats, compare with a tolerance
+        if (version - 2.0).abs() < 1e-4 {
+            Some(Self::V2_0)
+        } else if (version - 2.1).abs() < 1e-4 {
+            Some(Self::V2_1)
+        } else {
+            None
+        }
+    }
+}
+
 pub struct PMXGlobalsRaw {
     pub text_encoding: Option<i8>,
     pub additional_vec4_count: Option<i8>,
@@ -109,32 +133,42 @@ pub enum TextEncodingType {
 }
 
 impl TextEncodingType {
-    pub fn parse_text(&self, raw: &(i32, Vec<u8>)) -> anyhow::Result<String> {
-        let slice = unsafe {
-            let ptr = raw.1.as_ptr() as *const u8;
-            std::slice::from_raw_parts(ptr, raw.1.len())
-        };
+    pub fn parse_text(&self, raw: &(i32, Vec<u8>)) -> Result<String, ReadErrorKind> {
+        let bytes = raw.1.as_slice();
 
         let s = match *self {
-            // todo
-            TextEncodingType::UTF16LE => unsafe {
-                let ptr = raw.1.as_ptr() as *const u16;
-                let slice = std::slice::from_raw_parts(ptr, raw.1.len() / 2);
-                String::from_utf16(slice)?
+            TextEncodingType::UTF16LE => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(ReadErrorKind::InvalidText(format!("odd UTF-16 byte length {}", bytes.len())));
+                }
+                let units = bytes.chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect::<Vec<_>>();
+                String::from_utf16(&units).map_err(|e| ReadErrorKind::InvalidText(e.to_string()))?
             },
-            TextEncodingType::UTF8 => String::from(std::str::from_utf8(slice)?),
+            TextEncodingType::UTF8 => String::from(
+                std::str::from_utf8(bytes).map_err(|e| ReadErrorKind::InvalidText(e.to_string()))?
+            ),
         };
 
-        Ok(String::from(s))
+        Ok(s)
+    }
+
+    pub fn read_text(&self, data: &[u8], cursor: &mut usize) -> ReadResult<String> {
+        let offset = *cursor;
+        let raw = utils::read_text(data, cursor)?;
+        self.parse_text(&raw).map_err(|kind| ReadError::new(kind, offset))
     }
 }
 
-impl From<i8> for TextEncodingType {
-    fn from(value: i8) -> Self {
+impl TryFrom<i8> for TextEncodingType {
+    type Error = ReadErrorKind;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
         match value {
-            0 => Self::UTF16LE,
-            1 => Self::UTF8,
-            _ => panic!("invalid PMX text encoding {}", value)
+            0 => Ok(Self::UTF16LE),
+            1 => Ok(Self::UTF8),
+            _ => Err(ReadErrorKind::InvalidValue { field: "text encoding", value: value as i64 })
         }
     }
 }
@@ -155,30 +189,32 @@ impl PMXIndexType {
         }
     }
 
-    pub fn parse_i32(&self, data: &[u8], cursor: &mut usize, is_vertex: bool) -> i32 {
-        if is_vertex {
+    pub fn parse_i32(&self, data: &[u8], cursor: &mut usize, is_vertex: bool) -> ReadResult<i32> {
+        Ok(if is_vertex {
             match *self {
-                Self::B1 => utils::read::<u8>(data, cursor) as i32,
-                Self::B2 => utils::read::<u16>(data, cursor) as i32,
-                Self::B4 => utils::read::<i32>(data, cursor)
+                Self::B1 => utils::read::<u8>(data, cursor)? as i32,
+                Self::B2 => utils::read::<u16>(data, cursor)? as i32,
+                Self::B4 => utils::read::<i32>(data, cursor)?
             }
         } else {
             match *self {
-                Self::B1 => utils::read::<i8>(data, cursor) as i32,
-                Self::B2 => utils::read::<i16>(data, cursor) as i32,
-                Self::B4 => utils::read::<i32>(data, cursor)
+                Self::B1 => utils::read::<i8>(data, cursor)? as i32,
+                Self::B2 => utils::read::<i16>(data, cursor)? as i32,
+                Self::B4 => utils::read::<i32>(data, cursor)?
             }
-        }
+        })
     }
 }
 
-impl From<i8> for PMXIndexType {
-    fn from(value: i8) -> Self {
+impl TryFrom<i8> for PMXIndexType {
+    type Error = ReadErrorKind;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
         match value {
-            1 => Self::B1,
-            2 => Self::B2,
-            4 => Self::B4,
-            _ => panic!("invalid PMX index type {}", value)
+            1 => Ok(Self::B1),
+            2 => Ok(Self::B2),
+            4 => Ok(Self::B4),
+            _ => Err(ReadErrorKind::InvalidValue { field: "index size", value: value as i64 })
         }
     }
 }
@@ -195,18 +231,25 @@ pub struct PMXGlobals {
     pub rigidbody_index_size: PMXIndexType
 }
 
-impl From<&PMXGlobalsRaw> for PMXGlobals {
-    fn from(value: &PMXGlobalsRaw) -> Self {
-        Self {
-            text_encoding: value.text_encoding.unwrap_or(1).into(),
-            additional_vec4_count: value.additional_vec4_count.unwrap_or(0),
-            vertex_index_size: value.vertex_index_size.unwrap_or(4).into(),
-            texture_index_size: value.texture_index_size.unwrap_or(4).into(),
-            material_index_size: value.material_index_size.unwrap_or(4).into(),
-            bone_index_size: value.bone_index_size.unwrap_or(4).into(),
-            morph_index_size: value.morph_index_size.unwrap_or(4).into(),
-            rigidbody_index_size: value.rigidbody_index_size.unwrap_or(4).into()
+impl TryFrom<&PMXGlobalsRaw> for PMXGlobals {
+    type Error = ReadErrorKind;
+
+    fn try_from(value: &PMXGlobalsRaw) -> Result<Self, Self::Error> {
+        let additional_vec4_count = value.additional_vec4_count.unwrap_or(0);
+        if !(0..=4).contains(&additional_vec4_count) {
+            return Err(ReadErrorKind::InvalidValue { field: "additional vec4 count", value: additional_vec4_count as i64 });
         }
+
+        Ok(Self {
+            text_encoding: value.text_encoding.unwrap_or(1).try_into()?,
+            additional_vec4_count,
+            vertex_index_size: value.vertex_index_size.unwrap_or(4).try_into()?,
+            texture_index_size: value.texture_index_size.unwrap_or(4).try_into()?,
+            material_index_size: value.material_index_size.unwrap_or(4).try_into()?,
+            bone_index_size: value.bone_index_size.unwrap_or(4).try_into()?,
+            morph_index_size: value.morph_index_size.unwrap_or(4).try_into()?,
+            rigidbody_index_size: value.rigidbody_index_size.unwrap_or(4).try_into()?
+        })
     }
 }
 
@@ -220,55 +263,56 @@ pub enum PMXWeightDeformType {
 }
 
 impl PMXWeightDeformType {
-    pub fn parse(data: &[u8], cursor: &mut usize) -> Self {
-        let ty = utils::read::<i8>(data, cursor);
-        match ty {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
             0 => Self::BDEF1,
             1 => Self::BDEF2,
             2 => Self::BDEF4,
             3 => Self::SDEF,
             4 => Self::QDEF,
-            _ => panic!("Undefined Deform Type {}", ty)
-        }
+            _ => return Err(ReadError::invalid_value("deform type", ty as i64, offset))
+        })
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct BDEF1Data {
-    pub bone_index: Vec<u8>,
+    pub bone_index: i32,
 }
 
 impl BDEF1Data {
-    pub fn parse(data: &[u8], cursor: &mut usize, index_size: usize) -> Self {
-        Self {
-            bone_index: utils::read_var::<u8>(data, cursor, index_size)
-        }
+    pub fn parse(data: &[u8], cursor: &mut usize, index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            bone_index: index_size.parse_i32(data, cursor, false)?
+        })
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct BDEF2Data {
-    pub bone_index1: Vec<u8>,
-    pub bone_index2: Vec<u8>,
+    pub bone_index1: i32,
+    pub bone_index2: i32,
     pub bone1_weight: f32,
 }
 
 impl BDEF2Data {
-    pub fn parse(data: &[u8], cursor: &mut usize, index_size: usize) -> Self {
-        Self {
-            bone_index1: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index2: utils::read_var::<u8>(data, cursor, index_size),
-            bone1_weight: utils::read::<f32>(data, cursor)
-        }
+    pub fn parse(data: &[u8], cursor: &mut usize, index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            bone_index1: index_size.parse_i32(data, cursor, false)?,
+            bone_index2: index_size.parse_i32(data, cursor, false)?,
+            bone1_weight: utils::read::<f32>(data, cursor)?
+        })
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct BDEF4Data {
-    pub bone_index1: Vec<u8>,
-    pub bone_index2: Vec<u8>,
-    pub bone_index3: Vec<u8>,
-    pub bone_index4: Vec<u8>,
+    pub bone_index1: i32,
+    pub bone_index2: i32,
+    pub bone_index3: i32,
+    pub bone_index4: i32,
     pub bone1_weight: f32,
     pub bone2_weight: f32,
     pub bone3_weight: f32,
@@ -276,24 +320,24 @@ pub struct BDEF4Data {
 }
 
 impl BDEF4Data {
-    pub fn parse(data: &[u8], cursor: &mut usize, index_size: usize) -> Self {
-        Self {
-            bone_index1: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index2: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index3: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index4: utils::read_var::<u8>(data, cursor, index_size),
-            bone1_weight: utils::read::<f32>(data, cursor),
-            bone2_weight: utils::read::<f32>(data, cursor),
-            bone3_weight: utils::read::<f32>(data, cursor),
-            bone4_weight: utils::read::<f32>(data, cursor),
-        }
+    pub fn parse(data: &[u8], cursor: &mut usize, index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            bone_index1: index_size.parse_i32(data, cursor, false)?,
+            bone_index2: index_size.parse_i32(data, cursor, false)?,
+            bone_index3: index_size.parse_i32(data, cursor, false)?,
+            bone_index4: index_size.parse_i32(data, cursor, false)?,
+            bone1_weight: utils::read::<f32>(data, cursor)?,
+            bone2_weight: utils::read::<f32>(data, cursor)?,
+            bone3_weight: utils::read::<f32>(data, cursor)?,
+            bone4_weight: utils::read::<f32>(data, cursor)?,
+        })
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct SDEFData {
-    pub bone_index1: Vec<u8>,
-    pub bone_index2: Vec<u8>,
+    pub bone_index1: i32,
+    pub bone_index2: i32,
     pub bone1_weight: f32,
     pub c: [f32; 3],
     pub r0: [f32; 3],
@@ -301,24 +345,24 @@ pub struct SDEFData {
 }
 
 impl SDEFData {
-    pub fn parse(data: &[u8], cursor: &mut usize, index_size: usize) -> Self {
-        Self {
-            bone_index1: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index2: utils::read_var::<u8>(data, cursor, index_size),
-            bone1_weight: utils::read::<f32>(data, cursor),
-            c: utils::read::<[f32; 3]>(data, cursor),
-            r0: utils::read::<[f32; 3]>(data, cursor),
-            r1: utils::read::<[f32; 3]>(data, cursor),
-        }
+    pub fn parse(data: &[u8], cursor: &mut usize, index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            bone_index1: index_size.parse_i32(data, cursor, false)?,
+            bone_index2: index_size.parse_i32(data, cursor, false)?,
+            bone1_weight: utils::read::<f32>(data, cursor)?,
+            c: utils::read::<[f32; 3]>(data, cursor)?,
+            r0: utils::read::<[f32; 3]>(data, cursor)?,
+            r1: utils::read::<[f32; 3]>(data, cursor)?,
+        })
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct QDEFData {
-    pub bone_index1: Vec<u8>,
-    pub bone_index2: Vec<u8>,
-    pub bone_index3: Vec<u8>,
-    pub bone_index4: Vec<u8>,
+    pub bone_index1: i32,
+    pub bone_index2: i32,
+    pub bone_index3: i32,
+    pub bone_index4: i32,
     pub bone1_weight: f32,
     pub bone2_weight: f32,
     pub bone3_weight: f32,
@@ -326,17 +370,17 @@ pub struct QDEFData {
 }
 
 impl QDEFData {
-    pub fn parse(data: &[u8], cursor: &mut usize, index_size: usize) -> Self {
-        Self {
-            bone_index1: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index2: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index3: utils::read_var::<u8>(data, cursor, index_size),
-            bone_index4: utils::read_var::<u8>(data, cursor, index_size),
-            bone1_weight: utils::read::<f32>(data, cursor),
-            bone2_weight: utils::read::<f32>(data, cursor),
-            bone3_weight: utils::read::<f32>(data, cursor),
-            bone4_weight: utils::read::<f32>(data, cursor),
-        }
+    pub fn parse(data: &[u8], cursor: &mut usize, index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            bone_index1: index_size.parse_i32(data, cursor, false)?,
+            bone_index2: index_size.parse_i32(data, cursor, false)?,
+            bone_index3: index_size.parse_i32(data, cursor, false)?,
+            bone_index4: index_size.parse_i32(data, cursor, false)?,
+            bone1_weight: utils::read::<f32>(data, cursor)?,
+            bone2_weight: utils::read::<f32>(data, cursor)?,
+            bone3_weight: utils::read::<f32>(data, cursor)?,
+            bone4_weight: utils::read::<f32>(data, cursor)?,
+        })
     }
 }
 
@@ -350,13 +394,37 @@ pub enum PMXWeightDeformData {
 }
 
 impl PMXWeightDeformData {
-    pub fn parse(data: &[u8], cursor: &mut usize, index_size: usize, ty: PMXWeightDeformType) -> Self {
-        match ty {
-            PMXWeightDeformType::BDEF1 => Self::BDEF1(BDEF1Data::parse(data, cursor, index_size)),
-            PMXWeightDeformType::BDEF2 => Self::BDEF2(BDEF2Data::parse(data, cursor, index_size)),
-            PMXWeightDeformType::BDEF4 => Self::BDEF4(BDEF4Data::parse(data, cursor, index_size)),
-            PMXWeightDeformType::SDEF => Self::SDEF(SDEFData::parse(data, cursor, index_size)),
-            PMXWeightDeformType::QDEF => Self::QDEF(QDEFData::parse(data, cursor, index_size)),
+    pub fn parse(data: &[u8], cursor: &mut usize, index_size: PMXIndexType, ty: PMXWeightDeformType) -> ReadResult<Self> {
+        Ok(match ty {
+            PMXWeightDeformType::BDEF1 => Self::BDEF1(BDEF1Data::parse(data, cursor, index_size)?),
+            PMXWeightDeformType::BDEF2 => Self::BDEF2(BDEF2Data::parse(data, cursor, index_size)?),
+            PMXWeightDeformType::BDEF4 => Self::BDEF4(BDEF4Data::parse(data, cursor, index_size)?),
+            PMXWeightDeformType::SDEF => Self::SDEF(SDEFData::parse(data, cursor, index_size)?),
+            PMXWeightDeformType::QDEF => Self::QDEF(QDEFData::parse(data, cursor, index_size)?),
+        })
+    }
+
+    /// Up to 4 (bone index, weight) pairs for linear blend skinning,
+    /// SDEF and QDEF give their BDEF2 / BDEF4 weights
+    pub fn linear_blend_weights(&self) -> ([i32; 4], [f32; 4]) {
+        match self {
+            Self::BDEF1(d) => ([d.bone_index, -1, -1, -1], [1.0, 0.0, 0.0, 0.0]),
+            Self::BDEF2(d) => (
+                [d.bone_index1, d.bone_index2, -1, -1],
+                [d.bone1_weight, 1.0 - d.bone1_weight, 0.0, 0.0]
+            ),
+            Self::BDEF4(d) => (
+                [d.bone_index1, d.bone_index2, d.bone_index3, d.bone_index4],
+                [d.bone1_weight, d.bone2_weight, d.bone3_weight, d.bone4_weight]
+            ),
+            Self::SDEF(d) => (
+                [d.bone_index1, d.bone_index2, -1, -1],
+                [d.bone1_weight, 1.0 - d.bone1_weight, 0.0, 0.0]
+            ),
+            Self::QDEF(d) => (
+                [d.bone_index1, d.bone_index2, d.bone_index3, d.bone_index4],
+                [d.bone1_weight, d.bone2_weight, d.bone3_weight, d.bone4_weight]
+            ),
         }
     }
 }
@@ -373,18 +441,18 @@ pub struct PMXVertexData {
 }
 
 impl PMXVertexData {
-    pub fn parse(data: &[u8], cursor: &mut usize, index_size: usize, addition_vec4_size: usize) -> Self {
-        let position = utils::read::<[f32; 3]>(data, cursor);
-        let normal = utils::read::<[f32; 3]>(data, cursor);
-        let uv = utils::read::<[f32; 2]>(data, cursor);
-        let additional_vec4 = utils::read_var::<[f32; 4]>(data, cursor, addition_vec4_size);
-        let weight_deform_type = PMXWeightDeformType::parse(data, cursor);
-        let weight_deform = PMXWeightDeformData::parse(data, cursor, index_size, weight_deform_type);
-        let edge_scale = utils::read::<f32>(data, cursor);
+    pub fn parse(data: &[u8], cursor: &mut usize, index_size: PMXIndexType, addition_vec4_size: usize) -> ReadResult<Self> {
+        let position = utils::read::<[f32; 3]>(data, cursor)?;
+        let normal = utils::read::<[f32; 3]>(data, cursor)?;
+        let uv = utils::read::<[f32; 2]>(data, cursor)?;
+        let additional_vec4 = utils::read_var::<[f32; 4]>(data, cursor, addition_vec4_size)?;
+        let weight_deform_type = PMXWeightDeformType::parse(data, cursor)?;
+        let weight_deform = PMXWeightDeformData::parse(data, cursor, index_size, weight_deform_type)?;
+        let edge_scale = utils::read::<f32>(data, cursor)?;
 
         // println!("{:?}", position);
         
-        Self {
+        Ok(Self {
             position,
             normal,
             uv,
@@ -392,7 +460,7 @@ impl PMXVertexData {
             weight_deform_type,
             weight_deform,
             edge_scale
-        }
+        })
     }
 }
 
@@ -403,14 +471,14 @@ pub struct PMXSurfaceData {
 }
 
 impl PMXSurfaceData {
-    pub fn parse(data: &[u8], cursor: &mut usize, vertex_index_size: PMXIndexType) -> Self {
-        Self {
+    pub fn parse(data: &[u8], cursor: &mut usize, vertex_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
             triangle: [
-                vertex_index_size.parse_i32(data, cursor, true),
-                vertex_index_size.parse_i32(data, cursor, true),
-                vertex_index_size.parse_i32(data, cursor, true),
+                vertex_index_size.parse_i32(data, cursor, true)?,
+                vertex_index_size.parse_i32(data, cursor, true)?,
+                vertex_index_size.parse_i32(data, cursor, true)?,
             ]
-        }
+        })
     }
 }
 
@@ -423,15 +491,32 @@ pub enum PMXEnvironmentBlendMode {
 }
 
 impl PMXEnvironmentBlendMode {
-    pub fn parse(data: &[u8], cursor: &mut usize) -> Self {
-        let ty = utils::read::<i8>(data, cursor);
-        match ty {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
             0 => Self::Disabled,
             1 => Self::Multiply,
             2 => Self::Additive,
             3 => Self::AdditionalVec4,
-            _ => panic!("invalid environment blend mode")
-        }
+            _ => return Err(ReadError::invalid_value("environment blend mode", ty as i64, offset))
+        })
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct PMXMaterialFlags: u8 {
+        // double sided
+        const NO_CULL = 0x01;
+        const GROUND_SHADOW = 0x02;
+        const DRAW_SHADOW = 0x04;
+        const RECEIVE_SHADOW = 0x08;
+        const EDGE = 0x10;
+        // the following flags are PMX 2.1 only
+        const VERTEX_COLOR = 0x20;
+        const POINT_DRAWING = 0x40;
+        const LINE_DRAWING = 0x80;
     }
 }
 
@@ -442,13 +527,14 @@ pub enum PMXToonReference {
 }
 
 impl PMXToonReference {
-    pub fn parse(data: &[u8], cursor: &mut usize) -> Self {
-        let ty = utils::read::<i8>(data, cursor);
-        match ty {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
             0 => Self::Texture,
             1 => Self::Internal,
-            _ => panic!("invalid toon reference type")
-        }
+            _ => return Err(ReadError::invalid_value("toon reference type", ty as i64, offset))
+        })
     }
 }
 
@@ -459,15 +545,15 @@ pub enum PMXToonValue {
 }
 
 impl PMXToonValue {
-    pub fn parse(data: &[u8], cursor: &mut usize, ty: PMXToonReference, texture_index_size: PMXIndexType) -> Self {
-        match ty {
+    pub fn parse(data: &[u8], cursor: &mut usize, ty: PMXToonReference, texture_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(match ty {
             PMXToonReference::Texture => Self::Texture(
-                texture_index_size.parse_i32(data, cursor, false)
+                texture_index_size.parse_i32(data, cursor, false)?
             ),
             PMXToonReference::Internal => Self::Internal(
-                utils::read::<i8>(data, cursor)
+                utils::read::<i8>(data, cursor)?
             )
-        }
+        })
     }
 }
 
@@ -479,7 +565,7 @@ pub struct PMXMaterialData {
     pub specular_color: [f32; 3],
     pub specular_strength: f32,
     pub ambient_color: [f32; 3],
-    pub drawing_flags: u8,
+    pub drawing_flags: PMXMaterialFlags,
     pub edge_color: [f32; 4],
     pub edge_scale: f32,
     pub texture_index: i32,
@@ -492,27 +578,27 @@ pub struct PMXMaterialData {
 }
 
 impl PMXMaterialData {
-    pub fn parse(data: &[u8], cursor: &mut usize, texture_index_size: PMXIndexType, text_encoding: TextEncodingType) -> Result<Self> {
-        let material_name_local = utils::read_text(data, cursor);
-        let material_name_universal = utils::read_text(data, cursor);
-        let diffuse_color = utils::read::<[f32; 4]>(data, cursor);
-        let specular_color = utils::read::<[f32; 3]>(data, cursor);
-        let specular_strength = utils::read::<f32>(data, cursor);
-        let ambient_color = utils::read::<[f32; 3]>(data, cursor);
-        let drawing_flags = utils::read::<u8>(data, cursor);
-        let edge_color = utils::read::<[f32; 4]>(data, cursor);
-        let edge_scale = utils::read::<f32>(data, cursor);
-        let texture_index = texture_index_size.parse_i32(data, cursor, false);
-        let environment_index = texture_index_size.parse_i32(data, cursor, false);
-        let environment_blend_mode = PMXEnvironmentBlendMode::parse(data, cursor);
-        let toon_reference = PMXToonReference::parse(data, cursor);
-        let toon_value = PMXToonValue::parse(data, cursor, toon_reference, texture_index_size);
-        let meta_data = utils::read_text(data, cursor);
-        let surface_count = utils::read::<i32>(data, cursor);
+    pub fn parse(data: &[u8], cursor: &mut usize, texture_index_size: PMXIndexType, text_encoding: TextEncodingType) -> ReadResult<Self> {
+        let material_name_local = text_encoding.read_text(data, cursor)?;
+        let material_name_universal = text_encoding.read_text(data, cursor)?;
+        let diffuse_color = utils::read::<[f32; 4]>(data, cursor)?;
+        let specular_color = utils::read::<[f32; 3]>(data, cursor)?;
+        let specular_strength = utils::read::<f32>(data, cursor)?;
+        let ambient_color = utils::read::<[f32; 3]>(data, cursor)?;
+        let drawing_flags = PMXMaterialFlags::from_bits_retain(utils::read::<u8>(data, cursor)?);
+        let edge_color = utils::read::<[f32; 4]>(data, cursor)?;
+        let edge_scale = utils::read::<f32>(data, cursor)?;
+        let texture_index = texture_index_size.parse_i32(data, cursor, false)?;
+        let environment_index = texture_index_size.parse_i32(data, cursor, false)?;
+        let environment_blend_mode = PMXEnvironmentBlendMode::parse(data, cursor)?;
+        let toon_reference = PMXToonReference::parse(data, cursor)?;
+        let toon_value = PMXToonValue::parse(data, cursor, toon_reference, texture_index_size)?;
+        let meta_data = text_encoding.read_text(data, cursor)?;
+        let surface_count = utils::read::<i32>(data, cursor)?;
 
         Ok(Self {
-            material_name_local: text_encoding.parse_text(&material_name_local)?,
-            material_name_universal: text_encoding.parse_text(&material_name_universal)?,
+            material_name_local,
+            material_name_universal,
             diffuse_color,
             specular_color,
             specular_strength,
@@ -525,8 +611,925 @@ impl PMXMaterialData {
             environment_blend_mode,
             toon_reference,
             toon_value,
-            meta_data: text_encoding.parse_text(&meta_data)?,
+            meta_data,
             surface_count
         })
     }
-}
\ No newline at end of file
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct PMXBoneFlags: u16 {
+        const INDEXED_TAIL_POSITION = 0x0001;
+        const ROTATABLE = 0x0002;
+        const TRANSLATABLE = 0x0004;
+        const IS_VISIBLE = 0x0008;
+        const ENABLED = 0x0010;
+        const IK = 0x0020;
+        const INHERIT_LOCAL = 0x0080;
+        const INHERIT_ROTATION = 0x0100;
+        const INHERIT_TRANSLATION = 0x0200;
+        const FIXED_AXIS = 0x0400;
+        const LOCAL_COORDINATE = 0x0800;
+        const PHYSICS_AFTER_DEFORM = 0x1000;
+        const EXTERNAL_PARENT_DEFORM = 0x2000;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PMXBoneTail {
+    // offset from the bone position
+    Position([f32; 3]),
+    BoneIndex(i32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXBoneInheritData {
+    pub parent_index: i32,
+    pub ratio: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXBoneLocalAxes {
+    pub x_axis: [f32; 3],
+    pub z_axis: [f32; 3],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXIKAngleLimit {
+    // euler angles in radians
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXIKLinkData {
+    pub bone_index: i32,
+    pub angle_limit: Option<PMXIKAngleLimit>,
+}
+
+impl PMXIKLinkData {
+    pub fn parse(data: &[u8], cursor: &mut usize, bone_index_size: PMXIndexType) -> ReadResult<Self> {
+        let bone_index = bone_index_size.parse_i32(data, cursor, false)?;
+        let has_limits = utils::read::<i8>(data, cursor)?;
+        let angle_limit = if has_limits == 1 {
+            Some(PMXIKAngleLimit {
+                min: utils::read::<[f32; 3]>(data, cursor)?,
+                max: utils::read::<[f32; 3]>(data, cursor)?,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            bone_index,
+            angle_limit
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXIKData {
+    pub target_index: i32,
+    pub loop_count: i32,
+    // radians per iteration
+    pub limit_angle: f32,
+    pub links: Vec<PMXIKLinkData>,
+}
+
+impl PMXIKData {
+    pub fn parse(data: &[u8], cursor: &mut usize, bone_index_size: PMXIndexType) -> ReadResult<Self> {
+        let target_index = bone_index_size.parse_i32(data, cursor, false)?;
+        let loop_count = utils::read::<i32>(data, cursor)?;
+        let limit_angle = utils::read::<f32>(data, cursor)?;
+        let link_count = utils::read_count(data, cursor)?;
+        let mut links = Vec::new();
+        for _ in 0..link_count {
+            links.push(PMXIKLinkData::parse(data, cursor, bone_index_size)?);
+        }
+
+        Ok(Self {
+            target_index,
+            loop_count,
+            limit_angle,
+            links
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXBoneData {
+    pub bone_name_local: String,
+    pub bone_name_universal: String,
+    pub position: [f32; 3],
+    pub parent_index: i32,
+    pub layer: i32,
+    pub flags: PMXBoneFlags,
+    pub tail: PMXBoneTail,
+    pub inherit: Option<PMXBoneInheritData>,
+    pub fixed_axis: Option<[f32; 3]>,
+    pub local_axes: Option<PMXBoneLocalAxes>,
+    pub external_parent_key: Option<i32>,
+    pub ik: Option<PMXIKData>,
+}
+
+impl PMXBoneData {
+    pub fn parse(data: &[u8], cursor: &mut usize, bone_index_size: PMXIndexType, text_encoding: TextEncodingType) -> ReadResult<Self> {
+        let bone_name_local = text_encoding.read_text(data, cursor)?;
+        let bone_name_universal = text_encoding.read_text(data, cursor)?;
+        let position = utils::read::<[f32; 3]>(data, cursor)?;
+        let parent_index = bone_index_size.parse_i32(data, cursor, false)?;
+        let layer = utils::read::<i32>(data, cursor)?;
+        let flags = PMXBoneFlags::from_bits_retain(utils::read::<u16>(data, cursor)?);
+
+        let tail = if flags.contains(PMXBoneFlags::INDEXED_TAIL_POSITION) {
+            PMXBoneTail::BoneIndex(bone_index_size.parse_i32(data, cursor, false)?)
+        } else {
+            PMXBoneTail::Position(utils::read::<[f32; 3]>(data, cursor)?)
+        };
+
+        let inherit = if flags.intersects(PMXBoneFlags::INHERIT_ROTATION | PMXBoneFlags::INHERIT_TRANSLATION) {
+            Some(PMXBoneInheritData {
+                parent_index: bone_index_size.parse_i32(data, cursor, false)?,
+                ratio: utils::read::<f32>(data, cursor)?,
+            })
+        } else {
+            None
+        };
+
+        let fixed_axis = if flags.contains(PMXBoneFlags::FIXED_AXIS) {
+            Some(utils::read::<[f32; 3]>(data, cursor)?)
+        } else {
+            None
+        };
+
+        let local_axes = if flags.contains(PMXBoneFlags::LOCAL_COORDINATE) {
+            Some(PMXBoneLocalAxes {
+                x_axis: utils::read::<[f32; 3]>(data, cursor)?,
+                z_axis: utils::read::<[f32; 3]>(data, cursor)?,
+            })
+        } else {
+            None
+        };
+
+        let external_parent_key = if flags.contains(PMXBoneFlags::EXTERNAL_PARENT_DEFORM) {
+            Some(utils::read::<i32>(data, cursor)?)
+        } else {
+            None
+        };
+
+        let ik = if flags.contains(PMXBoneFlags::IK) {
+            Some(PMXIKData::parse(data, cursor, bone_index_size)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            bone_name_local,
+            bone_name_universal,
+            position,
+            parent_index,
+            layer,
+            flags,
+            tail,
+            inherit,
+            fixed_axis,
+            local_axes,
+            external_parent_key,
+            ik
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXMorphPanel {
+    Hidden,
+    Eyebrows,
+    Eyes,
+    Mouth,
+    Other,
+}
+
+impl PMXMorphPanel {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::Hidden,
+            1 => Self::Eyebrows,
+            2 => Self::Eyes,
+            3 => Self::Mouth,
+            4 => Self::Other,
+            _ => return Err(ReadError::invalid_value("morph panel", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXMorphType {
+    Group,
+    Vertex,
+    Bone,
+    UV,
+    AdditionalUV1,
+    AdditionalUV2,
+    AdditionalUV3,
+    AdditionalUV4,
+    Material,
+    Flip,
+    Impulse,
+}
+
+impl PMXMorphType {
+    pub fn parse(data: &[u8], cursor: &mut usize, version: PMXVersion) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        if version == PMXVersion::V2_0 && ty > 8 {
+            return Err(ReadError::invalid_value("PMX 2.0 morph type", ty as i64, offset));
+        }
+        Ok(match ty {
+            0 => Self::Group,
+            1 => Self::Vertex,
+            2 => Self::Bone,
+            3 => Self::UV,
+            4 => Self::AdditionalUV1,
+            5 => Self::AdditionalUV2,
+            6 => Self::AdditionalUV3,
+            7 => Self::AdditionalUV4,
+            8 => Self::Material,
+            9 => Self::Flip,
+            10 => Self::Impulse,
+            _ => return Err(ReadError::invalid_value("morph type", ty as i64, offset))
+        })
+    }
+
+    /// Which uv channel a uv morph targets, 0 for the base uv and 1-4 for the additional vec4s
+    pub fn uv_channel(&self) -> Option<usize> {
+        match *self {
+            Self::UV => Some(0),
+            Self::AdditionalUV1 => Some(1),
+            Self::AdditionalUV2 => Some(2),
+            Self::AdditionalUV3 => Some(3),
+            Self::AdditionalUV4 => Some(4),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXGroupMorphOffset {
+    pub morph_index: i32,
+    pub influence: f32,
+}
+
+impl PMXGroupMorphOffset {
+    pub fn parse(data: &[u8], cursor: &mut usize, morph_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            morph_index: morph_index_size.parse_i32(data, cursor, false)?,
+            influence: utils::read::<f32>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXVertexMorphOffset {
+    pub vertex_index: i32,
+    pub translation: [f32; 3],
+}
+
+impl PMXVertexMorphOffset {
+    pub fn parse(data: &[u8], cursor: &mut usize, vertex_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            vertex_index: vertex_index_size.parse_i32(data, cursor, true)?,
+            translation: utils::read::<[f32; 3]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXBoneMorphOffset {
+    pub bone_index: i32,
+    pub translation: [f32; 3],
+    // quaternion, xyzw
+    pub rotation: [f32; 4],
+}
+
+impl PMXBoneMorphOffset {
+    pub fn parse(data: &[u8], cursor: &mut usize, bone_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            bone_index: bone_index_size.parse_i32(data, cursor, false)?,
+            translation: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation: utils::read::<[f32; 4]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXUVMorphOffset {
+    pub vertex_index: i32,
+    pub offset: [f32; 4],
+}
+
+impl PMXUVMorphOffset {
+    pub fn parse(data: &[u8], cursor: &mut usize, vertex_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            vertex_index: vertex_index_size.parse_i32(data, cursor, true)?,
+            offset: utils::read::<[f32; 4]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXMaterialMorphMethod {
+    Multiply,
+    Additive,
+}
+
+impl PMXMaterialMorphMethod {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::Multiply,
+            1 => Self::Additive,
+            _ => return Err(ReadError::invalid_value("material morph method", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXMaterialMorphOffset {
+    // -1 means all materials
+    pub material_index: i32,
+    pub method: PMXMaterialMorphMethod,
+    pub diffuse_color: [f32; 4],
+    pub specular_color: [f32; 3],
+    pub specular_strength: f32,
+    pub ambient_color: [f32; 3],
+    pub edge_color: [f32; 4],
+    pub edge_scale: f32,
+    pub texture_tint: [f32; 4],
+    pub environment_tint: [f32; 4],
+    pub toon_tint: [f32; 4],
+}
+
+impl PMXMaterialMorphOffset {
+    pub fn parse(data: &[u8], cursor: &mut usize, material_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            material_index: material_index_size.parse_i32(data, cursor, false)?,
+            method: PMXMaterialMorphMethod::parse(data, cursor)?,
+            diffuse_color: utils::read::<[f32; 4]>(data, cursor)?,
+            specular_color: utils::read::<[f32; 3]>(data, cursor)?,
+            specular_strength: utils::read::<f32>(data, cursor)?,
+            ambient_color: utils::read::<[f32; 3]>(data, cursor)?,
+            edge_color: utils::read::<[f32; 4]>(data, cursor)?,
+            edge_scale: utils::read::<f32>(data, cursor)?,
+            texture_tint: utils::read::<[f32; 4]>(data, cursor)?,
+            environment_tint: utils::read::<[f32; 4]>(data, cursor)?,
+            toon_tint: utils::read::<[f32; 4]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXFlipMorphOffset {
+    pub morph_index: i32,
+    pub influence: f32,
+}
+
+impl PMXFlipMorphOffset {
+    pub fn parse(data: &[u8], cursor: &mut usize, morph_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            morph_index: morph_index_size.parse_i32(data, cursor, false)?,
+            influence: utils::read::<f32>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXImpulseMorphOffset {
+    pub rigidbody_index: i32,
+    pub is_local: bool,
+    pub movement_speed: [f32; 3],
+    pub rotation_torque: [f32; 3],
+}
+
+impl PMXImpulseMorphOffset {
+    pub fn parse(data: &[u8], cursor: &mut usize, rigidbody_index_size: PMXIndexType) -> ReadResult<Self> {
+        Ok(Self {
+            rigidbody_index: rigidbody_index_size.parse_i32(data, cursor, false)?,
+            is_local: utils::read::<i8>(data, cursor)? != 0,
+            movement_speed: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation_torque: utils::read::<[f32; 3]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum PMXMorphOffsetData {
+    Group(Vec<PMXGroupMorphOffset>),
+    Vertex(Vec<PMXVertexMorphOffset>),
+    Bone(Vec<PMXBoneMorphOffset>),
+    // base uv and additional uv1-4, see PMXMorphType::uv_channel
+    UV(Vec<PMXUVMorphOffset>),
+    Material(Vec<PMXMaterialMorphOffset>),
+    Flip(Vec<PMXFlipMorphOffset>),
+    Impulse(Vec<PMXImpulseMorphOffset>),
+}
+
+impl PMXMorphOffsetData {
+    pub fn parse(data: &[u8], cursor: &mut usize, globals: &PMXGlobals, ty: PMXMorphType) -> ReadResult<Self> {
+        let count = utils::read_count(data, cursor)?;
+
+        macro_rules! parse_offsets {
+            ($t:ty, $index_size:expr) => {{
+                let mut offsets = Vec::new();
+                for _ in 0..count {
+                    offsets.push(<$t>::parse(data, cursor, $index_size)?);
+                }
+                offsets
+            }};
+        }
+
+        Ok(match ty {
+            PMXMorphType::Group => Self::Group(parse_offsets!(PMXGroupMorphOffset, globals.morph_index_size)),
+            PMXMorphType::Vertex => Self::Vertex(parse_offsets!(PMXVertexMorphOffset, globals.vertex_index_size)),
+            PMXMorphType::Bone => Self::Bone(parse_offsets!(PMXBoneMorphOffset, globals.bone_index_size)),
+            PMXMorphType::UV
+            | PMXMorphType::AdditionalUV1
+            | PMXMorphType::AdditionalUV2
+            | PMXMorphType::AdditionalUV3
+            | PMXMorphType::AdditionalUV4 => Self::UV(parse_offsets!(PMXUVMorphOffset, globals.vertex_index_size)),
+            PMXMorphType::Material => Self::Material(parse_offsets!(PMXMaterialMorphOffset, globals.material_index_size)),
+            PMXMorphType::Flip => Self::Flip(parse_offsets!(PMXFlipMorphOffset, globals.morph_index_size)),
+            PMXMorphType::Impulse => Self::Impulse(parse_offsets!(PMXImpulseMorphOffset, globals.rigidbody_index_size)),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXMorphData {
+    pub morph_name_local: String,
+    pub morph_name_universal: String,
+    pub panel: PMXMorphPanel,
+    pub morph_type: PMXMorphType,
+    pub offsets: PMXMorphOffsetData,
+}
+
+impl PMXMorphData {
+    pub fn parse(data: &[u8], cursor: &mut usize, globals: &PMXGlobals, version: PMXVersion) -> ReadResult<Self> {
+        let morph_name_local = globals.text_encoding.read_text(data, cursor)?;
+        let morph_name_universal = globals.text_encoding.read_text(data, cursor)?;
+        let panel = PMXMorphPanel::parse(data, cursor)?;
+        let morph_type = PMXMorphType::parse(data, cursor, version)?;
+        let offsets = PMXMorphOffsetData::parse(data, cursor, globals, morph_type)?;
+
+        Ok(Self {
+            morph_name_local,
+            morph_name_universal,
+            panel,
+            morph_type,
+            offsets
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXFrameData {
+    Bone(i32),
+    Morph(i32),
+}
+
+impl PMXFrameData {
+    pub fn parse(data: &[u8], cursor: &mut usize, bone_index_size: PMXIndexType, morph_index_size: PMXIndexType) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::Bone(bone_index_size.parse_i32(data, cursor, false)?),
+            1 => Self::Morph(morph_index_size.parse_i32(data, cursor, false)?),
+            _ => return Err(ReadError::invalid_value("display frame type", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXDisplayFrameData {
+    pub display_name_local: String,
+    pub display_name_universal: String,
+    // special frames are "Root" and "表情", which can not be edited in PMXEditor
+    pub is_special: bool,
+    pub frames: Vec<PMXFrameData>,
+}
+
+impl PMXDisplayFrameData {
+    pub fn parse(data: &[u8], cursor: &mut usize, globals: &PMXGlobals) -> ReadResult<Self> {
+        let display_name_local = globals.text_encoding.read_text(data, cursor)?;
+        let display_name_universal = globals.text_encoding.read_text(data, cursor)?;
+        let is_special = utils::read::<i8>(data, cursor)? == 1;
+        let frame_count = utils::read_count(data, cursor)?;
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            frames.push(PMXFrameData::parse(data, cursor, globals.bone_index_size, globals.morph_index_size)?);
+        }
+
+        Ok(Self {
+            display_name_local,
+            display_name_universal,
+            is_special,
+            frames
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXRigidBodyShape {
+    Sphere,
+    Box,
+    Capsule,
+}
+
+impl PMXRigidBodyShape {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::Sphere,
+            1 => Self::Box,
+            2 => Self::Capsule,
+            _ => return Err(ReadError::invalid_value("rigid body shape", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXPhysicsMode {
+    FollowBone,
+    Physics,
+    PhysicsAndBone,
+}
+
+impl PMXPhysicsMode {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::FollowBone,
+            1 => Self::Physics,
+            2 => Self::PhysicsAndBone,
+            _ => return Err(ReadError::invalid_value("physics mode", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXRigidBodyData {
+    pub rigidbody_name_local: String,
+    pub rigidbody_name_universal: String,
+    pub bone_index: i32,
+    pub group_id: u8,
+    // bit n set means this body collides with group n
+    pub non_collision_mask: u16,
+    pub shape: PMXRigidBodyShape,
+    pub shape_size: [f32; 3],
+    pub shape_position: [f32; 3],
+    // euler angles in radians
+    pub shape_rotation: [f32; 3],
+    pub mass: f32,
+    pub move_attenuation: f32,
+    pub rotation_damping: f32,
+    pub repulsion: f32,
+    pub friction_force: f32,
+    pub physics_mode: PMXPhysicsMode,
+}
+
+impl PMXRigidBodyData {
+    pub fn parse(data: &[u8], cursor: &mut usize, bone_index_size: PMXIndexType, text_encoding: TextEncodingType) -> ReadResult<Self> {
+        let rigidbody_name_local = text_encoding.read_text(data, cursor)?;
+        let rigidbody_name_universal = text_encoding.read_text(data, cursor)?;
+        let bone_index = bone_index_size.parse_i32(data, cursor, false)?;
+        let group_id = utils::read::<u8>(data, cursor)?;
+        let non_collision_mask = utils::read::<u16>(data, cursor)?;
+        let shape = PMXRigidBodyShape::parse(data, cursor)?;
+        let shape_size = utils::read::<[f32; 3]>(data, cursor)?;
+        let shape_position = utils::read::<[f32; 3]>(data, cursor)?;
+        let shape_rotation = utils::read::<[f32; 3]>(data, cursor)?;
+        let mass = utils::read::<f32>(data, cursor)?;
+        let move_attenuation = utils::read::<f32>(data, cursor)?;
+        let rotation_damping = utils::read::<f32>(data, cursor)?;
+        let repulsion = utils::read::<f32>(data, cursor)?;
+        let friction_force = utils::read::<f32>(data, cursor)?;
+        let physics_mode = PMXPhysicsMode::parse(data, cursor)?;
+
+        Ok(Self {
+            rigidbody_name_local,
+            rigidbody_name_universal,
+            bone_index,
+            group_id,
+            non_collision_mask,
+            shape,
+            shape_size,
+            shape_position,
+            shape_rotation,
+            mass,
+            move_attenuation,
+            rotation_damping,
+            repulsion,
+            friction_force,
+            physics_mode
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXJointType {
+    Spring6DOF,
+    // the following types are PMX 2.1 only
+    SixDOF,
+    PointToPoint,
+    ConeTwist,
+    Slider,
+    Hinge,
+}
+
+impl PMXJointType {
+    pub fn parse(data: &[u8], cursor: &mut usize, version: PMXVersion) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        if version == PMXVersion::V2_0 && ty != 0 {
+            return Err(ReadError::invalid_value("PMX 2.0 joint type", ty as i64, offset));
+        }
+        Ok(match ty {
+            0 => Self::Spring6DOF,
+            1 => Self::SixDOF,
+            2 => Self::PointToPoint,
+            3 => Self::ConeTwist,
+            4 => Self::Slider,
+            5 => Self::Hinge,
+            _ => return Err(ReadError::invalid_value("joint type", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXJointData {
+    pub joint_name_local: String,
+    pub joint_name_universal: String,
+    pub joint_type: PMXJointType,
+    pub rigidbody_index_a: i32,
+    pub rigidbody_index_b: i32,
+    pub position: [f32; 3],
+    // euler angles in radians
+    pub rotation: [f32; 3],
+    pub position_min: [f32; 3],
+    pub position_max: [f32; 3],
+    pub rotation_min: [f32; 3],
+    pub rotation_max: [f32; 3],
+    pub position_spring: [f32; 3],
+    pub rotation_spring: [f32; 3],
+}
+
+impl PMXJointData {
+    pub fn parse(
+        data: &[u8],
+        cursor: &mut usize,
+        rigidbody_index_size: PMXIndexType,
+        text_encoding: TextEncodingType,
+        version: PMXVersion
+    ) -> ReadResult<Self> {
+        let joint_name_local = text_encoding.read_text(data, cursor)?;
+        let joint_name_universal = text_encoding.read_text(data, cursor)?;
+        let joint_type = PMXJointType::parse(data, cursor, version)?;
+
+        Ok(Self {
+            joint_name_local,
+            joint_name_universal,
+            joint_type,
+            rigidbody_index_a: rigidbody_index_size.parse_i32(data, cursor, false)?,
+            rigidbody_index_b: rigidbody_index_size.parse_i32(data, cursor, false)?,
+            position: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation: utils::read::<[f32; 3]>(data, cursor)?,
+            position_min: utils::read::<[f32; 3]>(data, cursor)?,
+            position_max: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation_min: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation_max: utils::read::<[f32; 3]>(data, cursor)?,
+            position_spring: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation_spring: utils::read::<[f32; 3]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXSoftBodyShape {
+    TriMesh,
+    Rope,
+}
+
+impl PMXSoftBodyShape {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::TriMesh,
+            1 => Self::Rope,
+            _ => return Err(ReadError::invalid_value("soft body shape", ty as i64, offset))
+        })
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct PMXSoftBodyFlags: u8 {
+        const B_LINK = 0x01;
+        const CLUSTER_CREATION = 0x02;
+        const LINK_CROSSING = 0x04;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PMXSoftBodyAeroModel {
+    VertexPoint,
+    VertexTwoSided,
+    VertexOneSided,
+    FaceTwoSided,
+    FaceOneSided,
+}
+
+impl PMXSoftBodyAeroModel {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<i32>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::VertexPoint,
+            1 => Self::VertexTwoSided,
+            2 => Self::VertexOneSided,
+            3 => Self::FaceTwoSided,
+            4 => Self::FaceOneSided,
+            _ => return Err(ReadError::invalid_value("soft body aero model", ty as i64, offset))
+        })
+    }
+}
+
+// names follow the bullet soft body config
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXSoftBodyConfig {
+    pub vcf: f32,
+    pub dp: f32,
+    pub dg: f32,
+    pub lf: f32,
+    pub pr: f32,
+    pub vc: f32,
+    pub df: f32,
+    pub mt: f32,
+    pub chr: f32,
+    pub khr: f32,
+    pub shr: f32,
+    pub ahr: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXSoftBodyClusterConfig {
+    pub srhr_cl: f32,
+    pub skhr_cl: f32,
+    pub sshr_cl: f32,
+    pub sr_splt_cl: f32,
+    pub sk_splt_cl: f32,
+    pub ss_splt_cl: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXSoftBodyIterationConfig {
+    pub v_it: i32,
+    pub p_it: i32,
+    pub d_it: i32,
+    pub c_it: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXSoftBodyMaterialConfig {
+    pub lst: f32,
+    pub ast: f32,
+    pub vst: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PMXSoftBodyAnchorData {
+    pub rigidbody_index: i32,
+    pub vertex_index: i32,
+    pub near_mode: bool,
+}
+
+impl PMXSoftBodyAnchorData {
+    pub fn parse(data: &[u8], cursor: &mut usize, globals: &PMXGlobals) -> ReadResult<Self> {
+        Ok(Self {
+            rigidbody_index: globals.rigidbody_index_size.parse_i32(data, cursor, false)?,
+            vertex_index: globals.vertex_index_size.parse_i32(data, cursor, true)?,
+            near_mode: utils::read::<i8>(data, cursor)? != 0,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PMXSoftBodyData {
+    pub softbody_name_local: String,
+    pub softbody_name_universal: String,
+    pub shape: PMXSoftBodyShape,
+    pub material_index: i32,
+    pub group_id: u8,
+    pub non_collision_mask: u16,
+    pub flags: PMXSoftBodyFlags,
+    pub b_link_create_distance: i32,
+    pub cluster_count: i32,
+    pub total_mass: f32,
+    pub collision_margin: f32,
+    pub aero_model: PMXSoftBodyAeroModel,
+    pub config: PMXSoftBodyConfig,
+    pub cluster_config: PMXSoftBodyClusterConfig,
+    pub iteration_config: PMXSoftBodyIterationConfig,
+    pub material_config: PMXSoftBodyMaterialConfig,
+    pub anchors: Vec<PMXSoftBodyAnchorData>,
+    pub pin_vertices: Vec<i32>,
+}
+
+impl PMXSoftBodyData {
+    pub fn parse(data: &[u8], cursor: &mut usize, globals: &PMXGlobals) -> ReadResult<Self> {
+        let softbody_name_local = globals.text_encoding.read_text(data, cursor)?;
+        let softbody_name_universal = globals.text_encoding.read_text(data, cursor)?;
+        let shape = PMXSoftBodyShape::parse(data, cursor)?;
+        let material_index = globals.material_index_size.parse_i32(data, cursor, false)?;
+        let group_id = utils::read::<u8>(data, cursor)?;
+        let non_collision_mask = utils::read::<u16>(data, cursor)?;
+        let flags = PMXSoftBodyFlags::from_bits_retain(utils::read::<u8>(data, cursor)?);
+        let b_link_create_distance = utils::read::<i32>(data, cursor)?;
+        let cluster_count = utils::read::<i32>(data, cursor)?;
+        let total_mass = utils::read::<f32>(data, cursor)?;
+        let collision_margin = utils::read::<f32>(data, cursor)?;
+        let aero_model = PMXSoftBodyAeroModel::parse(data, cursor)?;
+
+        let config = PMXSoftBodyConfig {
+            vcf: utils::read::<f32>(data, cursor)?,
+            dp: utils::read::<f32>(data, cursor)?,
+            dg: utils::read::<f32>(data, cursor)?,
+            lf: utils::read::<f32>(data, cursor)?,
+            pr: utils::read::<f32>(data, cursor)?,
+            vc: utils::read::<f32>(data, cursor)?,
+            df: utils::read::<f32>(data, cursor)?,
+            mt: utils::read::<f32>(data, cursor)?,
+            chr: utils::read::<f32>(data, cursor)?,
+            khr: utils::read::<f32>(data, cursor)?,
+            shr: utils::read::<f32>(data, cursor)?,
+            ahr: utils::read::<f32>(data, cursor)?,
+        };
+        let cluster_config = PMXSoftBodyClusterConfig {
+            srhr_cl: utils::read::<f32>(data, cursor)?,
+            skhr_cl: utils::read::<f32>(data, cursor)?,
+            sshr_cl: utils::read::<f32>(data, cursor)?,
+            sr_splt_cl: utils::read::<f32>(data, cursor)?,
+            sk_splt_cl: utils::read::<f32>(data, cursor)?,
+            ss_splt_cl: utils::read::<f32>(data, cursor)?,
+        };
+        let iteration_config = PMXSoftBodyIterationConfig {
+            v_it: utils::read::<i32>(data, cursor)?,
+            p_it: utils::read::<i32>(data, cursor)?,
+            d_it: utils::read::<i32>(data, cursor)?,
+            c_it: utils::read::<i32>(data, cursor)?,
+        };
+        let material_config = PMXSoftBodyMaterialConfig {
+            lst: utils::read::<f32>(data, cursor)?,
+            ast: utils::read::<f32>(data, cursor)?,
+            vst: utils::read::<f32>(data, cursor)?,
+        };
+
+        let anchor_count = utils::read_count(data, cursor)?;
+        let mut anchors = Vec::new();
+        for _ in 0..anchor_count {
+            anchors.push(PMXSoftBodyAnchorData::parse(data, cursor, globals)?);
+        }
+
+        let pin_vertex_count = utils::read_count(data, cursor)?;
+        let mut pin_vertices = Vec::new();
+        for _ in 0..pin_vertex_count {
+            pin_vertices.push(globals.vertex_index_size.parse_i32(data, cursor, true)?);
+        }
+
+        Ok(Self {
+            softbody_name_local,
+            softbody_name_universal,
+            shape,
+            material_index,
+            group_id,
+            non_collision_mask,
+            flags,
+            b_link_create_distance,
+            cluster_count,
+            total_mass,
+            collision_margin,
+            aero_model,
+            config,
+            cluster_config,
+            iteration_config,
+            material_config,
+            anchors,
+            pin_vertices
+        })
+    }
+}
@@ -0,0 +1,67 @@
+use std::{fmt, fs, path::{Path, PathBuf}};
+
+// tried in order when the file named by the model does not exist
+const FALLBACK_EXTENSIONS: [&str; 7] = ["png", "bmp", "jpg", "jpeg", "tga", "dds", "gif"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextureWarningKind {
+    IndexOutOfRange { count: usize },
+    NotFound,
+    // the file exists but is not an image we can decode
+    Unreadable(String),
+}
+
+/// A texture reference of a model that could not be loaded, the material samples white instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureWarning {
+    // -1 for a shared toon of the toon directory
+    pub texture_index: i32,
+    // as written in the model, empty for indices out of range
+    pub path: String,
+    pub kind: TextureWarningKind,
+}
+
+impl fmt::Display for TextureWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TextureWarningKind::IndexOutOfRange { count } => write!(f, "texture index {} out of range, count is {}", self.texture_index, count),
+            TextureWarningKind::NotFound => write!(f, "texture {} \"{}\" not found", self.texture_index, self.path),
+            TextureWarningKind::Unreadable(error) if self.texture_index < 0 => write!(f, "shared toon \"{}\" can not be read: {}", self.path, error),
+            TextureWarningKind::Unreadable(error) => write!(f, "texture {} \"{}\" can not be read: {}", self.texture_index, self.path, error),
+        }
+    }
+}
+
+// `name` in `dir`, exactly or ignoring case
+fn find_entry(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exact = dir.join(name);
+    if exact.exists() {
+        return Some(exact);
+    }
+    let lower = name.to_lowercase();
+    fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_str().map(|n| n.to_lowercase() == lower).unwrap_or(false))
+        .map(|entry| entry.path())
+}
+
+/// Finds the file a model means by `raw`, relative to `model_dir`. Windows separators are
+/// accepted, every component may differ in case and the file may have another common image extension.
+pub fn resolve_texture_path(model_dir: &Path, raw: &str) -> Option<PathBuf> {
+    let normalized = raw.trim().replace('\\', "/");
+    let components = normalized.split('/').filter(|c| !c.is_empty() && *c != ".").collect::<Vec<_>>();
+    let (file_name, dirs) = components.split_last()?;
+
+    let mut dir = if normalized.starts_with('/') { PathBuf::from("/") } else { model_dir.to_path_buf() };
+    for &component in dirs.iter() {
+        dir = if component == ".." { dir.join("..") } else { find_entry(&dir, component)? };
+    }
+
+    if let Some(path) = find_entry(&dir, file_name).filter(|p| p.is_file()) {
+        return Some(path);
+    }
+    let stem = Path::new(file_name).file_stem()?.to_str()?;
+    FALLBACK_EXTENSIONS.iter()
+        .filter_map(|extension| find_entry(&dir, &format!("{}.{}", stem, extension)))
+        .find(|p| p.is_file())
+}
@@ -1,27 +1,133 @@
-use std::mem;
-
-pub fn read<T: Sized>(data: &[u8], position: &mut usize) -> T {
-    let size = mem::size_of::<T>();
-    let result = unsafe {
-        let slice = &data[*position..*position + size];
-        let ptr = slice.as_ptr() as *const T;
-        mem::transmute_copy(&*ptr)
-    };
+use std::{mem, fmt};
+
+pub type ReadResult<T> = Result<T, ReadError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadErrorKind {
+    UnexpectedEof { needed: usize, remaining: usize },
+    InvalidLength(i64),
+    InvalidValue { field: &'static str, value: i64 },
+    InvalidText(String),
+}
+
+impl fmt::Display for ReadErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof { needed, remaining } => write!(f, "unexpected end of data, need {} bytes but {} remaining", needed, remaining),
+            Self::InvalidLength(length) => write!(f, "invalid length {}", length),
+            Self::InvalidValue { field, value } => write!(f, "invalid {} {}", field, value),
+            Self::InvalidText(reason) => write!(f, "invalid text: {}", reason),
+        }
+    }
+}
+
+/// A failed read from a byte buffer, `offset` is where the failing item starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadError {
+    pub kind: ReadErrorKind,
+    pub offset: usize,
+}
+
+impl ReadError {
+    pub fn new(kind: ReadErrorKind, offset: usize) -> Self {
+        Self {
+            kind,
+            offset
+        }
+    }
+
+    pub fn invalid_value(field: &'static str, value: i64, offset: usize) -> Self {
+        Self::new(ReadErrorKind::InvalidValue { field, value }, offset)
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+/// Types that are valid for any bit pattern and can be read straight from file data
+///
+/// # Safety
+/// Implementors must have no padding, no invalid bit patterns and no pointers
+pub unsafe trait Pod: Copy {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for f32 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+pub fn read_bytes<'a>(data: &'a [u8], position: &mut usize, size: usize) -> ReadResult<&'a [u8]> {
+    let remaining = data.len().saturating_sub(*position);
+    if size > remaining {
+        return Err(ReadError::new(ReadErrorKind::UnexpectedEof { needed: size, remaining }, *position));
+    }
+
+    let slice = &data[*position..*position + size];
     *position += size;
-    result
+    Ok(slice)
 }
 
-pub fn read_var<T: Sized>(data: &[u8], position: &mut usize, size: usize) -> Vec<T> {
+pub fn read<T: Pod>(data: &[u8], position: &mut usize) -> ReadResult<T> {
+    let bytes = read_bytes(data, position, mem::size_of::<T>())?;
+    // file data has no alignment guarantee
+    let result = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) };
+    Ok(result)
+}
+
+pub fn read_var<T: Pod>(data: &[u8], position: &mut usize, size: usize) -> ReadResult<Vec<T>> {
+    // check the whole range first, so a corrupt size can not cause a huge allocation
     let element_size = mem::size_of::<T>();
-    let mut result = Vec::new();
+    let remaining = data.len().saturating_sub(*position);
+    let needed = size.saturating_mul(element_size);
+    if needed > remaining {
+        return Err(ReadError::new(ReadErrorKind::UnexpectedEof { needed, remaining }, *position));
+    }
+
+    let mut result = Vec::with_capacity(size);
     for _ in 0..size {
-        result.push(read::<T>(data, position));
+        result.push(read::<T>(data, position)?);
     }
-    result
+    Ok(result)
 }
 
-pub fn read_text(data: &[u8], position: &mut usize) -> (i32, Vec<u8>) {
-    let length = read::<i32>(data, position);
-    let text = read_var::<u8>(data, position, length as usize);
-    (length, text)
-}
\ No newline at end of file
+/// Reads an i32 element count, rejecting negative values
+pub fn read_count(data: &[u8], position: &mut usize) -> ReadResult<usize> {
+    let offset = *position;
+    let count = read::<i32>(data, position)?;
+    if count < 0 {
+        return Err(ReadError::new(ReadErrorKind::InvalidLength(count as i64), offset));
+    }
+    Ok(count as usize)
+}
+
+pub fn read_text(data: &[u8], position: &mut usize) -> ReadResult<(i32, Vec<u8>)> {
+    let offset = *position;
+    let length = read_count(data, position)?;
+    let text = read_bytes(data, position, length)
+        .map_err(|e| ReadError::new(e.kind, offset))?
+        .to_vec();
+    Ok((length as i32, text))
+}
+
+/// Reads a fixed size, NUL padded Shift-JIS string as used by PMD and VMD files
+pub fn read_sjis(data: &[u8], position: &mut usize, size: usize) -> ReadResult<String> {
+    let bytes = read_bytes(data, position, size)?;
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let (text, _, _) = encoding_rs::SHIFT_JIS.decode(&bytes[..end]);
+    Ok(text.into_owned())
+}
+
+/// Appends the in-memory bytes of `value`, the counterpart of `read`
+pub fn write<T: Pod>(buffer: &mut Vec<u8>, value: T) {
+    // Pod types have no padding, so every byte is initialized
+    let bytes = unsafe { std::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>()) };
+    buffer.extend_from_slice(bytes);
+}
@@ -0,0 +1,5 @@
+pub mod vmd_parser;
+pub mod vmd_error;
+pub mod structs;
+pub mod vmd_player;
+pub mod vmd_camera;
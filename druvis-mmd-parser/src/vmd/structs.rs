@@ -0,0 +1,299 @@
+use crate::utils::{self, ReadResult, ReadError};
+
+pub const VMD_SIGNATURE_V1: &str = "Vocaloid Motion Data file";
+pub const VMD_SIGNATURE_V2: &str = "Vocaloid Motion Data 0002";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VMDVersion {
+    // model names are 10 bytes
+    V1,
+    // model names are 20 bytes
+    V2,
+}
+
+impl VMDVersion {
+    pub fn model_name_size(&self) -> usize {
+        match *self {
+            Self::V1 => 10,
+            Self::V2 => 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDHeader {
+    pub version: VMDVersion,
+    pub model_name: String,
+}
+
+/// Cubic bezier control points, (0, 0) and (127, 127) are the implicit end points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VMDBezier {
+    pub x1: u8,
+    pub y1: u8,
+    pub x2: u8,
+    pub y2: u8,
+}
+
+impl VMDBezier {
+    pub const LINEAR: Self = Self { x1: 20, y1: 20, x2: 107, y2: 107 };
+
+    fn curve(p1: f32, p2: f32, s: f32) -> f32 {
+        let r = 1.0 - s;
+        3.0 * r * r * s * p1 + 3.0 * r * s * s * p2 + s * s * s
+    }
+
+    /// Maps the linear progress `x` between two keyframes to the eased progress, both in [0, 1]
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        if self.x1 == self.y1 && self.x2 == self.y2 {
+            return x;
+        }
+        let (x1, x2) = (self.x1 as f32 / 127.0, self.x2 as f32 / 127.0);
+        let (y1, y2) = (self.y1 as f32 / 127.0, self.y2 as f32 / 127.0);
+
+        // x(s) is monotonic since the control points stay inside the unit square,
+        // so bisection always converges where newton steps could overshoot
+        let (mut low, mut high) = (0.0_f32, 1.0_f32);
+        let mut s = x;
+        for _ in 0..32 {
+            let error = Self::curve(x1, x2, s) - x;
+            if error.abs() < 1e-6 {
+                break;
+            }
+            if error > 0.0 {
+                high = s;
+            } else {
+                low = s;
+            }
+            s = (low + high) * 0.5;
+        }
+
+        Self::curve(y1, y2, s)
+    }
+}
+
+impl Default for VMDBezier {
+    fn default() -> Self {
+        Self::LINEAR
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VMDBoneInterpolation {
+    pub x: VMDBezier,
+    pub y: VMDBezier,
+    pub z: VMDBezier,
+    pub rotation: VMDBezier,
+}
+
+impl VMDBoneInterpolation {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        // 4 rows of 16 bytes, only the first row is meaningful, the rest are shifted copies
+        // within a row the curves are interleaved as x1[4], y1[4], x2[4], y2[4]
+        let raw = utils::read::<[u8; 64]>(data, cursor)?;
+        let curve = |channel: usize| VMDBezier {
+            x1: raw[channel],
+            y1: raw[channel + 4],
+            x2: raw[channel + 8],
+            y2: raw[channel + 12],
+        };
+
+        Ok(Self {
+            x: curve(0),
+            y: curve(1),
+            z: curve(2),
+            rotation: curve(3),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDBoneKeyframe {
+    pub bone_name: String,
+    pub frame: u32,
+    pub translation: [f32; 3],
+    // quaternion as x, y, z, w
+    pub rotation: [f32; 4],
+    pub interpolation: VMDBoneInterpolation,
+}
+
+impl VMDBoneKeyframe {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            bone_name: utils::read_sjis(data, cursor, 15)?,
+            frame: utils::read::<u32>(data, cursor)?,
+            translation: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation: utils::read::<[f32; 4]>(data, cursor)?,
+            interpolation: VMDBoneInterpolation::parse(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDMorphKeyframe {
+    pub morph_name: String,
+    pub frame: u32,
+    pub weight: f32,
+}
+
+impl VMDMorphKeyframe {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            morph_name: utils::read_sjis(data, cursor, 15)?,
+            frame: utils::read::<u32>(data, cursor)?,
+            weight: utils::read::<f32>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VMDCameraInterpolation {
+    pub x: VMDBezier,
+    pub y: VMDBezier,
+    pub z: VMDBezier,
+    pub rotation: VMDBezier,
+    pub distance: VMDBezier,
+    pub fov: VMDBezier,
+}
+
+impl VMDCameraInterpolation {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        // 6 curves of 4 bytes, each stored as x1, x2, y1, y2
+        let raw = utils::read::<[u8; 24]>(data, cursor)?;
+        let curve = |index: usize| VMDBezier {
+            x1: raw[index * 4],
+            x2: raw[index * 4 + 1],
+            y1: raw[index * 4 + 2],
+            y2: raw[index * 4 + 3],
+        };
+
+        Ok(Self {
+            x: curve(0),
+            y: curve(1),
+            z: curve(2),
+            rotation: curve(3),
+            distance: curve(4),
+            fov: curve(5),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDCameraKeyframe {
+    pub frame: u32,
+    // signed distance from the target, negative values are in front of it
+    pub distance: f32,
+    // the point the camera orbits around
+    pub target: [f32; 3],
+    // euler angles in radians
+    pub rotation: [f32; 3],
+    pub interpolation: VMDCameraInterpolation,
+    // vertical field of view in degrees
+    pub fov: u32,
+    pub perspective: bool,
+}
+
+impl VMDCameraKeyframe {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            frame: utils::read::<u32>(data, cursor)?,
+            distance: utils::read::<f32>(data, cursor)?,
+            target: utils::read::<[f32; 3]>(data, cursor)?,
+            rotation: utils::read::<[f32; 3]>(data, cursor)?,
+            interpolation: VMDCameraInterpolation::parse(data, cursor)?,
+            fov: utils::read::<u32>(data, cursor)?,
+            // stored inverted, 0 means perspective is on
+            perspective: utils::read::<u8>(data, cursor)? == 0,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDLightKeyframe {
+    pub frame: u32,
+    pub color: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+impl VMDLightKeyframe {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            frame: utils::read::<u32>(data, cursor)?,
+            color: utils::read::<[f32; 3]>(data, cursor)?,
+            direction: utils::read::<[f32; 3]>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VMDSelfShadowMode {
+    Off,
+    Mode1,
+    Mode2,
+}
+
+impl VMDSelfShadowMode {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let offset = *cursor;
+        let ty = utils::read::<u8>(data, cursor)?;
+        Ok(match ty {
+            0 => Self::Off,
+            1 => Self::Mode1,
+            2 => Self::Mode2,
+            _ => return Err(ReadError::invalid_value("self shadow mode", ty as i64, offset))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDSelfShadowKeyframe {
+    pub frame: u32,
+    pub mode: VMDSelfShadowMode,
+    // stored as 0.1 - distance * 0.00001 of the value shown in MMD
+    pub distance: f32,
+}
+
+impl VMDSelfShadowKeyframe {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        Ok(Self {
+            frame: utils::read::<u32>(data, cursor)?,
+            mode: VMDSelfShadowMode::parse(data, cursor)?,
+            distance: utils::read::<f32>(data, cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDIKEnableData {
+    pub bone_name: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VMDShowIKKeyframe {
+    pub frame: u32,
+    pub show: bool,
+    pub ik_states: Vec<VMDIKEnableData>,
+}
+
+impl VMDShowIKKeyframe {
+    pub fn parse(data: &[u8], cursor: &mut usize) -> ReadResult<Self> {
+        let frame = utils::read::<u32>(data, cursor)?;
+        let show = utils::read::<u8>(data, cursor)? != 0;
+        let ik_count = utils::read::<u32>(data, cursor)?;
+        let mut ik_states = Vec::new();
+        for _ in 0..ik_count {
+            ik_states.push(VMDIKEnableData {
+                bone_name: utils::read_sjis(data, cursor, 20)?,
+                enabled: utils::read::<u8>(data, cursor)? != 0,
+            });
+        }
+
+        Ok(Self {
+            frame,
+            show,
+            ik_states
+        })
+    }
+}
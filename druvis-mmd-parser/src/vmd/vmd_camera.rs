@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use cgmath::{Vector3, Matrix3, Rad, Deg, InnerSpace};
+use druvis_core::camera::{camera::CameraController, perspective_camera::PerspectiveCamera};
+
+use super::{vmd_parser::VMDMotion, structs::VMDCameraKeyframe, vmd_player::{find_keys, advance_frame}};
+
+/// MMD camera state at one frame, the camera orbits `target` at `distance`
+#[derive(Clone, Copy, Debug)]
+pub struct VMDCameraPose {
+    pub target: Vector3<f32>,
+    // euler angles in radians as stored in the motion
+    pub rotation: Vector3<f32>,
+    // negative in front of the target, like MMD's default of -45
+    pub distance: f32,
+    // vertical field of view in degrees
+    pub fov: f32,
+    // PerspectiveCamera has no orthographic mode, so this is only informative
+    pub perspective: bool,
+}
+
+impl VMDCameraPose {
+    // the stored angles are negated, z is applied first and x last
+    fn orientation(&self) -> Matrix3<f32> {
+        Matrix3::from_angle_x(Rad(-self.rotation.x))
+            * Matrix3::from_angle_y(Rad(-self.rotation.y))
+            * Matrix3::from_angle_z(Rad(-self.rotation.z))
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.target + self.orientation() * Vector3::new(0.0, 0.0, self.distance)
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        self.orientation() * Vector3::unit_z()
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.orientation() * Vector3::unit_y()
+    }
+
+    /// Writes the pose as position, yaw, pitch, roll and field of view
+    pub fn apply(&self, camera: &mut PerspectiveCamera) {
+        let forward = self.forward().normalize();
+        let position = self.position();
+        camera.position = (position.x, position.y, position.z).into();
+        camera.yaw = Rad(forward.z.atan2(forward.x));
+        camera.pitch = Rad(forward.y.clamp(-1.0, 1.0).asin());
+
+        // roll relative to the up vector PerspectiveCamera uses without roll
+        let right = forward.cross(Vector3::unit_y());
+        camera.roll = if right.magnitude2() < 1e-10 {
+            Rad(0.0)
+        } else {
+            let right = right.normalize();
+            let up = self.up();
+            Rad(up.dot(right).atan2(up.dot(right.cross(forward))))
+        };
+        camera.fovy = Deg(self.fov).into();
+    }
+}
+
+/// Plays the camera keyframes of a VMD motion, used in place of `SimplePerspectiveCameraController`
+pub struct VMDCameraController {
+    keys: Vec<VMDCameraKeyframe>,
+    pub last_frame: u32,
+
+    // current position in frames
+    pub frame: f32,
+    pub playing: bool,
+    pub looping: bool,
+}
+
+impl VMDCameraController {
+    pub fn new(motion: &VMDMotion) -> Self {
+        let mut keys = motion.camera_keyframes.clone();
+        keys.sort_by_key(|k| k.frame);
+
+        Self {
+            last_frame: keys.last().map(|k| k.frame).unwrap_or(0),
+            keys,
+            frame: 0.0,
+            playing: true,
+            looping: false,
+        }
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Jumps to `frame`, clamped to the motion
+    pub fn seek(&mut self, frame: f32) {
+        self.frame = frame.clamp(0.0, self.last_frame as f32);
+    }
+
+    /// None if the motion has no camera keyframes
+    pub fn sample(&self, frame: f32) -> Option<VMDCameraPose> {
+        if self.keys.is_empty() {
+            return None;
+        }
+
+        let (a, b, t) = find_keys(&self.keys, frame, |k| k.frame);
+        // keyframes on adjacent frames are a cut, the camera jumps instead of moving
+        let t = if b.frame - a.frame <= 1 { 0.0 } else { t };
+        let curves = &b.interpolation;
+        let lerp = |x: f32, y: f32, s: f32| x + (y - x) * s;
+
+        let rotation = curves.rotation.evaluate(t);
+        Some(VMDCameraPose {
+            target: Vector3::new(
+                lerp(a.target[0], b.target[0], curves.x.evaluate(t)),
+                lerp(a.target[1], b.target[1], curves.y.evaluate(t)),
+                lerp(a.target[2], b.target[2], curves.z.evaluate(t)),
+            ),
+            rotation: Vector3::new(
+                lerp(a.rotation[0], b.rotation[0], rotation),
+                lerp(a.rotation[1], b.rotation[1], rotation),
+                lerp(a.rotation[2], b.rotation[2], rotation),
+            ),
+            distance: lerp(a.distance, b.distance, curves.distance.evaluate(t)),
+            fov: lerp(a.fov as f32, b.fov as f32, curves.fov.evaluate(t)),
+            perspective: a.perspective,
+        })
+    }
+}
+
+impl CameraController<PerspectiveCamera> for VMDCameraController {
+    fn update_camera(&mut self, camera: &mut PerspectiveCamera, delta_time: Duration) {
+        if self.playing {
+            (self.frame, self.playing) = advance_frame(self.frame, self.last_frame, self.looping, delta_time);
+        }
+        if let Some(pose) = self.sample(self.frame) {
+            pose.apply(camera);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use crate::utils::{ReadError, ReadErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VmdSection {
+    Header,
+    BoneKeyframes,
+    MorphKeyframes,
+    CameraKeyframes,
+    LightKeyframes,
+    SelfShadowKeyframes,
+    ShowIKKeyframes,
+}
+
+impl fmt::Display for VmdSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            Self::Header => "header",
+            Self::BoneKeyframes => "bone keyframes",
+            Self::MorphKeyframes => "morph keyframes",
+            Self::CameraKeyframes => "camera keyframes",
+            Self::LightKeyframes => "light keyframes",
+            Self::SelfShadowKeyframes => "self shadow keyframes",
+            Self::ShowIKKeyframes => "show/IK keyframes",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmdErrorKind {
+    Read(ReadErrorKind),
+    InvalidSignature(String),
+}
+
+impl fmt::Display for VmdErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(kind) => write!(f, "{}", kind),
+            Self::InvalidSignature(signature) => write!(f, "invalid VMD signature {:?}", signature),
+        }
+    }
+}
+
+/// A VMD parse failure, `index` is the keyframe within `section` that failed, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmdError {
+    pub kind: VmdErrorKind,
+    pub offset: usize,
+    pub section: VmdSection,
+    pub index: Option<usize>,
+}
+
+pub type VmdResult<T> = Result<T, VmdError>;
+
+impl VmdError {
+    pub fn new(kind: VmdErrorKind, offset: usize, section: VmdSection, index: Option<usize>) -> Self {
+        Self {
+            kind,
+            offset,
+            section,
+            index
+        }
+    }
+
+    pub fn from_read(e: ReadError, section: VmdSection, index: Option<usize>) -> Self {
+        Self::new(VmdErrorKind::Read(e.kind), e.offset, section, index)
+    }
+}
+
+impl fmt::Display for VmdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "{} at byte {} (section {}, keyframe {})", self.kind, self.offset, self.section, index),
+            None => write!(f, "{} at byte {} (section {})", self.kind, self.offset, self.section),
+        }
+    }
+}
+
+impl std::error::Error for VmdError {}
+
+/// Failure when loading a VMD motion from a file or reader
+#[derive(Debug)]
+pub enum VmdLoadError {
+    Io(std::io::Error),
+    Parse(VmdError),
+}
+
+pub type VmdLoadResult<T> = Result<T, VmdLoadError>;
+
+impl fmt::Display for VmdLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read VMD data: {}", e),
+            Self::Parse(e) => write!(f, "failed to parse VMD data: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VmdLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for VmdLoadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<VmdError> for VmdLoadError {
+    fn from(e: VmdError) -> Self {
+        Self::Parse(e)
+    }
+}
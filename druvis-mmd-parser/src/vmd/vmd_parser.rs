@@ -0,0 +1,115 @@
+use std::{path::Path, io::Read, fs::File};
+
+use crate::utils::{self, ReadResult};
+
+use super::{structs::*, vmd_error::{VmdResult, VmdError, VmdErrorKind, VmdSection, VmdLoadResult}};
+
+#[derive(Clone, Debug)]
+pub struct VMDMotion {
+    pub header: VMDHeader,
+    pub bone_keyframes: Vec<VMDBoneKeyframe>,
+    pub morph_keyframes: Vec<VMDMorphKeyframe>,
+    pub camera_keyframes: Vec<VMDCameraKeyframe>,
+    pub light_keyframes: Vec<VMDLightKeyframe>,
+    pub self_shadow_keyframes: Vec<VMDSelfShadowKeyframe>,
+    pub show_ik_keyframes: Vec<VMDShowIKKeyframe>,
+}
+
+impl VMDMotion {
+    /// The last frame that has a keyframe of any kind
+    pub fn last_frame(&self) -> u32 {
+        let frames = self.bone_keyframes.iter().map(|k| k.frame)
+            .chain(self.morph_keyframes.iter().map(|k| k.frame))
+            .chain(self.camera_keyframes.iter().map(|k| k.frame))
+            .chain(self.light_keyframes.iter().map(|k| k.frame))
+            .chain(self.self_shadow_keyframes.iter().map(|k| k.frame))
+            .chain(self.show_ik_keyframes.iter().map(|k| k.frame));
+        frames.max().unwrap_or(0)
+    }
+}
+
+#[derive(Default)]
+pub struct VmdParser {
+
+}
+
+impl VmdParser {
+    pub fn new() -> Self {
+        VmdParser {  }
+    }
+}
+
+impl VmdParser {
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> VmdLoadResult<VMDMotion> {
+        let file = File::open(path)?;
+        self.parse_reader(file)
+    }
+
+    pub fn parse_reader<R: Read>(&self, mut reader: R) -> VmdLoadResult<VMDMotion> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let result = self.parse(&data)?;
+        Ok(result)
+    }
+
+    fn parse_header(&self, data: &[u8], cursor: &mut usize) -> VmdResult<VMDHeader> {
+        let section_error = |e| VmdError::from_read(e, VmdSection::Header, None);
+
+        let signature = utils::read_sjis(data, cursor, 30).map_err(section_error)?;
+        let version = if signature == VMD_SIGNATURE_V2 {
+            VMDVersion::V2
+        } else if signature == VMD_SIGNATURE_V1 {
+            VMDVersion::V1
+        } else {
+            return Err(VmdError::new(VmdErrorKind::InvalidSignature(signature), 0, VmdSection::Header, None));
+        };
+        let model_name = utils::read_sjis(data, cursor, version.model_name_size()).map_err(section_error)?;
+
+        Ok(VMDHeader {
+            version,
+            model_name
+        })
+    }
+
+    // every section is optional, files written by older tools simply end early
+    fn parse_section<T>(
+        data: &[u8],
+        cursor: &mut usize,
+        section: VmdSection,
+        parse: fn(&[u8], &mut usize) -> ReadResult<T>
+    ) -> VmdResult<Vec<T>> {
+        let mut result = Vec::new();
+        if *cursor >= data.len() {
+            return Ok(result);
+        }
+
+        let count = utils::read::<u32>(data, cursor).map_err(|e| VmdError::from_read(e, section, None))?;
+        for i in 0..count as usize {
+            result.push(parse(data, cursor).map_err(|e| VmdError::from_read(e, section, Some(i)))?);
+        }
+        Ok(result)
+    }
+
+    pub fn parse(&self, data: &[u8]) -> VmdResult<VMDMotion> {
+        let mut cursor: usize = 0;
+
+        let header = self.parse_header(data, &mut cursor)?;
+
+        let bone_keyframes = Self::parse_section(data, &mut cursor, VmdSection::BoneKeyframes, VMDBoneKeyframe::parse)?;
+        let morph_keyframes = Self::parse_section(data, &mut cursor, VmdSection::MorphKeyframes, VMDMorphKeyframe::parse)?;
+        let camera_keyframes = Self::parse_section(data, &mut cursor, VmdSection::CameraKeyframes, VMDCameraKeyframe::parse)?;
+        let light_keyframes = Self::parse_section(data, &mut cursor, VmdSection::LightKeyframes, VMDLightKeyframe::parse)?;
+        let self_shadow_keyframes = Self::parse_section(data, &mut cursor, VmdSection::SelfShadowKeyframes, VMDSelfShadowKeyframe::parse)?;
+        let show_ik_keyframes = Self::parse_section(data, &mut cursor, VmdSection::ShowIKKeyframes, VMDShowIKKeyframe::parse)?;
+
+        Ok(VMDMotion {
+            header,
+            bone_keyframes,
+            morph_keyframes,
+            camera_keyframes,
+            light_keyframes,
+            self_shadow_keyframes,
+            show_ik_keyframes,
+        })
+    }
+}
@@ -0,0 +1,299 @@
+use std::{rc::Rc, cell::RefCell, time::Duration, collections::HashMap};
+
+use cgmath::{Vector3, Quaternion, InnerSpace};
+use druvis_core::game_object::{DruvisGameObject, components::{SkeletonData, MorphControllerData, Animation}};
+
+use super::{vmd_parser::VMDMotion, structs::{VMDBoneInterpolation, VMDBoneKeyframe}};
+
+/// Keyframes are numbered in frames of 1/30 second
+pub const VMD_FRAME_RATE: f32 = 30.0;
+
+#[derive(Clone, Copy, Debug)]
+struct BoneKey {
+    frame: u32,
+    translation: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    // curves towards this keyframe from the previous one
+    interpolation: VMDBoneInterpolation,
+}
+
+impl From<&VMDBoneKeyframe> for BoneKey {
+    fn from(key: &VMDBoneKeyframe) -> Self {
+        let [x, y, z, w] = key.rotation;
+        Self {
+            frame: key.frame,
+            translation: key.translation.into(),
+            rotation: Quaternion::new(w, x, y, z),
+            interpolation: key.interpolation,
+        }
+    }
+}
+
+// a track per model bone, morph or IK bone, keyframes sorted by frame
+struct Track<K> {
+    index: usize,
+    keys: Vec<K>,
+}
+
+// the keyframes around `frame` and the linear progress between them,
+// clamps to the first and last keyframe outside the track
+pub(crate) fn find_keys<K, F: Fn(&K) -> u32>(keys: &[K], frame: f32, key_frame: F) -> (&K, &K, f32) {
+    let next = keys.partition_point(|k| key_frame(k) as f32 <= frame);
+    if next == 0 {
+        return (&keys[0], &keys[0], 0.0);
+    }
+    if next == keys.len() {
+        let last = &keys[keys.len() - 1];
+        return (last, last, 0.0);
+    }
+
+    let (a, b) = (&keys[next - 1], &keys[next]);
+    let (fa, fb) = (key_frame(a) as f32, key_frame(b) as f32);
+    (a, b, (frame - fa) / (fb - fa))
+}
+
+// moves `frame` on by `dt`, returns the new frame and whether playback goes on
+pub(crate) fn advance_frame(frame: f32, last_frame: u32, looping: bool, dt: Duration) -> (f32, bool) {
+    let frame = frame + dt.as_secs_f32() * VMD_FRAME_RATE;
+    let last = last_frame as f32;
+    if frame <= last {
+        (frame, true)
+    } else if looping && last > 0.0 {
+        (frame % last, true)
+    } else {
+        (last, false)
+    }
+}
+
+fn build_tracks<K, I>(keys: I, index_of: impl Fn(&str) -> Option<usize>) -> (Vec<Track<K>>, usize)
+where
+    I: Iterator<Item = (String, u32, K)>,
+{
+    let mut tracks: Vec<Track<(u32, K)>> = Vec::new();
+    let mut track_of_index = HashMap::new();
+    let mut missing = 0;
+    for (name, frame, key) in keys {
+        let index = match index_of(&name) {
+            Some(index) => index,
+            None => {
+                missing += 1;
+                continue;
+            },
+        };
+        let track = *track_of_index.entry(index).or_insert_with(|| {
+            tracks.push(Track { index, keys: Vec::new() });
+            tracks.len() - 1
+        });
+        tracks[track].keys.push((frame, key));
+    }
+
+    let tracks = tracks.into_iter().map(|mut track| {
+        // stable, a duplicated frame keeps the key written last
+        track.keys.sort_by_key(|&(frame, _)| frame);
+        track.keys.dedup_by(|b, a| {
+            if a.0 == b.0 {
+                std::mem::swap(a, b);
+                true
+            } else {
+                false
+            }
+        });
+        Track {
+            index: track.index,
+            keys: track.keys.into_iter().map(|(_, key)| key).collect(),
+        }
+    }).collect();
+    (tracks, missing)
+}
+
+/// Local transform of one bone, relative to its bind pose like `Bone::translation` and `Bone::rotation`
+#[derive(Clone, Copy, Debug)]
+pub struct VMDBonePose {
+    pub bone: usize,
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+}
+
+/// A motion sampled at one frame, indexed by the bones and morphs of the model it was bound to
+#[derive(Clone, Debug, Default)]
+pub struct VMDPose {
+    pub bones: Vec<VMDBonePose>,
+    pub morphs: Vec<(usize, f32)>,
+    pub ik_enabled: Vec<(usize, bool)>,
+}
+
+/// Plays a VMD motion on a model, keyframes are matched to bones and morphs by name
+pub struct VMDPlayer {
+    bone_tracks: Vec<Track<BoneKey>>,
+    morph_tracks: Vec<Track<(u32, f32)>>,
+    ik_tracks: Vec<Track<(u32, bool)>>,
+    pub last_frame: u32,
+    // keyframes dropped because the model has no bone or morph of that name
+    pub missing_bone_keyframes: usize,
+    pub missing_morph_keyframes: usize,
+
+    // current position in frames
+    pub frame: f32,
+    pub playing: bool,
+    pub looping: bool,
+    // set by seeking and wrapping around, taken by `Animation::advance`
+    jumped: bool,
+}
+
+impl VMDPlayer {
+    pub fn new(motion: &VMDMotion, skeleton: &SkeletonData, morphs: &MorphControllerData) -> Self {
+        let (bone_tracks, missing_bones) = build_tracks(
+            motion.bone_keyframes.iter().map(|k| (k.bone_name.clone(), k.frame, BoneKey::from(k))),
+            |name| skeleton.find_bone(name)
+        );
+        let (morph_tracks, missing_morphs) = build_tracks(
+            motion.morph_keyframes.iter().map(|k| (k.morph_name.clone(), k.frame, (k.frame, k.weight))),
+            |name| morphs.find_morph(name)
+        );
+        let (ik_tracks, _) = build_tracks(
+            motion.show_ik_keyframes.iter()
+                .flat_map(|k| k.ik_states.iter().map(move |s| (s.bone_name.clone(), k.frame, (k.frame, s.enabled)))),
+            |name| skeleton.find_bone(name).filter(|&i| skeleton.bones[i].ik.is_some())
+        );
+
+        Self {
+            bone_tracks,
+            morph_tracks,
+            ik_tracks,
+            last_frame: motion.last_frame(),
+            missing_bone_keyframes: missing_bones,
+            missing_morph_keyframes: missing_morphs,
+            frame: 0.0,
+            playing: false,
+            looping: false,
+            jumped: false,
+        }
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Jumps to `frame`, clamped to the motion
+    pub fn seek(&mut self, frame: f32) {
+        self.frame = frame.clamp(0.0, self.last_frame as f32);
+        self.jumped = true;
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f32(self.last_frame as f32 / VMD_FRAME_RATE)
+    }
+
+    /// Advances a playing motion, wraps around when looping and stops at the end otherwise
+    pub fn update(&mut self, dt: Duration) {
+        if !self.playing {
+            return;
+        }
+
+        let previous = self.frame;
+        (self.frame, self.playing) = advance_frame(self.frame, self.last_frame, self.looping, dt);
+        if self.frame < previous {
+            self.jumped = true;
+        }
+    }
+
+    fn sample_bone(keys: &[BoneKey], frame: f32) -> (Vector3<f32>, Quaternion<f32>) {
+        let (a, b, t) = find_keys(keys, frame, |k| k.frame);
+        let curves = &b.interpolation;
+        let translation = Vector3::new(
+            a.translation.x + (b.translation.x - a.translation.x) * curves.x.evaluate(t),
+            a.translation.y + (b.translation.y - a.translation.y) * curves.y.evaluate(t),
+            a.translation.z + (b.translation.z - a.translation.z) * curves.z.evaluate(t),
+        );
+        let rotation = a.rotation.normalize().slerp(b.rotation.normalize(), curves.rotation.evaluate(t));
+        (translation, rotation)
+    }
+
+    /// Samples every track at `frame`, which does not have to be a whole frame
+    pub fn sample(&self, frame: f32) -> VMDPose {
+        let bones = self.bone_tracks.iter().map(|track| {
+            let (translation, rotation) = Self::sample_bone(&track.keys, frame);
+            VMDBonePose {
+                bone: track.index,
+                translation,
+                rotation,
+            }
+        }).collect();
+
+        let morphs = self.morph_tracks.iter().map(|track| {
+            let (a, b, t) = find_keys(&track.keys, frame, |k| k.0);
+            (track.index, a.1 + (b.1 - a.1) * t)
+        }).collect();
+
+        // IK switches are steps, the state holds until the next keyframe
+        let ik_enabled = self.ik_tracks.iter().map(|track| {
+            let (a, _, _) = find_keys(&track.keys, frame, |k| k.0);
+            let enabled = if a.0 as f32 <= frame { a.1 } else { true };
+            (track.index, enabled)
+        }).collect();
+
+        VMDPose {
+            bones,
+            morphs,
+            ik_enabled,
+        }
+    }
+
+    /// Poses the skeleton and sets the morph weights at the current frame,
+    /// bones and morphs the motion does not key go back to the bind pose.
+    /// This overrides simulated bones, models with physics play the motion through an `AnimatorData`
+    pub fn apply(&self, skeleton: &mut SkeletonData, morphs: &mut MorphControllerData) {
+        let pose = self.sample(self.frame);
+        Self::pose_skeleton(&pose, skeleton);
+        skeleton.update_world_matrices();
+        Self::pose_morphs(&pose, morphs);
+    }
+
+    fn pose_skeleton(pose: &VMDPose, skeleton: &mut SkeletonData) {
+        skeleton.reset_pose();
+        for bone in pose.bones.iter() {
+            skeleton.set_bone_transform(bone.bone, bone.translation, bone.rotation);
+        }
+        for bone in skeleton.bones.iter_mut() {
+            bone.ik_enabled = true;
+        }
+        for &(bone, enabled) in pose.ik_enabled.iter() {
+            skeleton.bones[bone].ik_enabled = enabled;
+        }
+    }
+
+    fn pose_morphs(pose: &VMDPose, morphs: &mut MorphControllerData) {
+        morphs.reset();
+        for &(morph, weight) in pose.morphs.iter() {
+            morphs.weights[morph] = weight;
+        }
+    }
+
+    /// Same as `apply` on the skeleton and morph controller of a PMX game object
+    pub fn apply_to_game_object(&self, go: Rc<RefCell<DruvisGameObject>>) {
+        let skeleton = DruvisGameObject::get_component::<SkeletonData>(go.clone());
+        let morphs = DruvisGameObject::get_component::<MorphControllerData>(go);
+        if let (Some(skeleton), Some(morphs)) = (skeleton, morphs) {
+            self.apply(&mut skeleton.borrow_mut().data, &mut morphs.borrow_mut().data);
+        }
+    }
+}
+
+impl Animation for VMDPlayer {
+    fn advance(&mut self, dt: Duration) -> bool {
+        self.update(dt);
+        std::mem::take(&mut self.jumped)
+    }
+
+    fn pose(&self, skeleton: &mut SkeletonData, morphs: Option<&mut MorphControllerData>) {
+        let pose = self.sample(self.frame);
+        Self::pose_skeleton(&pose, skeleton);
+        if let Some(morphs) = morphs {
+            Self::pose_morphs(&pose, morphs);
+        }
+    }
+}
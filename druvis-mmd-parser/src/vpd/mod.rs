@@ -0,0 +1,3 @@
+pub mod vpd_parser;
+pub mod vpd_error;
+pub mod structs;
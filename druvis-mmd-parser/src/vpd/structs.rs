@@ -0,0 +1,115 @@
+use std::{io::{self, Write}, path::Path, fs::File};
+
+use crate::pmx::pmx_parser::PMXFormat;
+
+pub const VPD_SIGNATURE: &str = "Vocaloid Pose Data file";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VPDBonePose {
+    pub bone_name: String,
+    pub translation: [f32; 3],
+    // quaternion as x, y, z, w
+    pub rotation: [f32; 4],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VPDMorphPose {
+    pub morph_name: String,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VPDPose {
+    // the model file the pose was made for, e.g. "miku.osm"
+    pub model_file_name: String,
+    pub bones: Vec<VPDBonePose>,
+    pub morphs: Vec<VPDMorphPose>,
+}
+
+const IDENTITY_ROTATION: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+impl VPDPose {
+    /// Builds a pose from per bone (translation, rotation) and per morph weights of `model`,
+    /// bones at rest and morphs at zero are left out like MMD does
+    pub fn from_model(model: &PMXFormat, bone_transforms: &[([f32; 3], [f32; 4])], morph_weights: &[f32]) -> Self {
+        let bones = model.bones.iter().zip(bone_transforms.iter())
+            .filter(|(_, (t, r))| *t != [0.0; 3] || *r != IDENTITY_ROTATION)
+            .map(|(bone, (t, r))| VPDBonePose {
+                bone_name: bone.bone_name_local.clone(),
+                translation: *t,
+                rotation: *r,
+            })
+            .collect();
+        let morphs = model.morphs.iter().zip(morph_weights.iter())
+            .filter(|(_, w)| **w != 0.0)
+            .map(|(morph, w)| VPDMorphPose {
+                morph_name: morph.morph_name_local.clone(),
+                weight: *w,
+            })
+            .collect();
+
+        Self {
+            model_file_name: format!("{}.osm", model.header.model_name_local),
+            bones,
+            morphs,
+        }
+    }
+
+    /// Per bone pose of `model`, matched by the local bone name
+    pub fn bone_poses(&self, model: &PMXFormat) -> Vec<Option<&VPDBonePose>> {
+        model.bones.iter()
+            .map(|bone| self.bones.iter().find(|p| p.bone_name == bone.bone_name_local))
+            .collect()
+    }
+
+    /// Per morph weight of `model`, morphs missing from the pose are zero
+    pub fn morph_weights(&self, model: &PMXFormat) -> Vec<f32> {
+        model.morphs.iter()
+            .map(|morph| self.morphs.iter()
+                .find(|p| p.morph_name == morph.morph_name_local)
+                .map(|p| p.weight)
+                .unwrap_or(0.0))
+            .collect()
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str(VPD_SIGNATURE);
+        text.push_str("\r\n\r\n");
+        text.push_str(&format!("{};\t\t// 親ファイル名\r\n", self.model_file_name));
+        text.push_str(&format!("{};\t\t\t\t// 総ポーズボーン数\r\n\r\n", self.bones.len()));
+
+        for (i, bone) in self.bones.iter().enumerate() {
+            let [tx, ty, tz] = bone.translation;
+            let [rx, ry, rz, rw] = bone.rotation;
+            text.push_str(&format!("Bone{}{{{}\r\n", i, bone.bone_name));
+            text.push_str(&format!("  {:.6},{:.6},{:.6};\t\t\t\t// trans x,y,z\r\n", tx, ty, tz));
+            text.push_str(&format!("  {:.6},{:.6},{:.6},{:.6};\t\t// Quaternion x,y,z,w\r\n", rx, ry, rz, rw));
+            text.push_str("}\r\n\r\n");
+        }
+
+        for (i, morph) in self.morphs.iter().enumerate() {
+            text.push_str(&format!("Morph{}{{{}\r\n", i, morph.morph_name));
+            text.push_str(&format!("  {:.6};\t\t\t\t// weight\r\n", morph.weight));
+            text.push_str("}\r\n\r\n");
+        }
+
+        text
+    }
+
+    /// Writes the pose as Shift-JIS text, fails if a name can not be represented in Shift-JIS
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let text = self.to_text();
+        let (bytes, _, had_errors) = encoding_rs::SHIFT_JIS.encode(&text);
+        if had_errors {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "pose contains characters that can not be encoded as Shift-JIS"));
+        }
+        writer.write_all(&bytes)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VpdErrorKind {
+    InvalidSignature(String),
+    UnexpectedEnd,
+    InvalidNumber(String),
+    InvalidBlock(String),
+}
+
+impl fmt::Display for VpdErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSignature(signature) => write!(f, "invalid VPD signature {:?}", signature),
+            Self::UnexpectedEnd => write!(f, "unexpected end of data"),
+            Self::InvalidNumber(text) => write!(f, "invalid number {:?}", text),
+            Self::InvalidBlock(text) => write!(f, "invalid block {:?}", text),
+        }
+    }
+}
+
+/// A VPD parse failure, `line` is 1 based
+#[derive(Debug, Clone, PartialEq)]
+pub struct VpdError {
+    pub kind: VpdErrorKind,
+    pub line: usize,
+}
+
+pub type VpdResult<T> = Result<T, VpdError>;
+
+impl VpdError {
+    pub fn new(kind: VpdErrorKind, line: usize) -> Self {
+        Self {
+            kind,
+            line
+        }
+    }
+}
+
+impl fmt::Display for VpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.kind, self.line)
+    }
+}
+
+impl std::error::Error for VpdError {}
+
+/// Failure when loading a VPD pose from a file or reader
+#[derive(Debug)]
+pub enum VpdLoadError {
+    Io(std::io::Error),
+    Parse(VpdError),
+}
+
+pub type VpdLoadResult<T> = Result<T, VpdLoadError>;
+
+impl fmt::Display for VpdLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read VPD data: {}", e),
+            Self::Parse(e) => write!(f, "failed to parse VPD data: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VpdLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for VpdLoadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<VpdError> for VpdLoadError {
+    fn from(e: VpdError) -> Self {
+        Self::Parse(e)
+    }
+}
@@ -0,0 +1,142 @@
+use std::{path::Path, io::Read, fs::File};
+
+use super::{structs::*, vpd_error::{VpdResult, VpdError, VpdErrorKind, VpdLoadResult}};
+
+// walks the comment stripped text, keeping track of the position for error lines
+struct VpdTextReader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> VpdTextReader<'a> {
+    fn line(&self) -> usize {
+        self.text[..self.position].matches('\n').count() + 1
+    }
+
+    fn is_end(&mut self) -> bool {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+        self.position >= self.text.len()
+    }
+
+    fn read_until(&mut self, delimiter: char) -> VpdResult<&'a str> {
+        let rest = &self.text[self.position..];
+        match rest.find(delimiter) {
+            Some(index) => {
+                self.position += index + delimiter.len_utf8();
+                Ok(&rest[..index])
+            },
+            None => Err(VpdError::new(VpdErrorKind::UnexpectedEnd, self.line())),
+        }
+    }
+}
+
+fn parse_floats<const N: usize>(text: &str, line: usize) -> VpdResult<[f32; N]> {
+    let invalid = || VpdError::new(VpdErrorKind::InvalidNumber(text.to_string()), line);
+
+    let mut result = [0.0; N];
+    let mut values = text.split(',');
+    for value in result.iter_mut() {
+        *value = values.next()
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .ok_or_else(invalid)?;
+    }
+    if values.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(result)
+}
+
+#[derive(Default)]
+pub struct VpdParser {
+
+}
+
+impl VpdParser {
+    pub fn new() -> Self {
+        VpdParser {  }
+    }
+}
+
+impl VpdParser {
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> VpdLoadResult<VPDPose> {
+        let file = File::open(path)?;
+        self.parse_reader(file)
+    }
+
+    pub fn parse_reader<R: Read>(&self, mut reader: R) -> VpdLoadResult<VPDPose> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let result = self.parse(&data)?;
+        Ok(result)
+    }
+
+    /// Parses Shift-JIS encoded VPD data
+    pub fn parse(&self, data: &[u8]) -> VpdResult<VPDPose> {
+        let (text, _, _) = encoding_rs::SHIFT_JIS.decode(data);
+        self.parse_text(&text)
+    }
+
+    /// Parses already decoded VPD text
+    pub fn parse_text(&self, text: &str) -> VpdResult<VPDPose> {
+        // drop comments but keep the line structure for error reporting
+        let stripped = text.lines()
+            .map(|line| line.split("//").next().unwrap_or(""))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut reader = VpdTextReader {
+            text: &stripped,
+            position: 0,
+        };
+
+        let signature = match stripped.lines().next() {
+            Some(line) => line.trim(),
+            None => return Err(VpdError::new(VpdErrorKind::UnexpectedEnd, 1)),
+        };
+        if signature != VPD_SIGNATURE {
+            return Err(VpdError::new(VpdErrorKind::InvalidSignature(signature.to_string()), 1));
+        }
+        reader.read_until('\n')?;
+
+        let model_file_name = reader.read_until(';')?.trim().to_string();
+        let line = reader.line();
+        let bone_count = reader.read_until(';')?.trim();
+        bone_count.parse::<usize>()
+            .map_err(|_| VpdError::new(VpdErrorKind::InvalidNumber(bone_count.to_string()), line))?;
+
+        let mut bones = Vec::new();
+        let mut morphs = Vec::new();
+        while !reader.is_end() {
+            let line = reader.line();
+            let kind = reader.read_until('{')?.trim();
+            let name = reader.read_until('\n')?.trim().to_string();
+            // an unclosed block is reported where it starts
+            let body = reader.read_until('}').map_err(|e| VpdError::new(e.kind, line))?;
+            let statements = body.split(';')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>();
+
+            if kind.starts_with("Bone") && statements.len() == 2 {
+                bones.push(VPDBonePose {
+                    bone_name: name,
+                    translation: parse_floats::<3>(statements[0], line)?,
+                    rotation: parse_floats::<4>(statements[1], line)?,
+                });
+            } else if kind.starts_with("Morph") && statements.len() == 1 {
+                morphs.push(VPDMorphPose {
+                    morph_name: name,
+                    weight: parse_floats::<1>(statements[0], line)?[0],
+                });
+            } else {
+                return Err(VpdError::new(VpdErrorKind::InvalidBlock(kind.to_string()), line));
+            }
+        }
+
+        Ok(VPDPose {
+            model_file_name,
+            bones,
+            morphs,
+        })
+    }
+}
@@ -0,0 +1,239 @@
+use std::{f32::consts::PI, path::PathBuf};
+
+use druvis_mmd_parser::{PmdParser, utils, pmx::{pmx_parser::PMXFormat, pmx_error::PmxSection, structs::*}};
+
+// fixed size Shift-JIS field, zero padded like MMD writes them
+fn sjis(buffer: &mut Vec<u8>, text: &str, size: usize) {
+    let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(text);
+    let mut field = bytes.into_owned();
+    assert!(field.len() <= size);
+    field.resize(size, 0);
+    buffer.extend_from_slice(&field);
+}
+
+fn vertex(buffer: &mut Vec<u8>, position: [f32; 3], bones: [u16; 2], weight: u8, edge_flag: u8) {
+    utils::write(buffer, position);
+    utils::write(buffer, [0.0f32, 1.0, 0.0]);
+    utils::write(buffer, [0.0f32, 0.0]);
+    utils::write(buffer, bones);
+    utils::write(buffer, weight);
+    utils::write(buffer, edge_flag);
+}
+
+fn bone(buffer: &mut Vec<u8>, name: &str, parent: u16, tail: u16, bone_type: u8, ik_parent: u16, position: [f32; 3]) {
+    sjis(buffer, name, 20);
+    utils::write(buffer, parent);
+    utils::write(buffer, tail);
+    utils::write(buffer, bone_type);
+    utils::write(buffer, ik_parent);
+    utils::write(buffer, position);
+}
+
+fn skin(buffer: &mut Vec<u8>, name: &str, skin_type: u8, vertices: &[(u32, [f32; 3])]) {
+    sjis(buffer, name, 20);
+    utils::write(buffer, vertices.len() as u32);
+    utils::write(buffer, skin_type);
+    for &(index, position) in vertices {
+        utils::write(buffer, index);
+        utils::write(buffer, position);
+    }
+}
+
+// a leg with a knee, an IK bone, one mouth morph and one rigid body,
+// also returns the length of the sections every PMD has
+fn model() -> (Vec<u8>, usize) {
+    let mut data = Vec::new();
+    data.extend_from_slice(b"Pmd");
+    utils::write(&mut data, 1.0f32);
+    sjis(&mut data, "テスト", 20);
+    sjis(&mut data, "", 256);
+
+    utils::write(&mut data, 3u32);
+    vertex(&mut data, [0.0, 0.0, 0.0], [0, 1], 30, 0);
+    vertex(&mut data, [0.0, 1.0, 0.0], [1, 1], 100, 0);
+    vertex(&mut data, [1.0, 0.0, 0.0], [0, 1], 0, 1);
+
+    utils::write(&mut data, 3u32);
+    utils::write(&mut data, [0u16, 1, 2]);
+
+    utils::write(&mut data, 1u32);
+    utils::write(&mut data, [1.0f32, 1.0, 1.0, 1.0]);
+    utils::write(&mut data, 5.0f32);
+    utils::write(&mut data, [0.5f32, 0.5, 0.5]);
+    utils::write(&mut data, [0.2f32, 0.2, 0.2]);
+    utils::write(&mut data, 2u8);
+    utils::write(&mut data, 1u8);
+    utils::write(&mut data, 3u32);
+    sjis(&mut data, "body.png*glow.spa", 20);
+
+    utils::write(&mut data, 3u16);
+    bone(&mut data, "センター", 0xffff, 1, 1, 0, [0.0, 1.0, 0.0]);
+    bone(&mut data, "左ひざ", 0, 0, 4, 2, [0.0, 5.0, 0.0]);
+    bone(&mut data, "左足ＩＫ", 0, 0, 2, 0, [0.0, 0.0, 1.0]);
+
+    utils::write(&mut data, 1u16);
+    utils::write(&mut data, 2u16);
+    utils::write(&mut data, 1u16);
+    utils::write(&mut data, 1u8);
+    utils::write(&mut data, 40u16);
+    utils::write(&mut data, 0.5f32);
+    utils::write(&mut data, 1u16);
+
+    utils::write(&mut data, 2u16);
+    skin(&mut data, "base", 0, &[(0, [0.0, 0.0, 0.0]), (2, [1.0, 0.0, 0.0])]);
+    skin(&mut data, "あ", 3, &[(1, [0.0, 0.5, 0.0])]);
+
+    utils::write(&mut data, 1u8);
+    utils::write(&mut data, 1u16);
+    utils::write(&mut data, 0u8);
+    utils::write(&mut data, 0u32);
+    let required = data.len();
+
+    // no english names
+    utils::write(&mut data, 0u8);
+    for i in 1..=10 {
+        sjis(&mut data, &format!("toon{:02}.bmp", i), 100);
+    }
+
+    utils::write(&mut data, 1u32);
+    sjis(&mut data, "ひざ", 20);
+    utils::write(&mut data, 1u16);
+    utils::write(&mut data, 0u8);
+    utils::write(&mut data, 0xffffu16);
+    utils::write(&mut data, 0u8);
+    utils::write(&mut data, [1.0f32, 0.0, 0.0]);
+    utils::write(&mut data, [0.0f32, 1.0, 0.0]);
+    utils::write(&mut data, [0.0f32, 0.0, 0.0]);
+    utils::write(&mut data, 1.0f32);
+    utils::write(&mut data, [0.5f32, 0.5, 0.0, 0.5]);
+    utils::write(&mut data, 1u8);
+    utils::write(&mut data, 0u32);
+
+    (data, required)
+}
+
+fn parse(data: &[u8]) -> PMXFormat {
+    PmdParser::new().parse(data, PathBuf::new()).unwrap()
+}
+
+#[test]
+fn two_bone_weights_become_bdef() {
+    let (data, _) = model();
+    let model = parse(&data);
+
+    match &model.vertices[0].weight_deform {
+        PMXWeightDeformData::BDEF2(d) => {
+            assert_eq!((d.bone_index1, d.bone_index2), (0, 1));
+            assert!((d.bone1_weight - 0.3).abs() < 1e-6);
+        },
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(model.vertices[0].weight_deform_type, PMXWeightDeformType::BDEF2);
+    // the same bone twice, and a zero weight that leaves only the second bone
+    assert!(matches!(model.vertices[1].weight_deform, PMXWeightDeformData::BDEF1(BDEF1Data { bone_index: 1 })));
+    assert!(matches!(model.vertices[2].weight_deform, PMXWeightDeformData::BDEF1(BDEF1Data { bone_index: 1 })));
+    // the PMD edge flag turns the outline off
+    assert_eq!(model.vertices[0].edge_scale, 1.0);
+    assert_eq!(model.vertices[2].edge_scale, 0.0);
+}
+
+#[test]
+fn sphere_suffix_is_split_from_the_texture() {
+    let (data, _) = model();
+    let model = parse(&data);
+
+    let mat = &model.materials[0];
+    assert_eq!(model.texture_paths[mat.texture_index as usize], "body.png");
+    assert_eq!(model.texture_paths[mat.environment_index as usize], "glow.spa");
+    assert_eq!(mat.environment_blend_mode, PMXEnvironmentBlendMode::Additive);
+    assert!(matches!(mat.toon_value, PMXToonValue::Internal(2)));
+    assert!(mat.drawing_flags.contains(PMXMaterialFlags::EDGE));
+    assert_eq!(mat.surface_count, 3);
+}
+
+#[test]
+fn knees_get_ik_limits() {
+    let (data, _) = model();
+    let model = parse(&data);
+
+    let ik_bone = &model.bones[2];
+    assert!(ik_bone.flags.contains(PMXBoneFlags::IK | PMXBoneFlags::TRANSLATABLE));
+    let ik = ik_bone.ik.as_ref().unwrap();
+    assert_eq!(ik.target_index, 1);
+    assert_eq!(ik.loop_count, 40);
+    assert_eq!(ik.limit_angle, 2.0);
+    assert_eq!(ik.links.len(), 1);
+    assert_eq!(ik.links[0].bone_index, 1);
+    let limit = ik.links[0].angle_limit.as_ref().unwrap();
+    assert_eq!(limit.min, [-PI, 0.0, 0.0]);
+    assert!((limit.max[0] - -0.5f32.to_radians()).abs() < 1e-6);
+
+    assert!(model.bones[0].flags.contains(PMXBoneFlags::TRANSLATABLE | PMXBoneFlags::INDEXED_TAIL_POSITION));
+    assert_eq!(model.bones[1].parent_index, 0);
+    assert_eq!(model.bones[0].parent_index, -1);
+}
+
+#[test]
+fn skins_become_vertex_morphs_relative_to_the_base() {
+    let (data, _) = model();
+    let model = parse(&data);
+
+    // the base skin is not a morph
+    assert_eq!(model.morphs.len(), 1);
+    let morph = &model.morphs[0];
+    assert_eq!(morph.morph_name_local, "あ");
+    assert_eq!(morph.panel, PMXMorphPanel::Mouth);
+    match &morph.offsets {
+        PMXMorphOffsetData::Vertex(offsets) => {
+            assert_eq!(offsets.len(), 1);
+            // base skin entry 1 is vertex 2
+            assert_eq!(offsets[0].vertex_index, 2);
+            assert_eq!(offsets[0].translation, [0.0, 0.5, 0.0]);
+        },
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(model.display_frames[1].frames[..], [PMXFrameData::Morph(0)]));
+}
+
+#[test]
+fn rigid_bodies_are_moved_out_of_bone_space() {
+    let (data, _) = model();
+    let model = parse(&data);
+
+    let body = &model.rigidbodies[0];
+    assert_eq!(body.bone_index, 1);
+    // relative to the knee at y = 5
+    assert_eq!(body.shape_position, [0.0, 6.0, 0.0]);
+    assert_eq!(body.physics_mode, PMXPhysicsMode::Physics);
+    assert!(model.joints.is_empty());
+}
+
+#[test]
+fn optional_sections_may_be_missing() {
+    let (data, required) = model();
+    let model = parse(&data[..required]);
+    assert!(model.rigidbodies.is_empty());
+    assert!(matches!(model.materials[0].toon_value, PMXToonValue::Internal(2)));
+}
+
+#[test]
+fn truncated_model_returns_error() {
+    let (data, required) = model();
+    let parser = PmdParser::new();
+
+    for length in 0..required {
+        let error = match parser.parse(&data[..length], PathBuf::new()) {
+            Ok(_) => panic!("truncated model of {} bytes parsed", length),
+            Err(e) => e,
+        };
+        assert!(error.offset <= length, "offset {} past end {}", error.offset, length);
+    }
+    assert_eq!(parser.parse(&data[..10], PathBuf::new()).unwrap_err().section, PmxSection::Header);
+
+    // inside the optional sections, everything but the section boundaries is an error
+    for length in required..data.len() {
+        let _ = parser.parse(&data[..length], PathBuf::new());
+    }
+    let toon_start = required + 1;
+    assert_eq!(parser.parse(&data[..toon_start + 50], PathBuf::new()).unwrap_err().section, PmxSection::ToonTextures);
+}
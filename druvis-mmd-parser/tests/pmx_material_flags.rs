@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use druvis_mmd_parser::{PmxParser, pmx::{pmx_parser::{material_topology, material_cull_mode, material_indices}, structs::{PMXMaterialData, PMXMaterialFlags, PMXSurfaceData, PMXEnvironmentBlendMode, PMXToonReference, PMXToonValue}}};
+use wgpu::{PrimitiveTopology, Face};
+
+fn model_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../models/yoimiya/宵宫.pmx")
+}
+
+fn material(drawing_flags: PMXMaterialFlags, surface_count: i32) -> PMXMaterialData {
+    PMXMaterialData {
+        material_name_local: String::new(),
+        material_name_universal: String::new(),
+        diffuse_color: [1.0; 4],
+        specular_color: [0.0; 3],
+        specular_strength: 0.0,
+        ambient_color: [0.0; 3],
+        drawing_flags,
+        edge_color: [0.0, 0.0, 0.0, 1.0],
+        edge_scale: 1.0,
+        texture_index: -1,
+        environment_index: -1,
+        environment_blend_mode: PMXEnvironmentBlendMode::Disabled,
+        toon_reference: PMXToonReference::Internal,
+        toon_value: PMXToonValue::Internal(0),
+        meta_data: String::new(),
+        surface_count,
+    }
+}
+
+fn surfaces(triangles: &[[i32; 3]]) -> Vec<PMXSurfaceData> {
+    triangles.iter().map(|&triangle| PMXSurfaceData { triangle }).collect()
+}
+
+#[test]
+fn material_drawing_flags_are_decoded() {
+    let model = PmxParser::new().parse_file(model_path()).unwrap();
+    let material = |name: &str| model.materials.iter().find(|m| m.material_name_local == name).unwrap();
+
+    // double sided hair with an outline
+    assert!(material("髮").drawing_flags.contains(PMXMaterialFlags::NO_CULL | PMXMaterialFlags::EDGE));
+    // the eye whites stay out of the shadow map
+    let eyes = material("白目").drawing_flags;
+    assert!(!eyes.intersects(PMXMaterialFlags::DRAW_SHADOW | PMXMaterialFlags::RECEIVE_SHADOW | PMXMaterialFlags::EDGE));
+    assert!(model.materials.iter().all(|m| !m.drawing_flags.intersects(PMXMaterialFlags::POINT_DRAWING | PMXMaterialFlags::LINE_DRAWING)));
+}
+
+#[test]
+fn point_drawing_wins_over_line_drawing() {
+    assert_eq!(material_topology(PMXMaterialFlags::empty()), PrimitiveTopology::TriangleList);
+    assert_eq!(material_topology(PMXMaterialFlags::NO_CULL | PMXMaterialFlags::EDGE), PrimitiveTopology::TriangleList);
+    assert_eq!(material_topology(PMXMaterialFlags::LINE_DRAWING), PrimitiveTopology::LineList);
+    assert_eq!(material_topology(PMXMaterialFlags::POINT_DRAWING), PrimitiveTopology::PointList);
+    assert_eq!(material_topology(PMXMaterialFlags::POINT_DRAWING | PMXMaterialFlags::LINE_DRAWING), PrimitiveTopology::PointList);
+}
+
+#[test]
+fn only_single_sided_triangles_keep_the_shader_cull_mode() {
+    let shader = Some(Face::Back);
+    assert_eq!(material_cull_mode(PMXMaterialFlags::empty(), shader), shader);
+    assert_eq!(material_cull_mode(PMXMaterialFlags::DRAW_SHADOW | PMXMaterialFlags::EDGE, Some(Face::Front)), Some(Face::Front));
+    assert_eq!(material_cull_mode(PMXMaterialFlags::NO_CULL, shader), None);
+    // points and lines have no faces to cull
+    assert_eq!(material_cull_mode(PMXMaterialFlags::LINE_DRAWING, shader), None);
+    assert_eq!(material_cull_mode(PMXMaterialFlags::POINT_DRAWING, shader), None);
+    // a double sided shader stays double sided
+    assert_eq!(material_cull_mode(PMXMaterialFlags::empty(), None), None);
+}
+
+#[test]
+fn line_drawing_expands_triangles_into_edges() {
+    let materials = [
+        material(PMXMaterialFlags::empty(), 3),
+        material(PMXMaterialFlags::LINE_DRAWING, 6),
+        material(PMXMaterialFlags::POINT_DRAWING, 3),
+    ];
+    let surfaces = surfaces(&[[0, 1, 2], [3, 4, 5], [6, 7, 8], [9, 10, 11]]);
+    let (indices, submeshes) = material_indices(&materials, &surfaces);
+
+    assert_eq!(submeshes, vec![(0, 3), (3, 15), (15, 18)]);
+    assert_eq!(&indices[0..3], &[0, 1, 2]);
+    assert_eq!(&indices[3..15], &[3, 4, 4, 5, 5, 3, 6, 7, 7, 8, 8, 6]);
+    // points draw the triangle's vertices as they are
+    assert_eq!(&indices[15..18], &[9, 10, 11]);
+}
+
+#[test]
+fn surfaces_past_the_materials_are_kept() {
+    let materials = [material(PMXMaterialFlags::LINE_DRAWING, 3), material(PMXMaterialFlags::empty(), -3)];
+    let surfaces = surfaces(&[[0, 1, 2], [3, 4, 5]]);
+    let (indices, submeshes) = material_indices(&materials, &surfaces);
+
+    // a negative count takes no surfaces
+    assert_eq!(submeshes, vec![(0, 6), (6, 6)]);
+    assert_eq!(indices, vec![0, 1, 1, 2, 2, 0, 3, 4, 5]);
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use druvis_mmd_parser::{PmxParser, PmxWriter, pmx::structs::{TextEncodingType, PMXIndexType}};
+
+fn model_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../models/yoimiya/宵宫.pmx")
+}
+
+#[test]
+fn bundled_model_is_written_back_unchanged() {
+    let data = std::fs::read(model_path()).unwrap();
+    let parser = PmxParser::new();
+
+    let model = parser.parse(&data, model_path()).unwrap();
+    let written = PmxWriter::new(TextEncodingType::UTF16LE).to_bytes(&model).unwrap();
+    assert_eq!(written.len(), data.len());
+    assert!(written == data, "written model differs from the original");
+}
+
+#[test]
+fn round_trip_is_byte_stable() {
+    let parser = PmxParser::new();
+    let model = parser.parse_file(model_path()).unwrap();
+
+    for encoding in [TextEncodingType::UTF16LE, TextEncodingType::UTF8] {
+        let writer = PmxWriter::new(encoding);
+        let first = writer.to_bytes(&model).unwrap();
+        let reparsed = parser.parse(&first, model_path()).unwrap();
+        assert_eq!(reparsed.globals.text_encoding, encoding);
+        assert_eq!(reparsed.bones.len(), model.bones.len());
+        assert_eq!(reparsed.morphs.len(), model.morphs.len());
+        assert_eq!(reparsed.header.model_name_local, model.header.model_name_local);
+
+        let second = writer.to_bytes(&reparsed).unwrap();
+        assert!(first == second, "{:?} round trip is not byte stable", encoding);
+    }
+}
+
+#[test]
+fn index_sizes_are_minimal() {
+    let parser = PmxParser::new();
+    let mut model = parser.parse_file(model_path()).unwrap();
+    let writer = PmxWriter::default();
+
+    // 327 bones need 2 bytes, 62 morphs fit in 1
+    let globals = writer.globals_for(&model);
+    assert_eq!(globals.bone_index_size, PMXIndexType::B2);
+    assert_eq!(globals.morph_index_size, PMXIndexType::B1);
+
+    // stripping morphs still produces a valid model
+    model.morphs.truncate(10);
+    model.display_frames.iter_mut().for_each(|d| d.frames.retain(|f| match f {
+        druvis_mmd_parser::pmx::structs::PMXFrameData::Morph(i) => *i < 10,
+        _ => true,
+    }));
+    let written = writer.to_bytes(&model).unwrap();
+    let reparsed = parser.parse(&written, model_path()).unwrap();
+    assert_eq!(reparsed.morphs.len(), 10);
+}
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use druvis_mmd_parser::{PmxParser, pmx::pmx_error::{PmxErrorKind, PmxSection}};
+use druvis_mmd_parser::utils::ReadErrorKind;
+
+fn model_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../models/yoimiya/宵宫.pmx")
+}
+
+fn load_model() -> Vec<u8> {
+    std::fs::read(model_path()).unwrap()
+}
+
+#[test]
+fn parses_complete_model() {
+    let data = load_model();
+    let result = PmxParser::new().parse(&data, model_path()).unwrap();
+    assert_eq!(result.bones.len(), 327);
+    assert_eq!(result.morphs.len(), 62);
+}
+
+#[test]
+fn parses_model_file() {
+    let result = PmxParser::new().parse_file(model_path()).unwrap();
+    assert_eq!(result.model_path(), model_path().parent().unwrap());
+    assert_eq!(result.bones.len(), 327);
+}
+
+#[test]
+fn truncated_model_returns_error() {
+    let data = load_model();
+    let parser = PmxParser::new();
+
+    // every prefix of the header and the first vertices, then strided through the rest
+    let lengths = (0..2048).chain((2048..data.len()).step_by(8191));
+    for length in lengths {
+        let result = parser.parse(&data[..length], model_path());
+        let error = match result {
+            Ok(_) => panic!("truncated model of {} bytes parsed", length),
+            Err(e) => e,
+        };
+        assert!(error.offset <= length, "offset {} past end {}", error.offset, length);
+        assert!(matches!(error.kind, PmxErrorKind::Read(ReadErrorKind::UnexpectedEof { .. })), "{} bytes: {}", length, error);
+    }
+}
+
+#[test]
+fn truncation_reports_section() {
+    let data = load_model();
+    let parser = PmxParser::new();
+
+    let error = parser.parse(&data[..10], model_path()).unwrap_err();
+    assert_eq!(error.section, PmxSection::Header);
+
+    let error = parser.parse(&data[..data.len() - 1], model_path()).unwrap_err();
+    assert_eq!(error.section, PmxSection::Joints);
+    assert_eq!(error.index, Some(255));
+}
+
+#[test]
+fn corrupted_model_does_not_panic() {
+    let data = load_model();
+    let parser = PmxParser::new();
+
+    // deterministic linear congruential generator, so failures are reproducible
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    let mut next = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) as usize
+    };
+
+    for _ in 0..64 {
+        let mut corrupted = data.clone();
+        for _ in 0..8 {
+            let position = next() % corrupted.len();
+            corrupted[position] = next() as u8;
+        }
+        let _ = parser.parse(&corrupted, model_path());
+    }
+}
+
+#[test]
+fn invalid_signature_and_version() {
+    let mut data = load_model();
+    let parser = PmxParser::new();
+
+    data[0] = b'Q';
+    let error = parser.parse(&data, model_path()).unwrap_err();
+    assert!(matches!(error.kind, PmxErrorKind::InvalidSignature(_)));
+    assert_eq!(error.offset, 0);
+
+    data[0] = b'P';
+    data[4..8].copy_from_slice(&3.0f32.to_le_bytes());
+    let error = parser.parse(&data, model_path()).unwrap_err();
+    assert!(matches!(error.kind, PmxErrorKind::UnsupportedVersion(_)));
+    assert_eq!(error.offset, 4);
+}
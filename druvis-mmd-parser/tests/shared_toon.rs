@@ -0,0 +1,31 @@
+use druvis_mmd_parser::pmx::shared_toon::{self, SHARED_TOON_COUNT, SHARED_TOON_HEIGHT};
+
+#[test]
+fn file_names_match_mmd() {
+    assert_eq!(shared_toon::file_name(0), "toon01.bmp");
+    assert_eq!(shared_toon::file_name(9), "toon10.bmp");
+}
+
+#[test]
+fn ramps_go_from_white_to_shadow() {
+    for i in 0..SHARED_TOON_COUNT {
+        let pixels = shared_toon::pixels(i).unwrap();
+        assert_eq!(pixels.len(), SHARED_TOON_HEIGHT as usize * 4);
+
+        let rows = pixels.chunks(4).collect::<Vec<_>>();
+        // lit side at the top
+        assert_eq!(rows[0], &[255, 255, 255, 255]);
+        let bottom = rows[rows.len() - 1];
+        assert!(bottom[..3].iter().all(|&c| c < 255), "toon {} {:?}", i, bottom);
+        assert_eq!(bottom[3], 255);
+        // never brighter further down
+        for pair in rows.windows(2) {
+            assert!((0..3).all(|c| pair[1][c] <= pair[0][c]));
+        }
+    }
+}
+
+#[test]
+fn out_of_range_toons_have_no_pixels() {
+    assert!(shared_toon::pixels(SHARED_TOON_COUNT).is_none());
+}
@@ -0,0 +1,72 @@
+use std::{fs, path::PathBuf};
+
+use druvis_mmd_parser::{PmxParser, pmx::texture_path::{resolve_texture_path, TextureWarningKind}};
+
+fn model_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../models/yoimiya/宵宫.pmx")
+}
+
+// a fresh directory laid out like a model exported on windows
+fn model_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("druvis_texture_path_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("Tex/Sub")).unwrap();
+    fs::write(dir.join("Tex/Sub/Face.PNG"), b"").unwrap();
+    fs::write(dir.join("Tex/hair.bmp"), b"").unwrap();
+    dir
+}
+
+#[test]
+fn windows_separators_and_case_are_accepted() {
+    let dir = model_dir("case");
+    let expected = dir.join("Tex/Sub/Face.PNG");
+
+    assert_eq!(resolve_texture_path(&dir, "Tex\\Sub\\Face.PNG"), Some(expected.clone()));
+    assert_eq!(resolve_texture_path(&dir, "tex\\sub\\face.png"), Some(expected.clone()));
+    assert_eq!(resolve_texture_path(&dir, ".\\TEX/Sub\\\\face.png"), Some(expected));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn other_image_extensions_are_tried() {
+    let dir = model_dir("extension");
+
+    assert_eq!(resolve_texture_path(&dir, "tex\\hair.png"), Some(dir.join("Tex/hair.bmp")));
+    assert_eq!(resolve_texture_path(&dir, "tex\\sub\\face.tga"), Some(dir.join("Tex/Sub/Face.PNG")));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_files_are_not_resolved() {
+    let dir = model_dir("missing");
+
+    assert_eq!(resolve_texture_path(&dir, "tex\\eye.png"), None);
+    assert_eq!(resolve_texture_path(&dir, "other\\hair.bmp"), None);
+    // a directory is not a texture
+    assert_eq!(resolve_texture_path(&dir, "tex\\sub"), None);
+    assert_eq!(resolve_texture_path(&dir, ""), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bundled_model_has_every_texture() {
+    let model = PmxParser::new().parse_file(model_path()).unwrap();
+    assert_eq!(model.texture_warnings(), Vec::new());
+}
+
+#[test]
+fn broken_references_give_warnings() {
+    let mut model = PmxParser::new().parse_file(model_path()).unwrap();
+    let count = model.texture_paths.len();
+
+    assert_eq!(model.resolve_texture(-1), Ok(None));
+    let warning = model.resolve_texture(count as i32).unwrap_err();
+    assert_eq!(warning.kind, TextureWarningKind::IndexOutOfRange { count });
+
+    model.texture_paths[0] = String::from("tex\\does_not_exist.png");
+    model.materials[0].texture_index = 0;
+    let warning = model.resolve_texture(0).unwrap_err();
+    assert_eq!(warning.kind, TextureWarningKind::NotFound);
+    assert_eq!(warning.path, "tex\\does_not_exist.png");
+    assert!(model.texture_warnings().contains(&warning));
+}
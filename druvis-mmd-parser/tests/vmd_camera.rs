@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use cgmath::{Vector3, InnerSpace};
+use druvis_core::camera::{camera::{CameraController, GetViewProjectionMatrix}, perspective_camera::PerspectiveCamera};
+use druvis_mmd_parser::VMDCameraController;
+use druvis_mmd_parser::vmd::{vmd_parser::VMDMotion, structs::*};
+
+const EPSILON: f32 = 1e-3;
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < EPSILON, "{} != {}", a, b);
+}
+
+fn assert_vector(a: Vector3<f32>, b: Vector3<f32>) {
+    assert!((a - b).magnitude() < EPSILON, "{:?} != {:?}", a, b);
+}
+
+fn camera_key(frame: u32, distance: f32, target: [f32; 3], rotation: [f32; 3], fov: u32) -> VMDCameraKeyframe {
+    VMDCameraKeyframe {
+        frame,
+        distance,
+        target,
+        rotation,
+        interpolation: Default::default(),
+        fov,
+        perspective: true,
+    }
+}
+
+fn motion(camera_keyframes: Vec<VMDCameraKeyframe>) -> VMDMotion {
+    VMDMotion {
+        header: VMDHeader {
+            version: VMDVersion::V2,
+            model_name: String::from("カメラ・照明"),
+        },
+        bone_keyframes: Vec::new(),
+        morph_keyframes: Vec::new(),
+        camera_keyframes,
+        light_keyframes: Vec::new(),
+        self_shadow_keyframes: Vec::new(),
+        show_ik_keyframes: Vec::new(),
+    }
+}
+
+#[test]
+fn default_camera_looks_at_the_target() {
+    let controller = VMDCameraController::new(&motion(vec![camera_key(0, -45.0, [0.0, 10.0, 0.0], [0.0; 3], 30)]));
+    let pose = controller.sample(0.0).unwrap();
+    assert_vector(pose.position(), Vector3::new(0.0, 10.0, -45.0));
+
+    let mut camera = PerspectiveCamera::default();
+    pose.apply(&mut camera);
+    assert_close(camera.fovy.0, 30.0_f32.to_radians());
+    let target = camera.view_matrix() * Vector3::new(0.0, 10.0, 0.0).extend(1.0);
+    assert_vector(target.truncate(), Vector3::new(0.0, 0.0, -45.0));
+}
+
+#[test]
+fn rolled_camera_keeps_its_up_vector() {
+    let controller = VMDCameraController::new(&motion(vec![camera_key(0, -30.0, [1.0, 12.0, 3.0], [0.3, -0.7, 0.4], 45)]));
+    let pose = controller.sample(0.0).unwrap();
+    let mut camera = PerspectiveCamera::default();
+    pose.apply(&mut camera);
+
+    let view = camera.view_matrix();
+    assert_vector((view * pose.forward().extend(0.0)).truncate(), -Vector3::unit_z());
+    assert_vector((view * pose.up().extend(0.0)).truncate(), Vector3::unit_y());
+    assert_vector((view * Vector3::new(1.0, 12.0, 3.0).extend(1.0)).truncate(), Vector3::new(0.0, 0.0, -30.0));
+}
+
+#[test]
+fn interpolates_between_keyframes_and_cuts_on_adjacent_frames() {
+    let controller = VMDCameraController::new(&motion(vec![
+        camera_key(0, -40.0, [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 20),
+        camera_key(10, -20.0, [10.0, 0.0, 0.0], [0.0, 1.0, 0.0], 40),
+        camera_key(11, -50.0, [0.0, 5.0, 0.0], [0.0, 0.0, 0.0], 30),
+    ]));
+
+    let pose = controller.sample(5.0).unwrap();
+    assert_close(pose.distance, -30.0);
+    assert_vector(pose.target, Vector3::new(5.0, 0.0, 0.0));
+    assert_close(pose.rotation.y, 0.5);
+    assert_close(pose.fov, 30.0);
+
+    // the second keyframe holds until the cut
+    let pose = controller.sample(10.5).unwrap();
+    assert_close(pose.distance, -20.0);
+    let pose = controller.sample(11.0).unwrap();
+    assert_close(pose.distance, -50.0);
+}
+
+#[test]
+fn updates_the_camera_while_playing() {
+    let mut controller = VMDCameraController::new(&motion(vec![
+        camera_key(0, -40.0, [0.0, 0.0, 0.0], [0.0; 3], 30),
+        camera_key(30, -40.0, [0.0, 0.0, 30.0], [0.0; 3], 30),
+    ]));
+    let mut camera = PerspectiveCamera::default();
+
+    controller.update_camera(&mut camera, Duration::from_millis(500));
+    assert_close(controller.frame, 15.0);
+    assert_close(camera.position.z, -25.0);
+
+    controller.pause();
+    controller.update_camera(&mut camera, Duration::from_millis(500));
+    assert_close(controller.frame, 15.0);
+
+    controller.play();
+    controller.update_camera(&mut camera, Duration::from_secs(5));
+    assert_close(controller.frame, 30.0);
+    assert!(!controller.playing);
+    assert_close(camera.position.z, -10.0);
+}
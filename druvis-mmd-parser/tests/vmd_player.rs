@@ -0,0 +1,323 @@
+use std::time::Duration;
+
+use cgmath::{Vector3, Quaternion, Rotation3, Deg, InnerSpace, Zero};
+use druvis_core::game_object::components::{SkeletonData, Bone, IkChain, IkLink, MorphControllerData};
+use druvis_mmd_parser::VMDPlayer;
+use druvis_mmd_parser::vmd::{vmd_parser::VMDMotion, structs::*};
+
+const EPSILON: f32 = 1e-4;
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < EPSILON, "{} != {}", a, b);
+}
+
+fn assert_vector(a: Vector3<f32>, b: Vector3<f32>) {
+    assert!((a - b).magnitude() < EPSILON, "{:?} != {:?}", a, b);
+}
+
+fn assert_rotation(a: Quaternion<f32>, b: Quaternion<f32>) {
+    // q and -q are the same rotation
+    assert!(a.dot(b).abs() > 1.0 - EPSILON, "{:?} != {:?}", a, b);
+}
+
+fn to_xyzw(q: Quaternion<f32>) -> [f32; 4] {
+    [q.v.x, q.v.y, q.v.z, q.s]
+}
+
+fn bone_key(name: &str, frame: u32, translation: [f32; 3], rotation: Quaternion<f32>, interpolation: VMDBoneInterpolation) -> VMDBoneKeyframe {
+    VMDBoneKeyframe {
+        bone_name: String::from(name),
+        frame,
+        translation,
+        rotation: to_xyzw(rotation),
+        interpolation,
+    }
+}
+
+fn morph_key(name: &str, frame: u32, weight: f32) -> VMDMorphKeyframe {
+    VMDMorphKeyframe {
+        morph_name: String::from(name),
+        frame,
+        weight,
+    }
+}
+
+fn ik_key(frame: u32, enabled: bool) -> VMDShowIKKeyframe {
+    VMDShowIKKeyframe {
+        frame,
+        show: true,
+        ik_states: vec![VMDIKEnableData {
+            bone_name: String::from("左足ＩＫ"),
+            enabled,
+        }],
+    }
+}
+
+fn motion(bone_keyframes: Vec<VMDBoneKeyframe>, morph_keyframes: Vec<VMDMorphKeyframe>, show_ik_keyframes: Vec<VMDShowIKKeyframe>) -> VMDMotion {
+    VMDMotion {
+        header: VMDHeader {
+            version: VMDVersion::V2,
+            model_name: String::from("test"),
+        },
+        bone_keyframes,
+        morph_keyframes,
+        camera_keyframes: Vec::new(),
+        light_keyframes: Vec::new(),
+        self_shadow_keyframes: Vec::new(),
+        show_ik_keyframes,
+    }
+}
+
+// センター with a two bone leg and its IK bone
+fn skeleton() -> SkeletonData {
+    let mut ik = Bone::new("左足ＩＫ", None, Vector3::new(1.0, 0.0, 0.0));
+    ik.ik = Some(IkChain {
+        target: 3,
+        loop_count: 10,
+        limit_angle: 1.0,
+        links: vec![IkLink::new(2, None)],
+    });
+    SkeletonData::new(vec![
+        Bone::new("センター", None, Vector3::new(0.0, 8.0, 0.0)),
+        ik,
+        Bone::new("左足", Some(0), Vector3::new(1.0, 8.0, 0.0)),
+        Bone::new("左足首", Some(2), Vector3::new(1.0, 1.0, 0.0)),
+    ])
+}
+
+fn morphs() -> MorphControllerData {
+    MorphControllerData::new(vec![String::from("まばたき"), String::from("あ")])
+}
+
+fn ease_in() -> VMDBezier {
+    VMDBezier { x1: 127, y1: 0, x2: 127, y2: 127 }
+}
+
+#[test]
+fn linear_bezier_is_identity() {
+    for i in 0..=10 {
+        let x = i as f32 / 10.0;
+        assert_close(VMDBezier::LINEAR.evaluate(x), x);
+    }
+}
+
+#[test]
+fn bezier_hits_end_points_and_stays_monotonic() {
+    let curves = [
+        ease_in(),
+        VMDBezier { x1: 0, y1: 127, x2: 0, y2: 127 },
+        VMDBezier { x1: 64, y1: 0, x2: 64, y2: 127 },
+        VMDBezier { x1: 10, y1: 100, x2: 30, y2: 5 },
+    ];
+    for curve in curves.iter() {
+        assert_close(curve.evaluate(0.0), 0.0);
+        assert_close(curve.evaluate(1.0), 1.0);
+        let mut last = 0.0;
+        for i in 1..=100 {
+            let y = curve.evaluate(i as f32 / 100.0);
+            assert!(y >= last - EPSILON, "{:?} not monotonic at {}", curve, i);
+            last = y;
+        }
+    }
+}
+
+#[test]
+fn bezier_matches_parametric_curve() {
+    let curve = VMDBezier { x1: 20, y1: 90, x2: 60, y2: 120 };
+    let (x1, y1, x2, y2) = (20.0 / 127.0, 90.0 / 127.0, 60.0 / 127.0, 120.0 / 127.0);
+    let point = |p1: f32, p2: f32, s: f32| 3.0 * (1.0 - s) * (1.0 - s) * s * p1 + 3.0 * (1.0 - s) * s * s * p2 + s * s * s;
+    for i in 0..=20 {
+        let s = i as f32 / 20.0;
+        assert_close(curve.evaluate(point(x1, x2, s)), point(y1, y2, s));
+    }
+}
+
+#[test]
+fn samples_bone_keyframes_exactly() {
+    let r0 = Quaternion::from_angle_y(Deg(10.0));
+    let r1 = Quaternion::from_angle_x(Deg(-45.0));
+    let motion = motion(vec![
+        bone_key("センター", 0, [1.0, 2.0, 3.0], r0, Default::default()),
+        bone_key("センター", 30, [-4.0, 5.0, 0.5], r1, Default::default()),
+    ], Vec::new(), Vec::new());
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    let pose = player.sample(0.0);
+    assert_eq!(pose.bones.len(), 1);
+    assert_eq!(pose.bones[0].bone, 0);
+    assert_vector(pose.bones[0].translation, Vector3::new(1.0, 2.0, 3.0));
+    assert_rotation(pose.bones[0].rotation, r0);
+
+    let pose = player.sample(30.0);
+    assert_vector(pose.bones[0].translation, Vector3::new(-4.0, 5.0, 0.5));
+    assert_rotation(pose.bones[0].rotation, r1);
+}
+
+#[test]
+fn clamps_outside_of_the_keyframes() {
+    let motion = motion(vec![
+        bone_key("センター", 10, [1.0, 0.0, 0.0], Quaternion::from_angle_z(Deg(30.0)), Default::default()),
+        bone_key("センター", 20, [2.0, 0.0, 0.0], Quaternion::from_angle_z(Deg(60.0)), Default::default()),
+    ], Vec::new(), Vec::new());
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    assert_vector(player.sample(0.0).bones[0].translation, Vector3::new(1.0, 0.0, 0.0));
+    assert_vector(player.sample(100.0).bones[0].translation, Vector3::new(2.0, 0.0, 0.0));
+    assert_rotation(player.sample(100.0).bones[0].rotation, Quaternion::from_angle_z(Deg(60.0)));
+}
+
+#[test]
+fn interpolates_linearly_with_linear_curves() {
+    let motion = motion(vec![
+        bone_key("センター", 0, [0.0, 0.0, 0.0], Quaternion::from_angle_y(Deg(0.0)), Default::default()),
+        bone_key("センター", 10, [10.0, -20.0, 4.0], Quaternion::from_angle_y(Deg(90.0)), Default::default()),
+    ], Vec::new(), Vec::new());
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    let pose = player.sample(2.5);
+    assert_vector(pose.bones[0].translation, Vector3::new(2.5, -5.0, 1.0));
+    assert_rotation(pose.bones[0].rotation, Quaternion::from_angle_y(Deg(22.5)));
+}
+
+#[test]
+fn uses_the_curves_of_the_later_keyframe_per_channel() {
+    let interpolation = VMDBoneInterpolation {
+        x: ease_in(),
+        y: VMDBezier::LINEAR,
+        z: VMDBezier::LINEAR,
+        rotation: ease_in(),
+    };
+    let motion = motion(vec![
+        // curves on the first keyframe only shape the segment before it
+        bone_key("センター", 0, [0.0, 0.0, 0.0], Quaternion::from_angle_y(Deg(0.0)), VMDBoneInterpolation {
+            y: ease_in(),
+            ..Default::default()
+        }),
+        bone_key("センター", 10, [10.0, 10.0, 10.0], Quaternion::from_angle_y(Deg(90.0)), interpolation),
+    ], Vec::new(), Vec::new());
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    let eased = ease_in().evaluate(0.5);
+    assert!(eased < 0.5);
+    let pose = player.sample(5.0);
+    assert_vector(pose.bones[0].translation, Vector3::new(10.0 * eased, 5.0, 5.0));
+    assert_rotation(pose.bones[0].rotation, Quaternion::from_angle_y(Deg(90.0 * eased)));
+}
+
+#[test]
+fn keyframes_are_sorted_and_unknown_names_are_skipped() {
+    let motion = motion(vec![
+        bone_key("センター", 20, [2.0, 0.0, 0.0], Quaternion::from_angle_y(Deg(0.0)), Default::default()),
+        bone_key("存在しない", 5, [9.0, 9.0, 9.0], Quaternion::from_angle_y(Deg(0.0)), Default::default()),
+        bone_key("センター", 0, [0.0, 0.0, 0.0], Quaternion::from_angle_y(Deg(0.0)), Default::default()),
+    ], vec![morph_key("存在しない", 0, 1.0)], Vec::new());
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+    assert_eq!(player.missing_bone_keyframes, 1);
+    assert_eq!(player.missing_morph_keyframes, 1);
+
+    let pose = player.sample(10.0);
+    assert_eq!(pose.bones.len(), 1);
+    assert!(pose.morphs.is_empty());
+    assert_vector(pose.bones[0].translation, Vector3::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn interpolates_morph_weights_linearly() {
+    let motion = motion(Vec::new(), vec![
+        morph_key("あ", 0, 0.0),
+        morph_key("あ", 10, 1.0),
+        morph_key("あ", 20, 0.5),
+        morph_key("まばたき", 4, 0.25),
+    ], Vec::new());
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    let weight = |frame: f32, morph: usize| player.sample(frame).morphs.iter().find(|m| m.0 == morph).unwrap().1;
+    assert_close(weight(0.0, 1), 0.0);
+    assert_close(weight(3.0, 1), 0.3);
+    assert_close(weight(10.0, 1), 1.0);
+    assert_close(weight(15.0, 1), 0.75);
+    assert_close(weight(40.0, 1), 0.5);
+    assert_close(weight(0.0, 0), 0.25);
+}
+
+#[test]
+fn ik_switches_hold_until_the_next_keyframe() {
+    let motion = motion(Vec::new(), Vec::new(), vec![ik_key(10, false), ik_key(20, true)]);
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    let enabled = |frame: f32| player.sample(frame).ik_enabled[0];
+    assert_eq!(enabled(0.0), (1, true));
+    assert_eq!(enabled(10.0), (1, false));
+    assert_eq!(enabled(19.9), (1, false));
+    assert_eq!(enabled(25.0), (1, true));
+}
+
+#[test]
+fn applies_the_pose_to_the_model() {
+    let motion = motion(
+        vec![bone_key("センター", 0, [0.0, -1.0, 0.0], Quaternion::from_angle_y(Deg(0.0)), Default::default())],
+        vec![morph_key("まばたき", 0, 1.0)],
+        vec![ik_key(0, false)],
+    );
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    let mut skeleton = skeleton();
+    let mut morphs = morphs();
+    morphs.weights[1] = 0.5;
+    player.apply(&mut skeleton, &mut morphs);
+
+    assert_vector(skeleton.bones[0].translation, Vector3::new(0.0, -1.0, 0.0));
+    assert_vector(skeleton.bones[3].world_matrix.w.truncate(), Vector3::new(1.0, 0.0, 0.0));
+    assert!(!skeleton.bones[1].ik_enabled);
+    // morphs the motion does not key are reset
+    assert_eq!(morphs.weights, vec![1.0, 0.0]);
+}
+
+#[test]
+fn plays_pauses_seeks_and_loops() {
+    let motion = motion(Vec::new(), vec![morph_key("あ", 0, 0.0), morph_key("あ", 30, 1.0)], Vec::new());
+    let mut player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+    assert_eq!(player.duration(), Duration::from_secs(1));
+
+    // paused by default
+    player.update(Duration::from_millis(500));
+    assert_close(player.frame, 0.0);
+
+    player.play();
+    player.update(Duration::from_millis(500));
+    assert_close(player.frame, 15.0);
+
+    player.pause();
+    player.update(Duration::from_millis(500));
+    assert_close(player.frame, 15.0);
+
+    player.seek(-5.0);
+    assert_close(player.frame, 0.0);
+    player.seek(24.0);
+    assert_close(player.frame, 24.0);
+
+    player.looping = true;
+    player.play();
+    player.update(Duration::from_millis(400));
+    assert_close(player.frame, 6.0);
+    assert!(player.playing);
+
+    player.looping = false;
+    player.update(Duration::from_secs(2));
+    assert_close(player.frame, 30.0);
+    assert!(!player.playing);
+}
+
+#[test]
+fn samples_between_whole_frames() {
+    let motion = motion(vec![
+        bone_key("左足", 0, [0.0, 0.0, 0.0], Quaternion::from_angle_x(Deg(0.0)), Default::default()),
+        bone_key("左足", 1, [1.0, 0.0, 0.0], Quaternion::from_angle_x(Deg(0.0)), Default::default()),
+    ], Vec::new(), Vec::new());
+    let player = VMDPlayer::new(&motion, &skeleton(), &morphs());
+
+    let pose = player.sample(0.25);
+    assert_eq!(pose.bones[0].bone, 2);
+    assert_vector(pose.bones[0].translation, Vector3::new(0.25, 0.0, 0.0));
+    assert_vector(player.sample(0.0).bones[0].translation, Vector3::zero());
+}
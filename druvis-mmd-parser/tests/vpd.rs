@@ -0,0 +1,101 @@
+use druvis_mmd_parser::{VpdParser, vpd::{structs::{VPDPose, VPDBonePose, VPDMorphPose}, vpd_error::VpdErrorKind}};
+
+fn pose() -> VPDPose {
+    VPDPose {
+        model_file_name: String::from("初音ミク.osm"),
+        bones: vec![
+            VPDBonePose {
+                bone_name: String::from("センター"),
+                translation: [0.5, -1.25, 2.0],
+                rotation: [0.0, 0.0, 0.0, 1.0],
+            },
+            VPDBonePose {
+                bone_name: String::from("左ひじ"),
+                translation: [0.0, 0.0, 0.0],
+                rotation: [0.0, 0.6, 0.0, 0.8],
+            },
+        ],
+        morphs: vec![VPDMorphPose {
+            morph_name: String::from("まばたき"),
+            weight: 0.75,
+        }],
+    }
+}
+
+// a valid header followed by `blocks`, which start at line 5
+fn with_blocks(blocks: &str) -> String {
+    format!("Vocaloid Pose Data file\n\nmiku.osm;\n1;\n{}", blocks)
+}
+
+#[test]
+fn written_pose_parses_back() {
+    let mut data = Vec::new();
+    pose().write(&mut data).unwrap();
+    // Shift-JIS, not UTF-8
+    assert!(std::str::from_utf8(&data).is_err());
+
+    let parsed = VpdParser::new().parse(&data).unwrap();
+    assert_eq!(parsed, pose());
+}
+
+#[test]
+fn comments_and_blank_lines_are_ignored() {
+    let text = with_blocks("Bone0{センター // the root\n  1,2,3; // trans\n  0,0,0,1;\n}\n\n// Morph0{skipped\n");
+    let parsed = VpdParser::new().parse_text(&text).unwrap();
+    assert_eq!(parsed.model_file_name, "miku.osm");
+    assert_eq!(parsed.bones.len(), 1);
+    assert_eq!(parsed.bones[0].translation, [1.0, 2.0, 3.0]);
+    assert!(parsed.morphs.is_empty());
+}
+
+#[test]
+fn invalid_numbers_report_their_block() {
+    let text = with_blocks("Bone0{センター\n  0,0,0;\n  0,0,0,1;\n}\nBone1{頭\n  0,abc,0;\n  0,0,0,1;\n}\n");
+    let error = VpdParser::new().parse_text(&text).unwrap_err();
+    assert_eq!(error.kind, VpdErrorKind::InvalidNumber(String::from("0,abc,0")));
+    assert_eq!(error.line, 9);
+
+    // too many components is as bad as too few
+    let text = with_blocks("Morph0{あ\n  0.5,1;\n}\n");
+    let error = VpdParser::new().parse_text(&text).unwrap_err();
+    assert_eq!(error.kind, VpdErrorKind::InvalidNumber(String::from("0.5,1")));
+    assert_eq!(error.line, 5);
+}
+
+#[test]
+fn unknown_blocks_are_rejected() {
+    let text = with_blocks("\nCamera0{カメラ\n  0,0,0;\n}\n");
+    let error = VpdParser::new().parse_text(&text).unwrap_err();
+    assert_eq!(error.kind, VpdErrorKind::InvalidBlock(String::from("Camera0")));
+    assert_eq!(error.line, 6);
+
+    // a bone needs both statements
+    let text = with_blocks("Bone0{センター\n  0,0,0;\n}\n");
+    let error = VpdParser::new().parse_text(&text).unwrap_err();
+    assert_eq!(error.kind, VpdErrorKind::InvalidBlock(String::from("Bone0")));
+}
+
+#[test]
+fn unclosed_blocks_report_where_they_start() {
+    let text = with_blocks("Bone0{センター\n  0,0,0;\n  0,0,0,1;\n}\nMorph0{あ\n  0.5;\n");
+    let error = VpdParser::new().parse_text(&text).unwrap_err();
+    assert_eq!(error.kind, VpdErrorKind::UnexpectedEnd);
+    assert_eq!(error.line, 9);
+}
+
+#[test]
+fn invalid_signature_is_rejected() {
+    let error = VpdParser::new().parse_text("Vocaloid Motion Data 0002\n").unwrap_err();
+    assert!(matches!(error.kind, VpdErrorKind::InvalidSignature(_)));
+    assert_eq!(error.line, 1);
+}
+
+#[test]
+fn names_outside_shift_jis_can_not_be_written() {
+    let mut pose = pose();
+    pose.morphs[0].morph_name = String::from("笑顔😀");
+    let mut data = Vec::new();
+    let error = pose.write(&mut data).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(data.is_empty());
+}
@@ -0,0 +1,154 @@
+use cgmath::{Vector3, Matrix, Matrix3, InnerSpace};
+
+use crate::{rigid_body::RigidBody, shape::Shape};
+
+/// Overlap of two bodies, `normal` points from b towards a
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub normal: Vector3<f32>,
+    pub depth: f32,
+    // world space points on the surface of each body
+    pub point_a: Vector3<f32>,
+    pub point_b: Vector3<f32>,
+}
+
+// every shape is a point, segment or box swept by a sphere of `radius`
+enum Core {
+    Point(Vector3<f32>),
+    Segment(Vector3<f32>, Vector3<f32>),
+    Box(Vector3<f32>, Matrix3<f32>, Vector3<f32>),
+}
+
+impl Core {
+    fn of(body: &RigidBody) -> (Core, f32) {
+        let rotation = body.rotation_matrix();
+        match body.shape {
+            Shape::Sphere { radius } => (Core::Point(body.position), radius),
+            Shape::Capsule { radius, height } => {
+                let half = rotation.y * (0.5 * height);
+                (Core::Segment(body.position - half, body.position + half), radius)
+            },
+            Shape::Box { half_extents } => (Core::Box(body.position, rotation, half_extents), 0.0),
+        }
+    }
+
+    fn closest_point(&self, q: Vector3<f32>) -> Vector3<f32> {
+        match *self {
+            Core::Point(p) => p,
+            Core::Segment(p0, p1) => {
+                let d = p1 - p0;
+                let length2 = d.magnitude2();
+                if length2 < 1e-12 {
+                    return p0;
+                }
+                p0 + d * ((q - p0).dot(d) / length2).clamp(0.0, 1.0)
+            },
+            Core::Box(center, rotation, half) => {
+                let local = rotation.transpose() * (q - center);
+                let clamped = Vector3::new(
+                    local.x.clamp(-half.x, half.x),
+                    local.y.clamp(-half.y, half.y),
+                    local.z.clamp(-half.z, half.z),
+                );
+                center + rotation * clamped
+            },
+        }
+    }
+}
+
+// closest points of two segments, from Real-Time Collision Detection 5.1.9
+fn closest_segment_points(p1: Vector3<f32>, q1: Vector3<f32>, p2: Vector3<f32>, q2: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.magnitude2(), d2.magnitude2(), d2.dot(r));
+    let epsilon = 1e-12;
+
+    let (s, t) = if a <= epsilon && e <= epsilon {
+        (0.0, 0.0)
+    } else if a <= epsilon {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e <= epsilon {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let mut s = if denom > epsilon { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+fn closest_points(a: &Core, b: &Core, center_a: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    match (a, b) {
+        (Core::Point(p), _) => (*p, b.closest_point(*p)),
+        (_, Core::Point(q)) => (a.closest_point(*q), *q),
+        (Core::Segment(p1, q1), Core::Segment(p2, q2)) => closest_segment_points(*p1, *q1, *p2, *q2),
+        // alternating projection onto two convex sets converges to their closest pair
+        _ => {
+            let mut pa = center_a;
+            let mut pb = b.closest_point(pa);
+            for _ in 0..16 {
+                let next = a.closest_point(pb);
+                let moved = (next - pa).magnitude2();
+                pa = next;
+                pb = b.closest_point(pa);
+                if moved < 1e-12 {
+                    break;
+                }
+            }
+            (pa, pb)
+        },
+    }
+}
+
+pub fn collide(a: &RigidBody, b: &RigidBody) -> Option<Contact> {
+    let reach = a.shape.bounding_radius() + b.shape.bounding_radius();
+    if (a.position - b.position).magnitude2() > reach * reach {
+        return None;
+    }
+
+    let (core_a, radius_a) = Core::of(a);
+    let (core_b, radius_b) = Core::of(b);
+    let (pa, pb) = closest_points(&core_a, &core_b, a.position);
+    let distance = (pa - pb).magnitude();
+
+    if distance > 1e-5 {
+        let depth = radius_a + radius_b - distance;
+        if depth <= 0.0 {
+            return None;
+        }
+        let normal = (pa - pb) / distance;
+        return Some(Contact {
+            normal,
+            depth,
+            point_a: pa - normal * radius_a,
+            point_b: pb + normal * radius_b,
+        });
+    }
+
+    // the cores overlap, separate along the line between the centers
+    let offset = a.position - b.position;
+    let normal = if offset.magnitude2() > 1e-10 { offset.normalize() } else { Vector3::unit_y() };
+    let depth = a.shape.support_extent(&a.rotation_matrix(), normal)
+        + b.shape.support_extent(&b.rotation_matrix(), normal)
+        - offset.dot(normal);
+    if depth <= 0.0 {
+        return None;
+    }
+    Some(Contact {
+        normal,
+        depth,
+        point_a: pa,
+        point_b: pb,
+    })
+}
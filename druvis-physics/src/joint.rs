@@ -0,0 +1,47 @@
+use cgmath::{Vector3, Quaternion, Rotation};
+
+use crate::rigid_body::RigidBody;
+
+/// 6DOF joint with springs, the relative transform of the joint frame on b seen from the one on a
+/// is limited per axis. A lower limit above the upper one leaves that axis free.
+pub struct SpringJoint {
+    pub body_a: usize,
+    pub body_b: usize,
+    // the joint frame in the local space of each body
+    pub frame_a: (Vector3<f32>, Quaternion<f32>),
+    pub frame_b: (Vector3<f32>, Quaternion<f32>),
+
+    pub linear_lower: Vector3<f32>,
+    pub linear_upper: Vector3<f32>,
+    // euler angles in radians
+    pub angular_lower: Vector3<f32>,
+    pub angular_upper: Vector3<f32>,
+
+    // springs pull each axis back to zero, 0 disables the spring
+    pub linear_stiffness: Vector3<f32>,
+    pub angular_stiffness: Vector3<f32>,
+}
+
+fn local_frame(body: &RigidBody, position: Vector3<f32>, rotation: Quaternion<f32>) -> (Vector3<f32>, Quaternion<f32>) {
+    let inverse = body.rotation.invert();
+    (inverse.rotate_vector(position - body.position), inverse * rotation)
+}
+
+impl SpringJoint {
+    /// Joint at the world space `position` and `rotation`, every axis locked and without springs
+    pub fn new(body_a: usize, body_b: usize, bodies: &[RigidBody], position: Vector3<f32>, rotation: Quaternion<f32>) -> Self {
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+        Self {
+            body_a,
+            body_b,
+            frame_a: local_frame(&bodies[body_a], position, rotation),
+            frame_b: local_frame(&bodies[body_b], position, rotation),
+            linear_lower: zero,
+            linear_upper: zero,
+            angular_lower: zero,
+            angular_upper: zero,
+            linear_stiffness: zero,
+            angular_stiffness: zero,
+        }
+    }
+}
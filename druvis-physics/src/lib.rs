@@ -0,0 +1,10 @@
+pub mod shape;
+pub mod rigid_body;
+pub mod joint;
+pub mod collision;
+pub mod world;
+
+pub use shape::Shape;
+pub use rigid_body::RigidBody;
+pub use joint::SpringJoint;
+pub use world::PhysicsWorld;
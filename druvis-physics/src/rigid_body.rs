@@ -0,0 +1,126 @@
+use cgmath::{Vector3, Quaternion, Matrix, Matrix3, Matrix4, Zero, One, InnerSpace, SquareMatrix};
+
+use crate::shape::Shape;
+
+pub struct RigidBody {
+    pub shape: Shape,
+    pub mass: f32,
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub linear_velocity: Vector3<f32>,
+    pub angular_velocity: Vector3<f32>,
+
+    // fraction of velocity lost per second
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub restitution: f32,
+    pub friction: f32,
+
+    // collision group in 0..16
+    pub group: u8,
+    // bit n set means this body collides with group n
+    pub collision_mask: u16,
+    // moved by `PhysicsWorld::set_kinematic_target` instead of being simulated
+    pub kinematic: bool,
+
+    pub(crate) previous_position: Vector3<f32>,
+    pub(crate) previous_rotation: Quaternion<f32>,
+    pub(crate) kinematic_start: (Vector3<f32>, Quaternion<f32>),
+    pub(crate) kinematic_target: Option<(Vector3<f32>, Quaternion<f32>)>,
+}
+
+impl RigidBody {
+    pub fn new(shape: Shape, mass: f32, position: Vector3<f32>, rotation: Quaternion<f32>) -> Self {
+        Self {
+            shape,
+            mass,
+            position,
+            rotation,
+            linear_velocity: Vector3::zero(),
+            angular_velocity: Vector3::zero(),
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            restitution: 0.0,
+            friction: 0.5,
+            group: 0,
+            collision_mask: 0xffff,
+            kinematic: false,
+            previous_position: position,
+            previous_rotation: rotation,
+            kinematic_start: (position, rotation),
+            kinematic_target: None,
+        }
+    }
+
+    /// Zero for kinematic bodies and bodies without mass, which nothing can push
+    pub fn inverse_mass(&self) -> f32 {
+        if self.kinematic || self.mass <= 0.0 {
+            0.0
+        } else {
+            1.0 / self.mass
+        }
+    }
+
+    pub fn rotation_matrix(&self) -> Matrix3<f32> {
+        Matrix3::from(self.rotation)
+    }
+
+    pub(crate) fn inverse_inertia(&self) -> Matrix3<f32> {
+        let inverse_mass = self.inverse_mass();
+        if inverse_mass == 0.0 {
+            return Matrix3::zero();
+        }
+        let inertia = self.shape.principal_inertia(self.mass);
+        let inverse = |i: f32| if i > 0.0 { 1.0 / i } else { 0.0 };
+        let diagonal = Matrix3::from_diagonal(Vector3::new(inverse(inertia.x), inverse(inertia.y), inverse(inertia.z)));
+        let rotation = self.rotation_matrix();
+        rotation * diagonal * rotation.transpose()
+    }
+
+    // inverse mass felt by a push along `n` at `r` from the center
+    pub(crate) fn generalized_inverse_mass(&self, r: Vector3<f32>, n: Vector3<f32>) -> f32 {
+        let rn = r.cross(n);
+        self.inverse_mass() + rn.dot(self.inverse_inertia() * rn)
+    }
+
+    pub(crate) fn rotate_by(&mut self, rotation: Vector3<f32>) {
+        let delta = Quaternion::from_sv(0.0, rotation) * self.rotation * 0.5;
+        self.rotation = (self.rotation + delta).normalize();
+    }
+
+    pub(crate) fn velocity_at(&self, r: Vector3<f32>) -> Vector3<f32> {
+        self.linear_velocity + self.angular_velocity.cross(r)
+    }
+
+    /// Pushes the body so that the point at `r` from the center changes velocity along `impulse`
+    pub(crate) fn apply_impulse(&mut self, r: Vector3<f32>, impulse: Vector3<f32>) {
+        self.linear_velocity += impulse * self.inverse_mass();
+        self.angular_velocity += self.inverse_inertia() * r.cross(impulse);
+    }
+
+    pub fn collides_with(&self, other: &RigidBody) -> bool {
+        self.collision_mask & (1 << (other.group & 15)) != 0 && other.collision_mask & (1 << (self.group & 15)) != 0
+    }
+
+    pub fn transform(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position) * Matrix4::from(self.rotation)
+    }
+
+    /// Moves the body without giving it any velocity
+    pub fn teleport(&mut self, position: Vector3<f32>, rotation: Quaternion<f32>) {
+        self.position = position;
+        self.rotation = rotation;
+        self.previous_position = position;
+        self.previous_rotation = rotation;
+        self.kinematic_start = (position, rotation);
+        self.kinematic_target = None;
+        self.linear_velocity = Vector3::zero();
+        self.angular_velocity = Vector3::zero();
+    }
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self::new(Shape::Sphere { radius: 1.0 }, 1.0, Vector3::zero(), Quaternion::one())
+    }
+}
@@ -0,0 +1,52 @@
+use cgmath::{Vector3, Matrix3, InnerSpace};
+
+/// Collision shape in the body's local space, capsules run along the local y axis
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Sphere { radius: f32 },
+    Box { half_extents: Vector3<f32> },
+    // `height` is the length of the cylinder between the two caps
+    Capsule { radius: f32, height: f32 },
+}
+
+impl Shape {
+    /// Diagonal of the inertia tensor of a solid shape of `mass`
+    pub fn principal_inertia(&self, mass: f32) -> Vector3<f32> {
+        match *self {
+            Shape::Sphere { radius } => {
+                let i = 0.4 * mass * radius * radius;
+                Vector3::new(i, i, i)
+            },
+            Shape::Box { half_extents: h } => {
+                let (x, y, z) = (4.0 * h.x * h.x, 4.0 * h.y * h.y, 4.0 * h.z * h.z);
+                Vector3::new(y + z, x + z, x + y) * (mass / 12.0)
+            },
+            // as a cylinder that includes the caps
+            Shape::Capsule { radius, height } => {
+                let length = height + 2.0 * radius;
+                let side = mass * (3.0 * radius * radius + length * length) / 12.0;
+                Vector3::new(side, 0.5 * mass * radius * radius, side)
+            },
+        }
+    }
+
+    /// Radius of a sphere around the body position that contains the shape
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            Shape::Sphere { radius } => radius,
+            Shape::Box { half_extents } => half_extents.magnitude(),
+            Shape::Capsule { radius, height } => radius + 0.5 * height,
+        }
+    }
+
+    /// Half width of the shape along the unit direction `n`, with `rotation` the body orientation
+    pub fn support_extent(&self, rotation: &Matrix3<f32>, n: Vector3<f32>) -> f32 {
+        match *self {
+            Shape::Sphere { radius } => radius,
+            Shape::Box { half_extents: h } => {
+                h.x * rotation.x.dot(n).abs() + h.y * rotation.y.dot(n).abs() + h.z * rotation.z.dot(n).abs()
+            },
+            Shape::Capsule { radius, height } => radius + 0.5 * height * rotation.y.dot(n).abs(),
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::HashSet;
+
+use cgmath::{Vector3, Quaternion, InnerSpace, Rotation, Euler, Rad, Zero, VectorSpace};
+
+use crate::{rigid_body::RigidBody, joint::SpringJoint, collision::{self, Contact}};
+
+// a contact found during the position solve, kept for the velocity solve
+struct ContactState {
+    a: usize,
+    b: usize,
+    contact: Contact,
+    r_a: Vector3<f32>,
+    r_b: Vector3<f32>,
+    lambda: f32,
+    // along the normal, before the contact was resolved
+    normal_velocity: f32,
+}
+
+fn pair_mut(bodies: &mut [RigidBody], a: usize, b: usize) -> (&mut RigidBody, &mut RigidBody) {
+    if a < b {
+        let (left, right) = bodies.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = bodies.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+fn rotation_vector(q: Quaternion<f32>) -> Vector3<f32> {
+    let q = if q.s < 0.0 { -q } else { q };
+    let sin_half = q.v.magnitude();
+    if sin_half < 1e-9 {
+        return q.v * 2.0;
+    }
+    q.v / sin_half * (2.0 * sin_half.atan2(q.s))
+}
+
+fn euler_angles(q: Quaternion<f32>) -> Vector3<f32> {
+    let euler = Euler::from(q);
+    Vector3::new(euler.x.0, euler.y.0, euler.z.0)
+}
+
+// axes with lower > upper are free
+fn clamp_axes(values: Vector3<f32>, lower: Vector3<f32>, upper: Vector3<f32>) -> Vector3<f32> {
+    let mut result = values;
+    for i in 0..3 {
+        if lower[i] <= upper[i] {
+            result[i] = values[i].clamp(lower[i], upper[i]);
+        }
+    }
+    result
+}
+
+// moves the points at `r_a` and `r_b` closer by `correction`, a moves along it and b against it
+fn apply_positional(
+    a: &mut RigidBody,
+    b: &mut RigidBody,
+    correction: Vector3<f32>,
+    r_a: Vector3<f32>,
+    r_b: Vector3<f32>,
+    compliance: f32,
+    h: f32
+) -> f32 {
+    let c = correction.magnitude();
+    if c < 1e-9 {
+        return 0.0;
+    }
+    let n = correction / c;
+    let w = a.generalized_inverse_mass(r_a, n) + b.generalized_inverse_mass(r_b, n);
+    if w <= 0.0 {
+        return 0.0;
+    }
+
+    let lambda = c / (w + compliance / (h * h));
+    let p = n * lambda;
+    a.position += p * a.inverse_mass();
+    a.rotate_by(a.inverse_inertia() * r_a.cross(p));
+    b.position -= p * b.inverse_mass();
+    b.rotate_by(-(b.inverse_inertia() * r_b.cross(p)));
+    lambda
+}
+
+// rotates a by `correction` and b against it, split by their inertia
+fn apply_angular(a: &mut RigidBody, b: &mut RigidBody, correction: Vector3<f32>, compliance: f32, h: f32) {
+    let theta = correction.magnitude();
+    if theta < 1e-9 {
+        return;
+    }
+    let n = correction / theta;
+    let (inverse_a, inverse_b) = (a.inverse_inertia(), b.inverse_inertia());
+    let w = n.dot(inverse_a * n) + n.dot(inverse_b * n);
+    if w <= 0.0 {
+        return;
+    }
+
+    let p = n * (theta / (w + compliance / (h * h)));
+    a.rotate_by(inverse_a * p);
+    b.rotate_by(-(inverse_b * p));
+}
+
+fn compliance(stiffness: f32) -> f32 {
+    1.0 / stiffness
+}
+
+/// Fixed step rigid body simulation using XPBD, see "Detailed Rigid Body Simulation
+/// with Extended Position Based Dynamics" by Müller et al.
+pub struct PhysicsWorld {
+    pub bodies: Vec<RigidBody>,
+    pub joints: Vec<SpringJoint>,
+    pub gravity: Vector3<f32>,
+    pub fixed_time_step: f32,
+    // steps beyond this are dropped, so a long frame can not stall the simulation
+    pub max_steps: u32,
+    pub substeps: u32,
+    // joint passes per substep, long chains of light bodies stretch with a single one
+    pub iterations: u32,
+    // skip collisions between bodies connected by a joint
+    pub disable_linked_collisions: bool,
+    accumulator: f32,
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        Self {
+            bodies: Vec::new(),
+            joints: Vec::new(),
+            // MMD models are about 10 units per meter
+            gravity: Vector3::new(0.0, -98.0, 0.0),
+            fixed_time_step: 1.0 / 60.0,
+            max_steps: 5,
+            substeps: 8,
+            iterations: 2,
+            disable_linked_collisions: true,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn add_body(&mut self, body: RigidBody) -> usize {
+        self.bodies.push(body);
+        self.bodies.len() - 1
+    }
+
+    /// Joints between a body and itself or missing bodies are ignored
+    pub fn add_joint(&mut self, joint: SpringJoint) -> usize {
+        self.joints.push(joint);
+        self.joints.len() - 1
+    }
+
+    /// Moves a kinematic body to the pose over the next steps
+    pub fn set_kinematic_target(&mut self, index: usize, position: Vector3<f32>, rotation: Quaternion<f32>) {
+        self.bodies[index].kinematic_target = Some((position, rotation));
+    }
+
+    /// Advances by `dt` seconds in whole fixed steps, the remainder carries over to the next call.
+    /// Returns the number of steps taken
+    pub fn step(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.max(0.0);
+        let mut steps = (self.accumulator / self.fixed_time_step) as u32;
+        if steps > self.max_steps {
+            steps = self.max_steps;
+            self.accumulator = 0.0;
+        } else {
+            self.accumulator -= steps as f32 * self.fixed_time_step;
+        }
+
+        self.simulate(steps);
+        steps
+    }
+
+    /// Advances by exactly one fixed step
+    pub fn step_fixed(&mut self) {
+        self.simulate(1);
+    }
+
+    fn simulate(&mut self, steps: u32) {
+        if steps == 0 {
+            return;
+        }
+
+        for body in self.bodies.iter_mut() {
+            body.kinematic_start = (body.position, body.rotation);
+        }
+        let linked = self.linked_pairs();
+
+        let substeps = self.substeps.max(1);
+        let h = self.fixed_time_step / substeps as f32;
+        let total = steps * substeps;
+        for i in 0..total {
+            self.substep(h, (i + 1) as f32 / total as f32, &linked);
+        }
+
+        for body in self.bodies.iter_mut() {
+            body.kinematic_target = None;
+        }
+    }
+
+    fn linked_pairs(&self) -> HashSet<(usize, usize)> {
+        if !self.disable_linked_collisions {
+            return HashSet::new();
+        }
+        self.joints.iter()
+            .map(|j| (j.body_a.min(j.body_b), j.body_a.max(j.body_b)))
+            .collect()
+    }
+
+    fn integrate(&mut self, h: f32, alpha: f32) {
+        let gravity = self.gravity;
+        for body in self.bodies.iter_mut() {
+            body.previous_position = body.position;
+            body.previous_rotation = body.rotation;
+
+            if body.kinematic {
+                if let Some((position, rotation)) = body.kinematic_target {
+                    let (start_position, start_rotation) = body.kinematic_start;
+                    body.position = start_position.lerp(position, alpha);
+                    body.rotation = start_rotation.slerp(rotation, alpha);
+                }
+                continue;
+            }
+            if body.inverse_mass() == 0.0 {
+                continue;
+            }
+
+            body.linear_velocity += gravity * h;
+            body.linear_velocity *= (1.0 - body.linear_damping).clamp(0.0, 1.0).powf(h);
+            body.angular_velocity *= (1.0 - body.angular_damping).clamp(0.0, 1.0).powf(h);
+            body.position += body.linear_velocity * h;
+            let rotation = body.angular_velocity * h;
+            body.rotate_by(rotation);
+        }
+    }
+
+    fn solve_joint(&mut self, index: usize, h: f32) {
+        let joint = &self.joints[index];
+        let body_count = self.bodies.len();
+        if joint.body_a == joint.body_b || joint.body_a >= body_count || joint.body_b >= body_count {
+            return;
+        }
+        let (a, b) = pair_mut(&mut self.bodies, joint.body_a, joint.body_b);
+        if a.inverse_mass() == 0.0 && b.inverse_mass() == 0.0 {
+            return;
+        }
+
+        // relative rotation of frame b in frame a
+        let relative = |a: &RigidBody, b: &RigidBody| {
+            let frame_a = a.rotation * joint.frame_a.1;
+            (frame_a, frame_a.invert() * (b.rotation * joint.frame_b.1))
+        };
+        // rotates b so that the relative rotation becomes `target`
+        let rotate_to = |a: &mut RigidBody, b: &mut RigidBody, target: Vector3<f32>, compliance: f32| {
+            let (frame_a, relative) = relative(a, b);
+            let target = Quaternion::from(Euler::new(Rad(target.x), Rad(target.y), Rad(target.z)));
+            let world = frame_a * target * relative.invert() * frame_a.invert();
+            apply_angular(a, b, -rotation_vector(world), compliance, h);
+        };
+
+        let angles = euler_angles(relative(a, b).1);
+        let limited = clamp_axes(angles, joint.angular_lower, joint.angular_upper);
+        if (limited - angles).magnitude2() > 1e-12 {
+            rotate_to(a, b, limited, 0.0);
+        }
+        for axis in 0..3 {
+            if joint.angular_stiffness[axis] <= 0.0 {
+                continue;
+            }
+            let mut target = euler_angles(relative(a, b).1);
+            target[axis] = 0.0;
+            let target = clamp_axes(target, joint.angular_lower, joint.angular_upper);
+            rotate_to(a, b, target, compliance(joint.angular_stiffness[axis]));
+        }
+
+        // offset of the anchor on b from the anchor on a, in frame a
+        let offset = |a: &RigidBody, b: &RigidBody| {
+            let r_a = a.rotation.rotate_vector(joint.frame_a.0);
+            let r_b = b.rotation.rotate_vector(joint.frame_b.0);
+            let frame_a = a.rotation * joint.frame_a.1;
+            let local = frame_a.invert().rotate_vector((b.position + r_b) - (a.position + r_a));
+            (r_a, r_b, frame_a, local)
+        };
+
+        let (r_a, r_b, frame_a, local) = offset(a, b);
+        let limited = clamp_axes(local, joint.linear_lower, joint.linear_upper);
+        if (local - limited).magnitude2() > 1e-12 {
+            apply_positional(a, b, frame_a.rotate_vector(local - limited), r_a, r_b, 0.0, h);
+        }
+        for axis in 0..3 {
+            if joint.linear_stiffness[axis] <= 0.0 {
+                continue;
+            }
+            let (r_a, r_b, frame_a, local) = offset(a, b);
+            let mut target = local;
+            target[axis] = 0.0;
+            let target = clamp_axes(target, joint.linear_lower, joint.linear_upper);
+            apply_positional(a, b, frame_a.rotate_vector(local - target), r_a, r_b, compliance(joint.linear_stiffness[axis]), h);
+        }
+    }
+
+    fn solve_contacts(&mut self, h: f32, linked: &HashSet<(usize, usize)>) -> Vec<ContactState> {
+        let mut contacts = Vec::new();
+        for i in 0..self.bodies.len() {
+            for j in (i + 1)..self.bodies.len() {
+                let (a, b) = (&self.bodies[i], &self.bodies[j]);
+                if a.inverse_mass() == 0.0 && b.inverse_mass() == 0.0 {
+                    continue;
+                }
+                if !a.collides_with(b) || linked.contains(&(i, j)) {
+                    continue;
+                }
+                let contact = match collision::collide(a, b) {
+                    Some(contact) => contact,
+                    None => continue,
+                };
+
+                let r_a = contact.point_a - a.position;
+                let r_b = contact.point_b - b.position;
+                let normal_velocity = contact.normal.dot(a.velocity_at(r_a) - b.velocity_at(r_b));
+                let (a, b) = pair_mut(&mut self.bodies, i, j);
+                let lambda = apply_positional(a, b, contact.normal * contact.depth, r_a, r_b, 0.0, h);
+                contacts.push(ContactState {
+                    a: i,
+                    b: j,
+                    contact,
+                    r_a,
+                    r_b,
+                    lambda,
+                    normal_velocity,
+                });
+            }
+        }
+        contacts
+    }
+
+    fn update_velocities(&mut self, h: f32) {
+        for body in self.bodies.iter_mut() {
+            if !body.kinematic && body.inverse_mass() == 0.0 {
+                continue;
+            }
+            body.linear_velocity = (body.position - body.previous_position) / h;
+            let delta = body.rotation * body.previous_rotation.invert();
+            let angular = delta.v * (2.0 / h);
+            body.angular_velocity = if delta.s < 0.0 { -angular } else { angular };
+        }
+    }
+
+    // friction and restitution, which positions alone can not express
+    fn solve_contact_velocities(&mut self, contacts: &[ContactState], h: f32) {
+        let rest_threshold = 2.0 * self.gravity.magnitude() * h;
+        for state in contacts.iter() {
+            let (a, b) = pair_mut(&mut self.bodies, state.a, state.b);
+            let n = state.contact.normal;
+            let relative = a.velocity_at(state.r_a) - b.velocity_at(state.r_b);
+            let normal_velocity = n.dot(relative);
+            let tangent = relative - n * normal_velocity;
+
+            let mut delta = Vector3::zero();
+            let tangent_speed = tangent.magnitude();
+            if tangent_speed > 1e-6 {
+                let friction = a.friction * b.friction;
+                delta -= tangent / tangent_speed * (friction * state.lambda / h).min(tangent_speed);
+            }
+            let restitution = if state.normal_velocity.abs() > rest_threshold { a.restitution * b.restitution } else { 0.0 };
+            let target = (-restitution * state.normal_velocity).max(0.0);
+            if normal_velocity < target {
+                delta += n * (target - normal_velocity);
+            }
+
+            let speed = delta.magnitude();
+            if speed < 1e-9 {
+                continue;
+            }
+            let direction = delta / speed;
+            let w = a.generalized_inverse_mass(state.r_a, direction) + b.generalized_inverse_mass(state.r_b, direction);
+            if w <= 0.0 {
+                continue;
+            }
+            let impulse = delta / w;
+            a.apply_impulse(state.r_a, impulse);
+            b.apply_impulse(state.r_b, -impulse);
+        }
+    }
+
+    fn substep(&mut self, h: f32, alpha: f32, linked: &HashSet<(usize, usize)>) {
+        self.integrate(h, alpha);
+        for _ in 0..self.iterations.max(1) {
+            for i in 0..self.joints.len() {
+                self.solve_joint(i, h);
+            }
+        }
+        let contacts = self.solve_contacts(h, linked);
+        self.update_velocities(h);
+        self.solve_contact_velocities(&contacts, h);
+    }
+}
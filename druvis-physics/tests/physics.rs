@@ -0,0 +1,231 @@
+use cgmath::{Vector3, Quaternion, Rotation, Rotation3, Rad, Deg, InnerSpace, One, Zero};
+use druvis_physics::{PhysicsWorld, RigidBody, Shape, SpringJoint, collision};
+
+fn sphere(radius: f32, mass: f32, position: Vector3<f32>) -> RigidBody {
+    RigidBody::new(Shape::Sphere { radius }, mass, position, Quaternion::one())
+}
+
+fn floor() -> RigidBody {
+    let mut body = RigidBody::new(Shape::Box { half_extents: Vector3::new(50.0, 1.0, 50.0) }, 0.0, Vector3::new(0.0, -1.0, 0.0), Quaternion::one());
+    body.kinematic = true;
+    body.group = 1;
+    body
+}
+
+fn run(world: &mut PhysicsWorld, steps: u32) {
+    for _ in 0..steps {
+        world.step_fixed();
+    }
+}
+
+// hair like chain hanging from a kinematic root, every joint limited to 30 degrees with springs
+fn chain_world() -> PhysicsWorld {
+    let mut world = PhysicsWorld::new();
+    let mut root = sphere(0.5, 0.0, Vector3::new(0.0, 20.0, 0.0));
+    root.kinematic = true;
+    world.add_body(root);
+    for i in 1..6 {
+        let mut body = RigidBody::new(
+            Shape::Capsule { radius: 0.3, height: 1.0 },
+            1.0,
+            Vector3::new(i as f32 * 1.5, 20.0, 0.0),
+            Quaternion::from_angle_z(Deg(90.0)),
+        );
+        body.linear_damping = 0.5;
+        body.angular_damping = 0.5;
+        world.add_body(body);
+    }
+    world.add_body(floor());
+
+    for i in 1..6 {
+        let mut joint = SpringJoint::new(i - 1, i, &world.bodies, Vector3::new(i as f32 * 1.5 - 0.75, 20.0, 0.0), Quaternion::one());
+        let limit = 30.0_f32.to_radians();
+        joint.angular_lower = Vector3::new(-limit, -limit, -limit);
+        joint.angular_upper = Vector3::new(limit, limit, limit);
+        joint.angular_stiffness = Vector3::new(10.0, 10.0, 10.0);
+        world.add_joint(joint);
+    }
+    world
+}
+
+#[test]
+fn free_fall_follows_gravity() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(sphere(1.0, 1.0, Vector3::zero()));
+    run(&mut world, 60);
+
+    let body = &world.bodies[0];
+    // 0.5 * g * t^2 after one second, up to the integration error
+    assert!((body.position.y - -49.0).abs() < 0.5, "{:?}", body.position);
+    assert!((body.linear_velocity.y - -98.0).abs() < 0.5, "{:?}", body.linear_velocity);
+    assert!(body.position.x.abs() < 1e-6 && body.position.z.abs() < 1e-6);
+}
+
+#[test]
+fn simulation_is_deterministic() {
+    let mut first = chain_world();
+    let mut second = chain_world();
+    run(&mut first, 120);
+    run(&mut second, 120);
+
+    for (a, b) in first.bodies.iter().zip(second.bodies.iter()) {
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.rotation, b.rotation);
+        assert_eq!(a.linear_velocity, b.linear_velocity);
+    }
+}
+
+#[test]
+fn variable_frame_times_take_whole_fixed_steps() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(sphere(1.0, 1.0, Vector3::zero()));
+
+    assert_eq!(world.step(0.01), 0);
+    assert_eq!(world.step(0.01), 1);
+    assert_eq!(world.step(1.0 / 30.0), 2);
+    // a long hitch is capped instead of stalling
+    assert_eq!(world.step(1.0), world.max_steps);
+    assert_eq!(world.step(0.0), 0);
+}
+
+#[test]
+fn sphere_rests_on_the_floor() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(sphere(1.0, 1.0, Vector3::new(0.0, 5.0, 0.0)));
+    world.add_body(floor());
+    run(&mut world, 180);
+
+    let body = &world.bodies[0];
+    assert!((body.position.y - 1.0).abs() < 0.05, "{:?}", body.position);
+    assert!(body.linear_velocity.magnitude() < 0.5, "{:?}", body.linear_velocity);
+}
+
+#[test]
+fn collision_masks_filter_contacts() {
+    let mut world = PhysicsWorld::new();
+    let mut body = sphere(1.0, 1.0, Vector3::new(0.0, 5.0, 0.0));
+    // everything but the floor's group
+    body.collision_mask = !(1 << 1);
+    world.add_body(body);
+    world.add_body(floor());
+    run(&mut world, 60);
+
+    assert!(world.bodies[0].position.y < -10.0, "{:?}", world.bodies[0].position);
+}
+
+#[test]
+fn capsules_and_boxes_collide() {
+    let capsule = RigidBody::new(Shape::Capsule { radius: 0.5, height: 2.0 }, 1.0, Vector3::new(0.0, 0.0, 0.0), Quaternion::one());
+    // lying along x and passing in front of the standing one
+    let lying = RigidBody::new(Shape::Capsule { radius: 0.5, height: 2.0 }, 1.0, Vector3::new(0.0, 0.5, 0.8), Quaternion::from_angle_z(Deg(90.0)));
+    let contact = collision::collide(&lying, &capsule).unwrap();
+    assert!((contact.depth - 0.2).abs() < 1e-4, "{:?}", contact);
+    assert!((contact.normal - Vector3::unit_z()).magnitude() < 1e-4, "{:?}", contact);
+
+    let block = RigidBody::new(Shape::Box { half_extents: Vector3::new(1.0, 1.0, 1.0) }, 1.0, Vector3::zero(), Quaternion::from_angle_y(Deg(45.0)));
+    let ball = sphere(0.5, 1.0, Vector3::new(1.8, 0.0, 0.0));
+    // the rotated box reaches sqrt(2) along x
+    let contact = collision::collide(&ball, &block).unwrap();
+    assert!((contact.depth - (2.0_f32.sqrt() + 0.5 - 1.8)).abs() < 1e-3, "{:?}", contact);
+
+    let far = sphere(0.5, 1.0, Vector3::new(3.0, 0.0, 0.0));
+    assert!(collision::collide(&far, &block).is_none());
+
+    let other = RigidBody::new(Shape::Box { half_extents: Vector3::new(1.0, 1.0, 1.0) }, 1.0, Vector3::new(1.5, 0.0, 0.0), Quaternion::one());
+    let plain = RigidBody::new(Shape::Box { half_extents: Vector3::new(1.0, 1.0, 1.0) }, 1.0, Vector3::zero(), Quaternion::one());
+    let contact = collision::collide(&other, &plain).unwrap();
+    assert!((contact.depth - 0.5).abs() < 1e-4, "{:?}", contact);
+}
+
+#[test]
+fn locked_joint_keeps_its_anchor() {
+    let mut world = PhysicsWorld::new();
+    let mut anchor = sphere(0.2, 0.0, Vector3::new(0.0, 10.0, 0.0));
+    anchor.kinematic = true;
+    world.add_body(anchor);
+    world.add_body(sphere(0.5, 1.0, Vector3::new(3.0, 10.0, 0.0)));
+
+    // a ball joint, position locked and rotation free
+    let mut joint = SpringJoint::new(0, 1, &world.bodies, Vector3::new(0.0, 10.0, 0.0), Quaternion::one());
+    joint.angular_lower = Vector3::new(1.0, 1.0, 1.0);
+    joint.angular_upper = Vector3::new(-1.0, -1.0, -1.0);
+    world.add_joint(joint);
+    run(&mut world, 90);
+
+    // swung down as a pendulum without stretching
+    let body = &world.bodies[1];
+    assert!(((body.position - Vector3::new(0.0, 10.0, 0.0)).magnitude() - 3.0).abs() < 0.05, "{:?}", body.position);
+    assert!(body.position.y < 9.0, "{:?}", body.position);
+    let anchor = body.position + body.rotation.rotate_vector(world.joints[0].frame_b.0);
+    assert!((anchor - Vector3::new(0.0, 10.0, 0.0)).magnitude() < 0.05, "{:?}", anchor);
+}
+
+#[test]
+fn angular_limits_and_springs_hold_the_chain() {
+    let mut world = chain_world();
+    run(&mut world, 240);
+
+    for joint in world.joints.iter() {
+        let (a, b) = (&world.bodies[joint.body_a], &world.bodies[joint.body_b]);
+        let relative = (a.rotation * joint.frame_a.1).invert() * (b.rotation * joint.frame_b.1);
+        let angle = 2.0 * relative.v.magnitude().atan2(relative.s.abs());
+        // three axes at their 30 degree limits can reach at most this much
+        assert!(angle < 55.0_f32.to_radians(), "{}", angle.to_degrees());
+
+        let anchor_a = a.position + a.rotation.rotate_vector(joint.frame_a.0);
+        let anchor_b = b.position + b.rotation.rotate_vector(joint.frame_b.0);
+        assert!((anchor_a - anchor_b).magnitude() < 0.05);
+    }
+    // the chain bends under gravity but can not hang straight down
+    let tip = world.bodies[5].position;
+    assert!(tip.y < 20.0 && tip.x > 3.0, "{:?}", tip);
+}
+
+#[test]
+fn linear_springs_pull_back_to_the_rest_position() {
+    let mut world = PhysicsWorld::new();
+    world.gravity = Vector3::zero();
+    let mut anchor = sphere(0.2, 0.0, Vector3::zero());
+    anchor.kinematic = true;
+    world.add_body(anchor);
+    let mut body = sphere(0.2, 1.0, Vector3::zero());
+    body.linear_damping = 0.9;
+    world.add_body(body);
+
+    let mut joint = SpringJoint::new(0, 1, &world.bodies, Vector3::zero(), Quaternion::one());
+    joint.linear_lower = Vector3::new(-5.0, 0.0, 0.0);
+    joint.linear_upper = Vector3::new(5.0, 0.0, 0.0);
+    joint.linear_stiffness = Vector3::new(50.0, 0.0, 0.0);
+    world.add_joint(joint);
+    world.disable_linked_collisions = true;
+
+    world.bodies[1].position = Vector3::new(10.0, 0.0, 0.0);
+    world.step_fixed();
+    // the limit applies right away
+    assert!(world.bodies[1].position.x <= 5.0 + 1e-3, "{:?}", world.bodies[1].position);
+
+    run(&mut world, 300);
+    assert!(world.bodies[1].position.magnitude() < 0.1, "{:?}", world.bodies[1].position);
+}
+
+#[test]
+fn kinematic_bodies_reach_their_target() {
+    let mut world = PhysicsWorld::new();
+    let mut body = sphere(1.0, 1.0, Vector3::zero());
+    body.kinematic = true;
+    world.add_body(body);
+
+    let rotation = Quaternion::from_angle_x(Rad(1.0));
+    world.set_kinematic_target(0, Vector3::new(1.0, 2.0, 3.0), rotation);
+    world.step_fixed();
+
+    let body = &world.bodies[0];
+    assert!((body.position - Vector3::new(1.0, 2.0, 3.0)).magnitude() < 1e-5);
+    assert!(body.rotation.dot(rotation).abs() > 1.0 - 1e-5);
+    // moving a kinematic body gives it the matching velocity for contacts
+    let velocity = Vector3::new(1.0, 2.0, 3.0) / world.fixed_time_step;
+    assert!((body.linear_velocity - velocity).magnitude() < 1e-2, "{:?}", body.linear_velocity);
+
+    world.step_fixed();
+    assert!((world.bodies[0].position - Vector3::new(1.0, 2.0, 3.0)).magnitude() < 1e-5);
+}
@@ -1,8 +1,8 @@
-use std::{rc::Rc, cell::RefCell, collections::HashMap, path::Path};
+use std::{rc::Rc, cell::RefCell, collections::HashMap};
 
 use cgmath::{Quaternion, Euler, Deg};
-use druvis_core::{instance::instance::DruvisInstance, render_pipeline::simple_render_pipeline::SimpleRenderPipeline, camera::camera::CameraController, scene::scene::DruvisScene, shader::shader_manager::ShaderManager, material::{material_manager::MaterialManager, material::DruvisMaterial}, game_object::{DruvisGameObject, DruvisComponent, components::MeshRendererData, game_object::DruvisGameObjectExt, TransformComponentData}, mesh::mesh::DruvisMesh, lighting::light::{Light, LightType}};
-use druvis_mmd_parser::PmxParser;
+use druvis_core::{instance::instance::DruvisInstance, render_pipeline::simple_render_pipeline::SimpleRenderPipeline, scene::scene::DruvisScene, shader::shader_manager::ShaderManager, material::{material_manager::MaterialManager, material::DruvisMaterial}, game_object::{DruvisGameObject, DruvisComponent, components::{MeshRendererData, SkeletonData, MorphControllerData, AnimatorData}, game_object::DruvisGameObjectExt, TransformComponentData}, mesh::mesh::DruvisMesh, lighting::light::{Light, LightType}};
+use druvis_mmd_parser::{PmxParser, VmdParser, VMDCameraController, VMDPlayer};
 use winit::{event_loop::{EventLoop, ControlFlow}, window::*, event::*};
 
 pub async fn run() {
@@ -20,6 +20,26 @@ pub async fn run() {
     );
     state.scene = Some(scene);
 
+    // a motion can follow the model on the command line, it drives the camera, the model or both
+    if let Some(motion_path) = std::env::args().nth(2) {
+        let motion = VmdParser::new().parse_file(&motion_path).unwrap();
+        if !motion.camera_keyframes.is_empty() {
+            state.camera_controller = Box::new(VMDCameraController::new(&motion));
+        }
+        if !motion.bone_keyframes.is_empty() || !motion.morph_keyframes.is_empty() {
+            for go in state.scene.as_ref().unwrap().objects.iter() {
+                let (skeleton, morphs) = match (go.get_component::<SkeletonData>(), go.get_component::<MorphControllerData>()) {
+                    (Some(skeleton), Some(morphs)) => (skeleton, morphs),
+                    _ => continue,
+                };
+                let mut player = VMDPlayer::new(&motion, &skeleton.borrow().data, &morphs.borrow().data);
+                player.looping = true;
+                player.play();
+                go.add_component(DruvisComponent::new(AnimatorData::new(Box::new(player))));
+            }
+        }
+    }
+
     let mut rp = SimpleRenderPipeline::new(&state.device, wgpu::Extent3d {
         width: state.size.width,
         height: state.size.height,
@@ -88,11 +108,12 @@ fn create_scene(
     shader_manager: &ShaderManager,
     material_manager: &MaterialManager,
 ) -> DruvisScene {
-    let model = include_bytes!("../../models/yoimiya/宵宫.pmx");
-    let model_path = Path::new("E:\\rust\\druvis\\models\\yoimiya");
+    // the model can be given on the command line, otherwise use the bundled one
+    let model_path = std::env::args().nth(1)
+        .unwrap_or_else(|| concat!(env!("CARGO_MANIFEST_DIR"), "/../models/yoimiya/宵宫.pmx").to_string());
     let parser = PmxParser::new();
 
-    let parse_result = parser.parse(model, model_path.to_path_buf()).unwrap();
+    let parse_result = parser.parse_file(&model_path).unwrap();
     // let mesh = parse_result.to_druvis_mesh(device);
 
     // let go = DruvisGameObject::new();
@@ -107,7 +128,11 @@ fn create_scene(
 
     // go.add_component(mesh_renderer);
 
-    let go = parse_result.create_game_object(device, queue, shader_manager, builtin_bind_group_layouts);
+    let mut texture_warnings = Vec::new();
+    let go = parse_result.create_game_object(device, queue, shader_manager, builtin_bind_group_layouts, &mut texture_warnings);
+    for warning in texture_warnings.iter() {
+        println!("warning: {}", warning);
+    }
 
     let mut scene = DruvisScene::new();
     scene.add_object(go);